@@ -0,0 +1,60 @@
+//! Conversions between `str` and the numeric types.
+
+use std::str::FromStr;
+
+use crate::value::{error, release, Float, Int, Str};
+
+/// `int(str)`, which allows whitespace around the number like Python.
+#[no_mangle]
+pub unsafe extern "C" fn rt_str_to_int(string: *mut Str) -> *mut Int {
+    let value = parse_str(string, "int");
+    Int::new(value)
+}
+
+/// `float(str)`, which allows whitespace around the number like Python.
+#[no_mangle]
+pub unsafe extern "C" fn rt_str_to_float(string: *mut Str) -> *mut Float {
+    let value = parse_str(string, "float");
+    Float::new(value)
+}
+
+/// `str(int)`.
+#[no_mangle]
+pub unsafe extern "C" fn rt_int_to_str(int: *mut Int) -> *mut Str {
+    let string = (*int).value.to_string();
+
+    release(int);
+    Str::new(string.as_bytes())
+}
+
+/// `str(float)`, which always shows six decimal places.
+#[no_mangle]
+pub unsafe extern "C" fn rt_float_to_str(float: *mut Float) -> *mut Str {
+    let value = (*float).value;
+    let string = if value.is_nan() {
+        "nan".to_string()
+    } else {
+        format!("{:.6}", value)
+    };
+
+    release(float);
+    Str::new(string.as_bytes())
+}
+
+unsafe fn parse_str<T: FromStr>(string: *mut Str, type_name: &str) -> T {
+    let bytes = Str::bytes(string);
+    let parsed = std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|text| text.trim_ascii().parse().ok());
+
+    let Some(value) = parsed else {
+        error(&format!(
+            "invalid literal for {}(): '{}'",
+            type_name,
+            String::from_utf8_lossy(bytes)
+        ));
+    };
+
+    release(string);
+    value
+}
@@ -0,0 +1,87 @@
+use std::io::{self, BufRead, Read, Write};
+
+use crate::value::{error, release, File, NoneValue, Str};
+
+const STDOUT_FD: i64 = 1;
+const STDERR_FD: i64 = 2;
+
+/// `file.write(str)`, which writes the string to the file as it is. `print` is built on top of
+/// this.
+#[no_mangle]
+pub unsafe extern "C" fn rt_file_write(file: *mut File, text: *mut Str) -> NoneValue {
+    let bytes = Str::bytes(text);
+
+    // Like Python, a closed stream is not an error worth aborting over
+    let _ = match (*file).fd {
+        STDOUT_FD => write_flushed(io::stdout().lock(), bytes),
+        STDERR_FD => write_flushed(io::stderr().lock(), bytes),
+        fd => error(&format!("cannot write to file descriptor {}", fd)),
+    };
+
+    release(file);
+    release(text);
+    std::ptr::null_mut()
+}
+
+/// `input()`, which reads a line from stdin without its newline. Like Python, reaching the end
+/// of the input before reading anything is an error.
+#[no_mangle]
+pub unsafe extern "C" fn rt_input() -> *mut Str {
+    match read_line() {
+        Some(line) => Str::new(&line),
+        None => error("EOF when reading a line"),
+    }
+}
+
+/// `input(str)`, which writes the prompt to stdout before reading the line.
+#[no_mangle]
+pub unsafe extern "C" fn rt_input_prompt(prompt: *mut Str) -> *mut Str {
+    let _ = write_flushed(io::stdout().lock(), Str::bytes(prompt));
+    release(prompt);
+
+    rt_input()
+}
+
+/// `read_line()`, which reads a line from stdin without its newline, or returns `None` at the
+/// end of the input.
+#[no_mangle]
+pub unsafe extern "C" fn rt_read_line() -> *mut Str {
+    match read_line() {
+        Some(line) => Str::new(&line),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// `read_all()`, which reads everything left in stdin.
+#[no_mangle]
+pub unsafe extern "C" fn rt_read_all() -> *mut Str {
+    let mut text = Vec::new();
+    if let Err(err) = io::stdin().lock().read_to_end(&mut text) {
+        error(&format!("cannot read stdin: {}", err));
+    }
+
+    Str::new(&text)
+}
+
+/// Reads the next line of stdin without its newline, returning `None` if the input has ended.
+/// The last line does not need to end with a newline.
+fn read_line() -> Option<Vec<u8>> {
+    let mut line = Vec::new();
+    match io::stdin().lock().read_until(b'\n', &mut line) {
+        Ok(0) => return None,
+        Ok(_) => {}
+        Err(err) => error(&format!("cannot read stdin: {}", err)),
+    }
+
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    }
+    Some(line)
+}
+
+/// Writes the bytes and flushes them straight away, since programs exit without running the
+/// cleanup that would flush Rust's buffers.
+fn write_flushed(mut out: impl Write, bytes: &[u8]) -> io::Result<()> {
+    out.write_all(bytes)?;
+    out.flush()
+}
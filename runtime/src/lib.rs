@@ -0,0 +1,15 @@
+//! The runtime library linked into every Reticulated program.
+//!
+//! Builtins that are easier to write in Rust than to emit as LLVM IR live here as `extern "C"`
+//! functions. The compiler registers each of them in its `Environment` under the Reticulated
+//! name it implements, so generated code calls them like any other function: every argument is
+//! a pointer to a value that the callee takes ownership of, and the result is a new value.
+//!
+//! Every function dereferences the pointers it is given, which the generated code guarantees to
+//! be valid values of the declared types.
+#![allow(clippy::missing_safety_doc)]
+
+pub mod convert;
+pub mod io;
+pub mod string;
+pub mod value;
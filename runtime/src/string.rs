@@ -0,0 +1,18 @@
+use crate::value::{release, NoneValue, Str};
+
+/// `$freeptr` for `str`.
+#[no_mangle]
+pub unsafe extern "C" fn rt_str_free(string: *mut Str) -> NoneValue {
+    release(string);
+    std::ptr::null_mut()
+}
+
+/// `str + str`.
+#[no_mangle]
+pub unsafe extern "C" fn rt_str_add(left: *mut Str, right: *mut Str) -> *mut Str {
+    let result = [Str::bytes(left), Str::bytes(right)].concat();
+
+    release(left);
+    release(right);
+    Str::new(&result)
+}
@@ -0,0 +1,160 @@
+//! The memory layout of Reticulated values, which must match the LLVM structs created by the
+//! compiler. Every value is allocated with `malloc` and ends with its reference count.
+
+use std::{
+    ffi::{c_char, c_void, CString},
+    mem::size_of,
+    ptr, slice,
+};
+
+extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+
+    /// Defined by the generated code, which reports the error at the line being executed and
+    /// exits.
+    #[link_name = "$runtime_error"]
+    fn runtime_error(msg: *const c_char) -> !;
+}
+
+/// `None`, which is always a null pointer.
+pub type NoneValue = *mut c_void;
+
+#[repr(C)]
+pub struct Str {
+    pub data: *mut u8,
+    pub len: i64,
+    pub ref_count: i64,
+}
+
+#[repr(C)]
+pub struct Int {
+    pub value: i64,
+    pub ref_count: i64,
+}
+
+#[repr(C)]
+pub struct Float {
+    pub value: f64,
+    pub ref_count: i64,
+}
+
+/// A file that can be written to, which is only ever stdout or stderr for now.
+#[repr(C)]
+pub struct File {
+    pub fd: i64,
+    pub ref_count: i64,
+}
+
+/// A value with a reference count, which can be released by the runtime.
+pub trait Value {
+    fn ref_count(&mut self) -> &mut i64;
+
+    /// Frees any memory owned by the value, before the value itself is freed.
+    unsafe fn free_contents(&mut self) {}
+}
+
+impl Value for Str {
+    fn ref_count(&mut self) -> &mut i64 {
+        &mut self.ref_count
+    }
+
+    unsafe fn free_contents(&mut self) {
+        free(self.data as *mut c_void);
+    }
+}
+
+impl Value for Int {
+    fn ref_count(&mut self) -> &mut i64 {
+        &mut self.ref_count
+    }
+}
+
+impl Value for Float {
+    fn ref_count(&mut self) -> &mut i64 {
+        &mut self.ref_count
+    }
+}
+
+impl Value for File {
+    fn ref_count(&mut self) -> &mut i64 {
+        &mut self.ref_count
+    }
+}
+
+/// Gives up a reference to a value, freeing it once no references are left. This does the same
+/// as the `$freeptr` functions generated by the compiler.
+pub unsafe fn release<T: Value>(value: *mut T) {
+    let ref_count = (*value).ref_count();
+    *ref_count -= 1;
+
+    if *ref_count == 0 {
+        (*value).free_contents();
+        free(value as *mut c_void);
+    }
+}
+
+/// Moves a value into memory allocated with `malloc`, so that generated code can free it.
+pub fn alloc<T>(value: T) -> *mut T {
+    unsafe {
+        let ptr = malloc_or_exit(size_of::<T>()) as *mut T;
+        ptr.write(value);
+        ptr
+    }
+}
+
+unsafe fn malloc_or_exit(size: usize) -> *mut c_void {
+    let ptr = malloc(size);
+    if ptr.is_null() && size > 0 {
+        error("out of memory");
+    }
+    ptr
+}
+
+impl Str {
+    /// Creates a new string holding a copy of the bytes.
+    pub fn new(bytes: &[u8]) -> *mut Str {
+        unsafe {
+            let data = malloc_or_exit(bytes.len()) as *mut u8;
+            ptr::copy_nonoverlapping(bytes.as_ptr(), data, bytes.len());
+
+            alloc(Str {
+                data,
+                len: bytes.len() as i64,
+                ref_count: 1,
+            })
+        }
+    }
+
+    pub unsafe fn bytes<'a>(string: *const Str) -> &'a [u8] {
+        let string = &*string;
+        if string.len == 0 {
+            return &[];
+        }
+        slice::from_raw_parts(string.data, string.len as usize)
+    }
+}
+
+impl Int {
+    pub fn new(value: i64) -> *mut Int {
+        alloc(Int {
+            value,
+            ref_count: 1,
+        })
+    }
+}
+
+impl Float {
+    pub fn new(value: f64) -> *mut Float {
+        alloc(Float {
+            value,
+            ref_count: 1,
+        })
+    }
+}
+
+/// Aborts the program with the given message, along with the line being executed.
+pub fn error(msg: &str) -> ! {
+    let msg = CString::new(msg.replace('\0', "\\0")).unwrap();
+    unsafe { runtime_error(msg.as_ptr()) }
+}
@@ -1,16 +1,19 @@
-use inkwell::types::StructType;
+use inkwell::{types::StructType, values::BasicValue};
 
-use crate::codegen::{
-    env::{
-        id::{BOOL_ID, STR_ID},
-        type_def::TypeDef,
-        Environment,
+use crate::{
+    codegen::{
+        env::{
+            id::{BOOL_ID, INT_ID, STR_ID},
+            type_def::TypeDef,
+            Environment,
+        },
+        err::GenError,
+        CodeGen,
     },
-    err::GenError,
-    CodeGen,
+    parser::BinaryFnOp,
 };
 
-use super::{llvm_resources::LLVMResources, primitive_unalloc, TO_STR_FN};
+use super::{llvm_resources::LLVMResources, primitive_unalloc, HASH_FN, TO_BOOL_FN, TO_STR_FN};
 
 pub const BOOL_NAME: &str = "bool";
 
@@ -36,12 +39,139 @@ impl<'ctx> CodeGen<'ctx> {
         self.build_copy_ptr_fn(BOOL_ID, env)?;
         self.build_get_reference_count_fn(BOOL_ID, env)?;
 
+        // Binary
+        self.setup_bool_eq_bool(env)?;
+        self.setup_bool_ne_bool(env)?;
+        self.setup_bool_and_bool(env)?;
+        self.setup_bool_or_bool(env)?;
+        self.setup_bool_xor_bool(env)?;
+
         // Conversion
         self.setup_bool_to_str(bool_struct, res, env)?;
+        self.setup_bool_to_bool(env)?;
+
+        // Hashing
+        self.setup_bool_hash(env)?;
 
         Ok(())
     }
 
+    fn setup_bool_eq_bool(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_binary_fn(
+            BinaryFnOp::Equal.fn_name(),
+            BOOL_ID,
+            BOOL_ID,
+            BOOL_ID,
+            |gen, left, right| {
+                Ok(gen
+                    .builder
+                    .build_int_compare(
+                        inkwell::IntPredicate::EQ,
+                        left.into_int_value(),
+                        right.into_int_value(),
+                        "bool_eq",
+                    )?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
+    fn setup_bool_ne_bool(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_binary_fn(
+            BinaryFnOp::NotEqual.fn_name(),
+            BOOL_ID,
+            BOOL_ID,
+            BOOL_ID,
+            |gen, left, right| {
+                Ok(gen
+                    .builder
+                    .build_int_compare(
+                        inkwell::IntPredicate::NE,
+                        left.into_int_value(),
+                        right.into_int_value(),
+                        "bool_ne",
+                    )?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
+    fn setup_bool_to_bool(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_unary_fn(
+            TO_BOOL_FN,
+            BOOL_ID,
+            BOOL_ID,
+            false, // The parameter is returned as is
+            |_gen, _fn_val, param, _env| Ok(param),
+            env,
+        )
+    }
+
+    fn setup_bool_hash(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_unary_fn(
+            HASH_FN,
+            BOOL_ID,
+            INT_ID,
+            |gen, expr| {
+                Ok(gen
+                    .builder
+                    .build_int_z_extend(expr.into_int_value(), gen.prim_int_type(), "bool_hash")?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
+    fn setup_bool_and_bool(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_binary_fn(
+            BinaryFnOp::BitAnd.fn_name(),
+            BOOL_ID,
+            BOOL_ID,
+            BOOL_ID,
+            |gen, left, right| {
+                Ok(gen
+                    .builder
+                    .build_and(left.into_int_value(), right.into_int_value(), "bool_and")?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
+    fn setup_bool_or_bool(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_binary_fn(
+            BinaryFnOp::BitOr.fn_name(),
+            BOOL_ID,
+            BOOL_ID,
+            BOOL_ID,
+            |gen, left, right| {
+                Ok(gen
+                    .builder
+                    .build_or(left.into_int_value(), right.into_int_value(), "bool_or")?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
+    fn setup_bool_xor_bool(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_binary_fn(
+            BinaryFnOp::BitXor.fn_name(),
+            BOOL_ID,
+            BOOL_ID,
+            BOOL_ID,
+            |gen, left, right| {
+                Ok(gen
+                    .builder
+                    .build_xor(left.into_int_value(), right.into_int_value(), "bool_xor")?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
     fn setup_bool_to_str(
         &mut self,
         bool_struct: StructType<'ctx>,
@@ -0,0 +1,94 @@
+use crate::{
+    codegen::{
+        env::{
+            id::{TypeId, STR_ID},
+            Environment,
+        },
+        err::GenError,
+        CodeGen,
+    },
+    parser::Derive,
+};
+
+use super::TO_STR_FN;
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Creates the method of a struct with the given name, returning false if the struct does not
+    /// derive a method with that name.
+    pub(super) fn create_derived_method(
+        &mut self,
+        ident: &str,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<bool, GenError> {
+        let derive = env
+            .derives(tid)
+            .iter()
+            .find(|derive| derive.fn_name() == ident)
+            .copied();
+
+        match derive {
+            Some(Derive::Str) => self.setup_struct_to_str(tid, env)?,
+            Some(Derive::Eq) => self.setup_fieldwise_eq(tid, env)?,
+            Some(Derive::Hash) => self.setup_fieldwise_hash(tid, env)?,
+            None => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Creates `__str__` for a struct deriving `str`, which shows every field by name like
+    /// `Vec(x=1.0, y=2.0)`.
+    fn setup_struct_to_str(
+        &mut self,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let fields: Vec<(String, TypeId)> = tid
+            .get_from(env)
+            .fields()
+            .iter()
+            .map(|field| (field.ident().to_string(), field.tid()))
+            .collect();
+
+        // Instances of generic structs are shown with the name of the struct they come from
+        let name = match env.instance_of(tid) {
+            Some((template_ident, _)) => template_ident.clone(),
+            None => env.type_id_ident(tid).to_string(),
+        };
+
+        self.create_unary_fn(
+            TO_STR_FN,
+            tid,
+            STR_ID,
+            true,
+            |gen, _fn_val, value, env| {
+                let result_var = gen.builder.build_alloca(gen.ptr_type(), "result_var")?;
+                let open = gen.build_str_const(&format!("{}(", name), env)?;
+                gen.builder.build_store(result_var, open)?;
+
+                for (i, (field_ident, field_tid)) in fields.iter().enumerate() {
+                    let to_str_fn = gen.find_method(TO_STR_FN, *field_tid, &[*field_tid], env)?;
+
+                    let separator = if i > 0 { ", " } else { "" };
+                    let label =
+                        gen.build_str_const(&format!("{}{}=", separator, field_ident), env)?;
+                    gen.build_append_str(result_var, label, env)?;
+
+                    let field_ptr = gen.build_load_tuple_elem(value, tid, i as u32, env)?;
+                    gen.build_append_repr(result_var, field_ptr, *field_tid, to_str_fn, env)?;
+                }
+
+                let close = gen.build_str_const(")", env)?;
+                gen.build_append_str(result_var, close, env)?;
+
+                let result = gen
+                    .builder
+                    .build_load(gen.ptr_type(), result_var, "result")?
+                    .into_pointer_value();
+                Ok(result)
+            },
+            env,
+        )
+    }
+}
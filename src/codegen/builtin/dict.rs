@@ -0,0 +1,525 @@
+use inkwell::{values::PointerValue, IntPredicate};
+
+use crate::codegen::{
+    env::{
+        id::{TypeId, BOOL_ID, INT_ID, NONE_ID, STR_ID},
+        type_def::{TypeDef, TypeInfo},
+        Environment,
+    },
+    err::GenError,
+    CodeGen,
+};
+
+use super::{
+    hash_table::{ENTRY_KEY_FIELD, ENTRY_VALUE_FIELD},
+    CONTAINS_FN, DEL_ITEM_FN, GET_ITEM_FN, ITER_FN, LEN_FN, SET_ITEM_FN, TO_BOOL_FN, TO_STR_FN,
+};
+
+pub const DICT_NAME: &str = "dict";
+
+const KEY_NOT_FOUND_MSG: &str = "key not found in dict";
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Returns the dict type with the given key and value types, creating it if it does not exist
+    /// yet.
+    ///
+    /// Only the pointer functions are created here, all other methods are created once they are
+    /// used. Keys are required to have `__hash__` and `__eq__` methods at that point.
+    pub(in crate::codegen) fn dict_of(
+        &mut self,
+        key: TypeId,
+        value: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<TypeId, GenError> {
+        let ident = format!(
+            "{}[{}, {}]",
+            DICT_NAME,
+            env.type_id_ident(key),
+            env.type_id_ident(value)
+        );
+        if let Ok(tid) = env.find_type(&ident) {
+            return Ok(tid);
+        }
+
+        let struct_type = self.create_struct_type(&ident, self.hash_table_fields());
+        let tid = env.gen_type_id();
+        env.register_type(
+            &ident,
+            tid,
+            TypeDef::new_generic(&ident, struct_type, TypeInfo::Dict(key, value)),
+        )?;
+
+        let prev_block = self.builder.get_insert_block().unwrap();
+
+        self.build_free_ptr_fn(tid, dict_unalloc, env)?;
+        self.build_copy_ptr_fn(tid, env)?;
+        self.build_get_reference_count_fn(tid, env)?;
+
+        self.builder.position_at_end(prev_block);
+
+        Ok(tid)
+    }
+
+    /// Builds a dict containing the given entries, taking ownership of them. Like Python, later
+    /// entries replace earlier ones with an equal key.
+    pub(in crate::codegen) fn build_dict(
+        &mut self,
+        tid: TypeId,
+        entries: &[(PointerValue<'ctx>, PointerValue<'ctx>)],
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        let TypeInfo::Dict(key, value) = tid.get_from(env).info() else {
+            unreachable!()
+        };
+        let set_item_fn = self.find_method(SET_ITEM_FN, tid, &[tid, key, value], env)?;
+
+        let dict = self.build_empty_hash_table(tid, entries.len(), env)?;
+
+        for (key_ptr, value_ptr) in entries {
+            self.copy_pointer(dict, tid, env)?;
+            let (none, ..) = self.call_func(set_item_fn, &[dict, *key_ptr, *value_ptr], env)?;
+            self.free_pointer(none, NONE_ID, env)?;
+        }
+
+        Ok(dict)
+    }
+
+    /// Creates the method of a dict type with the given name, returning false if dicts do not
+    /// have a method with that name.
+    pub(super) fn create_dict_method(
+        &mut self,
+        ident: &str,
+        tid: TypeId,
+        key: TypeId,
+        value: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<bool, GenError> {
+        match ident {
+            LEN_FN => self.setup_dict_len(tid, env)?,
+            TO_BOOL_FN => self.setup_dict_to_bool(tid, env)?,
+            TO_STR_FN => self.setup_dict_to_str(tid, key, value, env)?,
+            GET_ITEM_FN => self.setup_dict_get_item(tid, key, value, env)?,
+            SET_ITEM_FN => self.setup_dict_set_item(tid, key, value, env)?,
+            DEL_ITEM_FN => self.setup_dict_del_item(tid, key, value, env)?,
+            CONTAINS_FN => self.setup_dict_contains(tid, key, env)?,
+            "get" => self.setup_dict_get(tid, key, value, env)?,
+            // Like Python, iterating over a dict iterates over its keys
+            "keys" | ITER_FN => self.setup_dict_keys(ident, tid, key, env)?,
+            "values" => self.setup_dict_values(tid, value, env)?,
+            "items" => self.setup_dict_items(tid, key, value, env)?,
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn setup_dict_len(&mut self, tid: TypeId, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_unary_fn(
+            LEN_FN,
+            tid,
+            INT_ID,
+            true,
+            |gen, _fn_val, dict, env| {
+                let len = gen.build_hash_table_len(dict, tid, env)?;
+                gen.build_struct(INT_ID.get_from(env).ink(), vec![len.into()])
+            },
+            env,
+        )
+    }
+
+    fn setup_dict_to_bool(
+        &mut self,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        self.create_unary_fn(
+            TO_BOOL_FN,
+            tid,
+            BOOL_ID,
+            true,
+            |gen, _fn_val, dict, env| {
+                let len = gen.build_hash_table_len(dict, tid, env)?;
+                let not_empty = gen.builder.build_int_compare(
+                    IntPredicate::NE,
+                    len,
+                    gen.len_type().const_zero(),
+                    "not_empty",
+                )?;
+                gen.build_struct(BOOL_ID.get_from(env).ink(), vec![not_empty.into()])
+            },
+            env,
+        )
+    }
+
+    fn setup_dict_to_str(
+        &mut self,
+        tid: TypeId,
+        key: TypeId,
+        value: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let key_to_str_fn = self.find_method(TO_STR_FN, key, &[key], env)?;
+        let value_to_str_fn = self.find_method(TO_STR_FN, value, &[value], env)?;
+
+        self.create_unary_fn(
+            TO_STR_FN,
+            tid,
+            STR_ID,
+            true,
+            |gen, _fn_val, dict, env| {
+                let result_var = gen.builder.build_alloca(gen.ptr_type(), "result_var")?;
+                let open = gen.build_str_const("{", env)?;
+                gen.builder.build_store(result_var, open)?;
+
+                gen.build_append_hash_table_entries(
+                    result_var,
+                    dict,
+                    tid,
+                    |gen, entry, env| {
+                        let key_ptr = gen.build_load_entry_ptr(entry, ENTRY_KEY_FIELD)?;
+                        gen.build_append_repr(result_var, key_ptr, key, key_to_str_fn, env)?;
+
+                        let colon = gen.build_str_const(": ", env)?;
+                        gen.build_append_str(result_var, colon, env)?;
+
+                        let value_ptr = gen.build_load_entry_ptr(entry, ENTRY_VALUE_FIELD)?;
+                        gen.build_append_repr(result_var, value_ptr, value, value_to_str_fn, env)
+                    },
+                    env,
+                )?;
+
+                let close = gen.build_str_const("}", env)?;
+                gen.build_append_str(result_var, close, env)?;
+
+                let result = gen
+                    .builder
+                    .build_load(gen.ptr_type(), result_var, "result")?
+                    .into_pointer_value();
+                Ok(result)
+            },
+            env,
+        )
+    }
+
+    fn setup_dict_get_item(
+        &mut self,
+        tid: TypeId,
+        key: TypeId,
+        value: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let key_fns = self.find_key_fns(key, env)?;
+
+        self.create_binary_fn(
+            GET_ITEM_FN,
+            tid,
+            key,
+            value,
+            true,
+            |gen, dict, key_ptr, env| {
+                let entry = gen.build_hash_table_find_existing(
+                    dict,
+                    tid,
+                    key_ptr,
+                    key,
+                    key_fns,
+                    KEY_NOT_FOUND_MSG,
+                    env,
+                )?;
+
+                let value_ptr = gen.build_load_entry_ptr(entry, ENTRY_VALUE_FIELD)?;
+                gen.copy_pointer(value_ptr, value, env)
+            },
+            env,
+        )
+    }
+
+    fn setup_dict_set_item(
+        &mut self,
+        tid: TypeId,
+        key: TypeId,
+        value: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let key_fns = self.find_key_fns(key, env)?;
+
+        let (fn_val, ..) =
+            env.create_func(Some(tid), SET_ITEM_FN, &[tid, key, value], NONE_ID, false)?;
+        let (dict, key_ptr, value_ptr) = self.build_ternary_fn_entry(fn_val);
+
+        // Make space first, since growing the dict moves its entries
+        self.build_hash_table_reserve_one(dict, tid, env)?;
+
+        let hash = self.build_hash(key_ptr, key, key_fns, env)?;
+        let (slot, index) =
+            self.build_hash_table_lookup(dict, tid, key_ptr, hash, key, key_fns, env)?;
+
+        let replace_block = self.ctx.append_basic_block(fn_val, "replace");
+        let insert_block = self.ctx.append_basic_block(fn_val, "insert");
+        let end_block = self.ctx.append_basic_block(fn_val, "end");
+
+        let is_missing = self.build_is_empty_slot(index)?;
+        self.builder
+            .build_conditional_branch(is_missing, insert_block, replace_block)?;
+
+        // The dict keeps its existing key and takes ownership of the new value
+        self.builder.position_at_end(replace_block);
+        let entry = self.build_hash_table_entry_ptr(dict, tid, index, env)?;
+        let old_value = self.build_load_entry_ptr(entry, ENTRY_VALUE_FIELD)?;
+        self.free_pointer(old_value, value, env)?;
+        self.build_store_entry_field(entry, ENTRY_VALUE_FIELD, value_ptr)?;
+        self.free_pointer(key_ptr, key, env)?;
+        self.builder.build_unconditional_branch(end_block)?;
+
+        // New entries are added after all existing ones
+        self.builder.position_at_end(insert_block);
+        self.build_hash_table_insert(dict, tid, slot, hash, key_ptr, value_ptr, env)?;
+        self.builder.build_unconditional_branch(end_block)?;
+
+        self.builder.position_at_end(end_block);
+        self.free_pointer(dict, tid, env)?;
+
+        let none = self.build_none(env)?;
+        self.builder.build_return(Some(&none))?;
+
+        Ok(())
+    }
+
+    fn setup_dict_del_item(
+        &mut self,
+        tid: TypeId,
+        key: TypeId,
+        value: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let key_fns = self.find_key_fns(key, env)?;
+
+        self.create_binary_fn(
+            DEL_ITEM_FN,
+            tid,
+            key,
+            NONE_ID,
+            true,
+            |gen, dict, key_ptr, env| {
+                let entry = gen.build_hash_table_find_existing(
+                    dict,
+                    tid,
+                    key_ptr,
+                    key,
+                    key_fns,
+                    KEY_NOT_FOUND_MSG,
+                    env,
+                )?;
+
+                let old_key = gen.build_load_entry_ptr(entry, ENTRY_KEY_FIELD)?;
+                gen.free_pointer(old_key, key, env)?;
+                let old_value = gen.build_load_entry_ptr(entry, ENTRY_VALUE_FIELD)?;
+                gen.free_pointer(old_value, value, env)?;
+                gen.build_hash_table_remove(dict, tid, entry, env)?;
+
+                gen.build_none(env)
+            },
+            env,
+        )
+    }
+
+    fn setup_dict_contains(
+        &mut self,
+        tid: TypeId,
+        key: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let key_fns = self.find_key_fns(key, env)?;
+
+        self.create_binary_fn(
+            CONTAINS_FN,
+            tid,
+            key,
+            BOOL_ID,
+            true,
+            |gen, dict, key_ptr, env| {
+                let contains =
+                    gen.build_hash_table_contains(dict, tid, key_ptr, key, key_fns, env)?;
+                gen.build_struct(BOOL_ID.get_from(env).ink(), vec![contains.into()])
+            },
+            env,
+        )
+    }
+
+    fn setup_dict_get(
+        &mut self,
+        tid: TypeId,
+        key: TypeId,
+        value: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let key_fns = self.find_key_fns(key, env)?;
+
+        // get(key, default) returns the default when the key is missing
+        let (fn_val, ..) = env.create_func(Some(tid), "get", &[tid, key, value], value, false)?;
+        let (dict, key_ptr, default) = self.build_ternary_fn_entry(fn_val);
+
+        let hash = self.build_hash(key_ptr, key, key_fns, env)?;
+        let (_, index) =
+            self.build_hash_table_lookup(dict, tid, key_ptr, hash, key, key_fns, env)?;
+
+        let found_block = self.ctx.append_basic_block(fn_val, "found");
+        let missing_block = self.ctx.append_basic_block(fn_val, "missing");
+        let end_block = self.ctx.append_basic_block(fn_val, "end");
+
+        let is_missing = self.build_is_empty_slot(index)?;
+        self.builder
+            .build_conditional_branch(is_missing, missing_block, found_block)?;
+
+        self.builder.position_at_end(found_block);
+        let entry = self.build_hash_table_entry_ptr(dict, tid, index, env)?;
+        let value_ptr = self.build_load_entry_ptr(entry, ENTRY_VALUE_FIELD)?;
+        self.copy_pointer(value_ptr, value, env)?;
+        self.free_pointer(default, value, env)?;
+        let found_end_block = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(end_block)?;
+
+        self.builder.position_at_end(missing_block);
+        self.builder.build_unconditional_branch(end_block)?;
+
+        self.builder.position_at_end(end_block);
+        let result = self.builder.build_phi(self.ptr_type(), "result")?;
+        result.add_incoming(&[(&value_ptr, found_end_block), (&default, missing_block)]);
+
+        self.free_pointer(dict, tid, env)?;
+        self.free_pointer(key_ptr, key, env)?;
+
+        self.builder.build_return(Some(&result.as_basic_value()))?;
+
+        Ok(())
+    }
+
+    fn setup_dict_keys(
+        &mut self,
+        ident: &str,
+        tid: TypeId,
+        key: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        self.create_dict_to_list_fn(
+            ident,
+            tid,
+            key,
+            |gen, entry, env| {
+                let key_ptr = gen.build_load_entry_ptr(entry, ENTRY_KEY_FIELD)?;
+                gen.copy_pointer(key_ptr, key, env)
+            },
+            env,
+        )
+    }
+
+    fn setup_dict_values(
+        &mut self,
+        tid: TypeId,
+        value: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        self.create_dict_to_list_fn(
+            "values",
+            tid,
+            value,
+            |gen, entry, env| {
+                let value_ptr = gen.build_load_entry_ptr(entry, ENTRY_VALUE_FIELD)?;
+                gen.copy_pointer(value_ptr, value, env)
+            },
+            env,
+        )
+    }
+
+    fn setup_dict_items(
+        &mut self,
+        tid: TypeId,
+        key: TypeId,
+        value: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let item_tid = self.tuple_of(&[key, value], env)?;
+
+        self.create_dict_to_list_fn(
+            "items",
+            tid,
+            item_tid,
+            |gen, entry, env| {
+                let key_ptr = gen.build_load_entry_ptr(entry, ENTRY_KEY_FIELD)?;
+                let key_ptr = gen.copy_pointer(key_ptr, key, env)?;
+                let value_ptr = gen.build_load_entry_ptr(entry, ENTRY_VALUE_FIELD)?;
+                let value_ptr = gen.copy_pointer(value_ptr, value, env)?;
+                gen.build_tuple(item_tid, &[key_ptr, value_ptr], env)
+            },
+            env,
+        )
+    }
+
+    /// Creates a method that returns a list with one element for every entry of the dict.
+    fn create_dict_to_list_fn(
+        &mut self,
+        ident: &str,
+        tid: TypeId,
+        elem: TypeId,
+        build_elem: impl Fn(
+            &mut Self,
+            PointerValue<'ctx>,
+            &mut Environment<'ctx>,
+        ) -> Result<PointerValue<'ctx>, GenError>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let list_tid = self.list_of(elem, env)?;
+        let append_fn = self.find_method("append", list_tid, &[list_tid, elem], env)?;
+
+        self.create_unary_fn(
+            ident,
+            tid,
+            list_tid,
+            true,
+            |gen, _fn_val, dict, env| {
+                let list = gen.build_list(list_tid, &[], env)?;
+
+                gen.build_hash_table_entry_loop(
+                    dict,
+                    tid,
+                    |gen, entry, env| {
+                        let elem_ptr = build_elem(gen, entry, env)?;
+
+                        gen.copy_pointer(list, list_tid, env)?;
+                        let (none, ..) = gen.call_func(append_fn, &[list, elem_ptr], env)?;
+                        gen.free_pointer(none, NONE_ID, env)
+                    },
+                    env,
+                )?;
+
+                Ok(list)
+            },
+            env,
+        )
+    }
+}
+
+fn dict_unalloc<'ctx>(
+    ptr: PointerValue<'ctx>,
+    tid: TypeId,
+    gen: &mut CodeGen<'ctx>,
+    env: &mut Environment<'ctx>,
+) -> Result<(), GenError> {
+    let TypeInfo::Dict(key, value) = tid.get_from(env).info() else {
+        unreachable!()
+    };
+
+    gen.build_hash_table_entry_loop(
+        ptr,
+        tid,
+        |gen, entry, env| {
+            let key_ptr = gen.build_load_entry_ptr(entry, ENTRY_KEY_FIELD)?;
+            gen.free_pointer(key_ptr, key, env)?;
+            let value_ptr = gen.build_load_entry_ptr(entry, ENTRY_VALUE_FIELD)?;
+            gen.free_pointer(value_ptr, value, env)
+        },
+        env,
+    )?;
+
+    gen.build_free_hash_table_buffers(ptr, tid, env)
+}
@@ -3,7 +3,7 @@ use inkwell::{types::FloatType, values::BasicValue};
 use crate::{
     codegen::{
         env::{
-            id::{BOOL_ID, FLOAT_ID},
+            id::{BOOL_ID, FLOAT_ID, INT_ID},
             type_def::TypeDef,
             Environment,
         },
@@ -13,7 +13,7 @@ use crate::{
     parser::{BinaryFnOp, UnaryFnOp},
 };
 
-use super::{llvm_resources::LLVMResources, primitive_unalloc};
+use super::{llvm_resources::LLVMResources, primitive_unalloc, HASH_FN, TO_BOOL_FN};
 
 pub const FLOAT_NAME: &str = "float";
 
@@ -42,6 +42,8 @@ impl<'ctx> CodeGen<'ctx> {
         self.setup_float_sub_float(env)?;
         self.setup_float_mul_float(env)?;
         self.setup_float_div_float(env)?;
+        self.setup_float_eq_float(env)?;
+        self.setup_float_ne_float(env)?;
         self.setup_float_gt_float(env)?;
         self.setup_float_lt_float(env)?;
         self.setup_float_ge_float(env)?;
@@ -52,7 +54,10 @@ impl<'ctx> CodeGen<'ctx> {
         self.setup_negate_float(env)?;
 
         // Conversion
-        self.setup_float_to_str(res, env)?;
+        self.setup_float_to_bool(env)?;
+
+        // Hashing
+        self.setup_float_hash(env)?;
 
         Ok(())
     }
@@ -137,6 +142,48 @@ impl<'ctx> CodeGen<'ctx> {
         )
     }
 
+    fn setup_float_eq_float(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_binary_fn(
+            BinaryFnOp::Equal.fn_name(),
+            FLOAT_ID,
+            FLOAT_ID,
+            BOOL_ID,
+            |gen, left, right| {
+                Ok(gen
+                    .builder
+                    .build_float_compare(
+                        inkwell::FloatPredicate::OEQ,
+                        left.into_float_value(),
+                        right.into_float_value(),
+                        "float_eq",
+                    )?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
+    fn setup_float_ne_float(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_binary_fn(
+            BinaryFnOp::NotEqual.fn_name(),
+            FLOAT_ID,
+            FLOAT_ID,
+            BOOL_ID,
+            |gen, left, right| {
+                Ok(gen
+                    .builder
+                    .build_float_compare(
+                        inkwell::FloatPredicate::UNE,
+                        left.into_float_value(),
+                        right.into_float_value(),
+                        "float_ne",
+                    )?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
     fn setup_float_gt_float(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
         self.create_primitive_binary_fn(
             BinaryFnOp::Greater.fn_name(),
@@ -254,12 +301,48 @@ impl<'ctx> CodeGen<'ctx> {
         )
     }
 
-    fn setup_float_to_str(
-        &mut self,
-        res: &LLVMResources<'ctx>,
-        env: &mut Environment<'ctx>,
-    ) -> Result<(), GenError> {
-        self.create_primitive_to_str_fn(FLOAT_ID, "%lf", res, env)
+    fn setup_float_to_bool(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_unary_fn(
+            TO_BOOL_FN,
+            FLOAT_ID,
+            BOOL_ID,
+            |gen, expr| {
+                let zero = gen.prim_float_type().const_zero();
+
+                // Unordered so that NaN is truthy, like Python
+                Ok(gen
+                    .builder
+                    .build_float_compare(
+                        inkwell::FloatPredicate::UNE,
+                        expr.into_float_value(),
+                        zero,
+                        "float_to_bool",
+                    )?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
+    fn setup_float_hash(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_unary_fn(
+            HASH_FN,
+            FLOAT_ID,
+            INT_ID,
+            |gen, expr| {
+                // Adding zero turns -0.0 into 0.0, so that equal floats have the same bits
+                let zero = gen.prim_float_type().const_zero();
+                let normalized =
+                    gen.builder
+                        .build_float_add(expr.into_float_value(), zero, "normalized")?;
+
+                Ok(gen
+                    .builder
+                    .build_bit_cast(normalized, gen.prim_int_type(), "float_hash")?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
     }
 
     pub fn prim_float_type(&mut self) -> FloatType<'ctx> {
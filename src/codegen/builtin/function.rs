@@ -0,0 +1,249 @@
+use inkwell::{
+    types::{BasicMetadataTypeEnum, BasicTypeEnum, FunctionType},
+    values::{BasicMetadataValueEnum, FunctionValue, PointerValue},
+};
+
+use crate::codegen::{
+    env::{
+        id::{FunctionId, TypeId},
+        type_def::{TypeDef, TypeInfo},
+        Environment,
+    },
+    err::GenError,
+    util::FREE_PTR_IDENT,
+    CodeGen,
+};
+
+pub const FN_NAME: &str = "fn";
+
+/// The fields of a function value, which is called through `code` with `context` as its first
+/// argument. `free_context` releases the context, which is null for functions that need none.
+const CODE_FIELD: u32 = 0;
+const CONTEXT_FIELD: u32 = 1;
+const FREE_CONTEXT_FIELD: u32 = 2;
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Returns the function type with the given signature, creating it if it does not exist yet.
+    pub(in crate::codegen) fn fn_type_of(
+        &mut self,
+        params: &[TypeId],
+        ret_tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<TypeId, GenError> {
+        let param_idents: Vec<&str> = params.iter().map(|tid| env.type_id_ident(*tid)).collect();
+        let ident = format!(
+            "{}({}) -> {}",
+            FN_NAME,
+            param_idents.join(", "),
+            env.type_id_ident(ret_tid)
+        );
+        if let Ok(tid) = env.find_type(&ident) {
+            return Ok(tid);
+        }
+
+        let field_types: Vec<BasicTypeEnum> = vec![self.ptr_type().into(); 3];
+        let struct_type = self.create_struct_type(&ident, field_types);
+
+        let tid = env.gen_type_id();
+        env.register_type(
+            &ident,
+            tid,
+            TypeDef::new_generic(&ident, struct_type, TypeInfo::Func),
+        )?;
+        env.register_fn_type(tid, params, ret_tid);
+
+        let prev_block = self.builder.get_insert_block().unwrap();
+
+        self.build_free_ptr_fn(tid, Self::fn_value_unalloc, env)?;
+        self.build_copy_ptr_fn(tid, env)?;
+        self.build_get_reference_count_fn(tid, env)?;
+
+        self.builder.position_at_end(prev_block);
+
+        Ok(tid)
+    }
+
+    /// Builds a function value referring to a function declared with `def`. A method is bound to
+    /// the receiver, which the value takes ownership of. Overloads are told apart by the expected
+    /// type, whose type the value also takes when it is given.
+    pub(in crate::codegen) fn build_fn_ref(
+        &mut self,
+        ident: &str,
+        receiver: Option<(PointerValue<'ctx>, TypeId)>,
+        expected: Option<TypeId>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let owner = receiver.map(|(_, tid)| tid);
+        let expected = expected.filter(|tid| env.fn_signature(*tid).is_some());
+        let fn_id = env.resolve_fn_ref(ident, owner, expected)?;
+
+        let fn_tid = match expected {
+            Some(tid) => tid,
+            None => {
+                let func = env.get_func(fn_id);
+                let params = func.params[owner.is_some() as usize..].to_vec();
+                let ret_tid = func.ret_type;
+                self.fn_type_of(&params, ret_tid, env)?
+            }
+        };
+
+        let thunk = self.get_or_build_fn_thunk(fn_id, owner, env)?;
+        let (context, free_context) = match receiver {
+            Some((ptr, tid)) => {
+                let free_fn = env.find_func(FREE_PTR_IDENT, Some(tid), &[tid])?;
+                let free_fn = free_fn.get_from(env).ink();
+                (ptr, free_fn.as_global_value().as_pointer_value())
+            }
+            None => (self.ptr_type().const_null(), self.ptr_type().const_null()),
+        };
+
+        let fn_value = self.build_struct(
+            fn_tid.get_from(env).ink(),
+            vec![
+                thunk.as_global_value().as_pointer_value().into(),
+                context.into(),
+                free_context.into(),
+            ],
+        )?;
+
+        Ok((fn_value, fn_tid))
+    }
+
+    /// Builds a call to a function value, taking ownership of it and of the arguments.
+    pub(in crate::codegen) fn build_call_fn_value(
+        &mut self,
+        fn_value: PointerValue<'ctx>,
+        fn_tid: TypeId,
+        args: &[PointerValue<'ctx>],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let ret_tid = env.fn_signature(fn_tid).unwrap().1;
+        let struct_type = fn_tid.get_from(env).ink();
+
+        let code_ptr =
+            self.builder
+                .build_struct_gep(struct_type, fn_value, CODE_FIELD, "code_ptr")?;
+        let code = self
+            .builder
+            .build_load(self.ptr_type(), code_ptr, "code")?
+            .into_pointer_value();
+        let context_ptr =
+            self.builder
+                .build_struct_gep(struct_type, fn_value, CONTEXT_FIELD, "context_ptr")?;
+        let context = self
+            .builder
+            .build_load(self.ptr_type(), context_ptr, "context")?;
+
+        let mut call_args: Vec<BasicMetadataValueEnum> = vec![context.into()];
+        call_args.extend(args.iter().map(|arg| BasicMetadataValueEnum::from(*arg)));
+
+        let ret = self
+            .builder
+            .build_indirect_call(self.thunk_type(args.len()), code, &call_args, "_")?
+            .try_as_basic_value()
+            .unwrap_left()
+            .into_pointer_value();
+
+        self.free_pointer(fn_value, fn_tid, env)?;
+
+        Ok((ret, ret_tid))
+    }
+
+    /// Returns the function that function values referring to the given function call, which
+    /// takes the context of the value before the arguments and passes it on as `self` for a
+    /// method.
+    fn get_or_build_fn_thunk(
+        &mut self,
+        fn_id: FunctionId,
+        owner: Option<TypeId>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<FunctionValue<'ctx>, GenError> {
+        let func = env.get_func(fn_id);
+        let thunk_name = format!("{}$ref", func.ident);
+        if let Some(thunk) = env.module.get_function(&thunk_name) {
+            return Ok(thunk);
+        }
+
+        let arg_count = func.params.len() - owner.is_some() as usize;
+        let thunk = env
+            .module
+            .add_function(&thunk_name, self.thunk_type(arg_count), None);
+
+        let prev_block = self.builder.get_insert_block().unwrap();
+        let entry = self.ctx.append_basic_block(thunk, "entry");
+        self.builder.position_at_end(entry);
+
+        let mut args = Vec::with_capacity(arg_count + 1);
+        if let Some(owner) = owner {
+            // The value keeps its own reference to the receiver
+            let context = thunk.get_nth_param(0).unwrap().into_pointer_value();
+            args.push(self.copy_pointer(context, owner, env)?);
+        }
+        for i in 0..arg_count {
+            args.push(
+                thunk
+                    .get_nth_param(i as u32 + 1)
+                    .unwrap()
+                    .into_pointer_value(),
+            );
+        }
+
+        let (ret, _) = self.call_func(fn_id, &args, env)?;
+        self.builder.build_return(Some(&ret))?;
+
+        self.builder.position_at_end(prev_block);
+
+        Ok(thunk)
+    }
+
+    fn thunk_type(&self, arg_count: usize) -> FunctionType<'ctx> {
+        let params: Vec<BasicMetadataTypeEnum> = vec![self.ptr_type().into(); arg_count + 1];
+        self.ptr_type().fn_type(&params, false)
+    }
+
+    fn fn_value_unalloc(
+        ptr: PointerValue<'ctx>,
+        tid: TypeId,
+        gen: &mut CodeGen<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let struct_type = tid.get_from(env).ink();
+        let context_ptr =
+            gen.builder
+                .build_struct_gep(struct_type, ptr, CONTEXT_FIELD, "context_ptr")?;
+        let context = gen
+            .builder
+            .build_load(gen.ptr_type(), context_ptr, "context")?
+            .into_pointer_value();
+
+        let cur_block = gen.builder.get_insert_block().unwrap();
+        let free_block = gen.ctx.insert_basic_block_after(cur_block, "free_context");
+        let merge_block = gen
+            .ctx
+            .insert_basic_block_after(free_block, "freed_context");
+
+        let has_context = gen.builder.build_is_not_null(context, "has_context")?;
+        gen.builder
+            .build_conditional_branch(has_context, free_block, merge_block)?;
+
+        gen.builder.position_at_end(free_block);
+        let free_context_ptr = gen.builder.build_struct_gep(
+            struct_type,
+            ptr,
+            FREE_CONTEXT_FIELD,
+            "free_context_ptr",
+        )?;
+        let free_context = gen
+            .builder
+            .build_load(gen.ptr_type(), free_context_ptr, "free_context")?
+            .into_pointer_value();
+        let free_type = gen.ptr_type().fn_type(&[gen.ptr_type().into()], false);
+        gen.builder
+            .build_indirect_call(free_type, free_context, &[context.into()], "_")?;
+        gen.builder.build_unconditional_branch(merge_block)?;
+
+        gen.builder.position_at_end(merge_block);
+
+        Ok(())
+    }
+}
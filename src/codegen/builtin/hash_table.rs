@@ -0,0 +1,787 @@
+use inkwell::{
+    types::{BasicTypeEnum, StructType},
+    values::{BasicValue, BasicValueEnum, IntValue, PointerValue},
+    IntPredicate,
+};
+
+use crate::{
+    codegen::{
+        env::{
+            id::{FunctionId, TypeId, INT_ID},
+            Environment,
+        },
+        err::GenError,
+        CodeGen,
+    },
+    parser::BinaryFnOp,
+};
+
+use super::HASH_FN;
+
+// The hash table shared by dicts and sets, which requires keys to have `__hash__` and `__eq__`
+// methods. Sets store their elements as keys and leave the values null.
+
+// Fields of a hash table struct, which is followed by the reference count like every other type.
+// Entries are stored in insertion order, and the table maps hashes to indices into the entries.
+const ENTRIES_FIELD: u32 = 0;
+const LEN_FIELD: u32 = 1;
+const USED_FIELD: u32 = 2; // Number of entries, including deleted ones
+const CAP_FIELD: u32 = 3; // Capacity of the entries, the table has twice as many slots
+const TABLE_FIELD: u32 = 4;
+
+// Fields of an entry, where a deleted entry has a null key
+const ENTRY_HASH_FIELD: u32 = 0;
+pub(super) const ENTRY_KEY_FIELD: u32 = 1;
+pub(super) const ENTRY_VALUE_FIELD: u32 = 2;
+
+/// Marks a slot of the table that does not refer to an entry.
+const EMPTY_SLOT: i64 = -1;
+
+const MIN_CAPACITY: u64 = 8;
+
+/// The methods used to find keys in a hash table.
+#[derive(Clone, Copy)]
+pub(super) struct KeyFns {
+    hash: FunctionId,
+    eq: FunctionId,
+}
+
+impl<'ctx> CodeGen<'ctx> {
+    /// The fields of a hash table struct, not including the reference count.
+    pub(super) fn hash_table_fields(&self) -> Vec<BasicTypeEnum<'ctx>> {
+        vec![
+            self.ptr_type().into(),
+            self.len_type().into(),
+            self.len_type().into(),
+            self.len_type().into(),
+            self.ptr_type().into(),
+        ]
+    }
+
+    pub(super) fn find_key_fns(
+        &mut self,
+        key: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<KeyFns, GenError> {
+        Ok(KeyFns {
+            hash: self.find_method(HASH_FN, key, &[key], env)?,
+            eq: self.find_method(BinaryFnOp::Equal.fn_name(), key, &[key, key], env)?,
+        })
+    }
+
+    /// Builds an empty hash table with space for at least the given number of entries.
+    pub(super) fn build_empty_hash_table(
+        &mut self,
+        tid: TypeId,
+        len: usize,
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        // The capacity must be a power of two, so that slots can be found by masking
+        let cap = (len as u64).next_power_of_two().max(MIN_CAPACITY);
+        let cap = self.len_type().const_int(cap, false);
+        let (entries, table) = self.build_hash_table_buffers(cap)?;
+        let zero = self.len_type().const_zero();
+
+        self.build_struct(
+            tid.get_from(env).ink(),
+            vec![
+                entries.into(),
+                zero.into(),
+                zero.into(),
+                cap.into(),
+                table.into(),
+            ],
+        )
+    }
+
+    /// Allocates the entries and an empty table for a hash table with the given capacity.
+    fn build_hash_table_buffers(
+        &mut self,
+        cap: IntValue<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, PointerValue<'ctx>), GenError> {
+        let entries =
+            self.builder
+                .build_array_malloc(self.hash_table_entry_type(), cap, "entries")?;
+
+        let slot_count = self.build_slot_count(cap)?;
+        let table = self
+            .builder
+            .build_array_malloc(self.len_type(), slot_count, "table")?;
+
+        // Every byte of an empty slot is set, since it holds -1
+        let table_size =
+            self.builder
+                .build_int_mul(slot_count, self.len_type().size_of(), "table_size")?;
+        self.builder.build_memset(
+            table,
+            8,
+            self.ctx.i8_type().const_int(u8::MAX as u64, false),
+            table_size,
+        )?;
+
+        Ok((entries, table))
+    }
+
+    /// Calls the key's `__hash__` method without consuming the key.
+    pub(super) fn build_hash(
+        &mut self,
+        key_ptr: PointerValue<'ctx>,
+        key: TypeId,
+        key_fns: KeyFns,
+        env: &mut Environment<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        self.copy_pointer(key_ptr, key, env)?;
+        let (hash_ptr, ..) = self.call_func(key_fns.hash, &[key_ptr], env)?;
+        let hash = self
+            .extract_primitive(hash_ptr, INT_ID.get_from(env).ink())?
+            .into_int_value();
+        self.free_pointer(hash_ptr, INT_ID, env)?;
+
+        Ok(hash)
+    }
+
+    /// Finds the entry with the given key, aborting with the message if the hash table does not
+    /// contain it.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn build_hash_table_find_existing(
+        &mut self,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        key_ptr: PointerValue<'ctx>,
+        key: TypeId,
+        key_fns: KeyFns,
+        missing_msg: &str,
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        let hash = self.build_hash(key_ptr, key, key_fns, env)?;
+        let (_, index) =
+            self.build_hash_table_lookup(hash_table, tid, key_ptr, hash, key, key_fns, env)?;
+
+        let is_missing = self.build_is_empty_slot(index)?;
+        self.build_runtime_check(is_missing, missing_msg)?;
+
+        self.build_hash_table_entry_ptr(hash_table, tid, index, env)
+    }
+
+    /// Finds the slot of the table for a key, returning the slot and the index of the entry it
+    /// refers to, which is empty if the hash table does not contain the key.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn build_hash_table_lookup(
+        &mut self,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        key_ptr: PointerValue<'ctx>,
+        hash: IntValue<'ctx>,
+        key: TypeId,
+        key_fns: KeyFns,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(IntValue<'ctx>, IntValue<'ctx>), GenError> {
+        let table = self
+            .build_load_hash_table_field(hash_table, tid, TABLE_FIELD, env)?
+            .into_pointer_value();
+        let cap = self
+            .build_load_hash_table_field(hash_table, tid, CAP_FIELD, env)?
+            .into_int_value();
+
+        self.build_table_probe(
+            table,
+            cap,
+            hash,
+            |gen, index, env| {
+                let cur_fn = gen
+                    .builder
+                    .get_insert_block()
+                    .unwrap()
+                    .get_parent()
+                    .unwrap();
+                let compare_block = gen.ctx.append_basic_block(cur_fn, "compare_keys");
+                let result_block = gen.ctx.append_basic_block(cur_fn, "key_matches");
+
+                let entry = gen.build_hash_table_entry_ptr(hash_table, tid, index, env)?;
+                let entry_key = gen.build_load_entry_ptr(entry, ENTRY_KEY_FIELD)?;
+                let entry_hash = gen
+                    .build_load_entry_field(entry, ENTRY_HASH_FIELD)?
+                    .into_int_value();
+
+                // Only compare live entries with the same hash
+                let is_live = gen.builder.build_is_not_null(entry_key, "is_live")?;
+                let same_hash = gen.builder.build_int_compare(
+                    IntPredicate::EQ,
+                    entry_hash,
+                    hash,
+                    "same_hash",
+                )?;
+                let is_candidate = gen.builder.build_and(is_live, same_hash, "is_candidate")?;
+                let check_block = gen.builder.get_insert_block().unwrap();
+                gen.builder
+                    .build_conditional_branch(is_candidate, compare_block, result_block)?;
+
+                gen.builder.position_at_end(compare_block);
+                gen.copy_pointer(entry_key, key, env)?;
+                gen.copy_pointer(key_ptr, key, env)?;
+                let (is_eq_ptr, is_eq_tid) =
+                    gen.call_func(key_fns.eq, &[entry_key, key_ptr], env)?;
+                let is_eq = gen.build_truthy(is_eq_ptr, is_eq_tid, env)?;
+                let compare_end_block = gen.builder.get_insert_block().unwrap();
+                gen.builder.build_unconditional_branch(result_block)?;
+
+                gen.builder.position_at_end(result_block);
+                let bool_type = gen.ctx.bool_type();
+                let matches = gen.builder.build_phi(bool_type, "matches")?;
+                matches.add_incoming(&[
+                    (&bool_type.const_zero(), check_block),
+                    (&is_eq, compare_end_block),
+                ]);
+
+                Ok(matches.as_basic_value().into_int_value())
+            },
+            env,
+        )
+    }
+
+    /// Probes the table starting from the slot of the hash until reaching an empty slot or a slot
+    /// whose entry `is_match` accepts, returning the slot and the index of its entry.
+    fn build_table_probe(
+        &mut self,
+        table: PointerValue<'ctx>,
+        cap: IntValue<'ctx>,
+        hash: IntValue<'ctx>,
+        is_match: impl FnOnce(
+            &mut Self,
+            IntValue<'ctx>,
+            &mut Environment<'ctx>,
+        ) -> Result<IntValue<'ctx>, GenError>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(IntValue<'ctx>, IntValue<'ctx>), GenError> {
+        let start_block = self.builder.get_insert_block().unwrap();
+        let cur_fn = start_block.get_parent().unwrap();
+        let probe_block = self.ctx.append_basic_block(cur_fn, "probe");
+        let check_block = self.ctx.append_basic_block(cur_fn, "check_slot");
+        let next_block = self.ctx.append_basic_block(cur_fn, "next_slot");
+        let end_block = self.ctx.append_basic_block(cur_fn, "probe_end");
+
+        // The number of slots is a power of two, so masking wraps the slot around the table
+        let slot_count = self.build_slot_count(cap)?;
+        let mask =
+            self.builder
+                .build_int_sub(slot_count, self.len_type().const_int(1, false), "mask")?;
+        let start_slot = self.builder.build_and(hash, mask, "start_slot")?;
+        self.builder.build_unconditional_branch(probe_block)?;
+
+        self.builder.position_at_end(probe_block);
+        let slot = self.builder.build_phi(self.len_type(), "slot")?;
+        let slot_val = slot.as_basic_value().into_int_value();
+        let slot_ptr = self.build_table_slot_ptr(table, slot_val)?;
+        let index = self
+            .builder
+            .build_load(self.len_type(), slot_ptr, "index")?
+            .into_int_value();
+        let is_empty = self.build_is_empty_slot(index)?;
+        self.builder
+            .build_conditional_branch(is_empty, end_block, check_block)?;
+
+        self.builder.position_at_end(check_block);
+        let matches = is_match(self, index, env)?;
+        self.builder
+            .build_conditional_branch(matches, end_block, next_block)?;
+
+        self.builder.position_at_end(next_block);
+        let next_slot = self.builder.build_int_add(
+            slot_val,
+            self.len_type().const_int(1, false),
+            "next_slot",
+        )?;
+        let next_slot = self.builder.build_and(next_slot, mask, "next_slot")?;
+        self.builder.build_unconditional_branch(probe_block)?;
+
+        slot.add_incoming(&[(&start_slot, start_block), (&next_slot, next_block)]);
+
+        self.builder.position_at_end(end_block);
+
+        Ok((slot_val, index))
+    }
+
+    /// Makes sure there is space for one more entry, growing the hash table or removing its deleted
+    /// entries when it is full.
+    pub(super) fn build_hash_table_reserve_one(
+        &mut self,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let cur_fn = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let grow_block = self.ctx.append_basic_block(cur_fn, "grow");
+        let continue_block = self.ctx.append_basic_block(cur_fn, "has_capacity");
+
+        let used = self
+            .build_load_hash_table_field(hash_table, tid, USED_FIELD, env)?
+            .into_int_value();
+        let cap = self
+            .build_load_hash_table_field(hash_table, tid, CAP_FIELD, env)?
+            .into_int_value();
+        let is_full = self
+            .builder
+            .build_int_compare(IntPredicate::EQ, used, cap, "is_full")?;
+        self.builder
+            .build_conditional_branch(is_full, grow_block, continue_block)?;
+
+        // Double the capacity unless removing the deleted entries frees at least half of it
+        self.builder.position_at_end(grow_block);
+        let len = self
+            .build_load_hash_table_field(hash_table, tid, LEN_FIELD, env)?
+            .into_int_value();
+        let two = self.len_type().const_int(2, false);
+        let double_len = self.builder.build_int_mul(len, two, "double_len")?;
+        let should_double =
+            self.builder
+                .build_int_compare(IntPredicate::UGE, double_len, cap, "should_double")?;
+        let doubled_cap = self.builder.build_int_mul(cap, two, "doubled_cap")?;
+        let new_cap = self
+            .builder
+            .build_select(should_double, doubled_cap, cap, "new_cap")?
+            .into_int_value();
+        self.build_hash_table_rebuild(hash_table, tid, new_cap, env)?;
+        self.builder.build_unconditional_branch(continue_block)?;
+
+        self.builder.position_at_end(continue_block);
+
+        Ok(())
+    }
+
+    /// Moves the live entries of the hash table into new buffers with the given capacity.
+    fn build_hash_table_rebuild(
+        &mut self,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        new_cap: IntValue<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let old_entries = self
+            .build_load_hash_table_field(hash_table, tid, ENTRIES_FIELD, env)?
+            .into_pointer_value();
+        let old_table = self
+            .build_load_hash_table_field(hash_table, tid, TABLE_FIELD, env)?
+            .into_pointer_value();
+        let (new_entries, new_table) = self.build_hash_table_buffers(new_cap)?;
+
+        let count_var = self.builder.build_alloca(self.len_type(), "count_var")?;
+        self.builder
+            .build_store(count_var, self.len_type().const_zero())?;
+
+        let entry_type = self.hash_table_entry_type();
+        self.build_hash_table_entry_loop(
+            hash_table,
+            tid,
+            |gen, entry, env| {
+                let count = gen
+                    .builder
+                    .build_load(gen.len_type(), count_var, "count")?
+                    .into_int_value();
+
+                let entry_val = gen.builder.build_load(entry_type, entry, "entry_val")?;
+                let new_entry = unsafe {
+                    gen.builder
+                        .build_gep(entry_type, new_entries, &[count], "new_entry")?
+                };
+                gen.builder.build_store(new_entry, entry_val)?;
+
+                // Keys are unique, so the entry goes in the first empty slot
+                let hash = gen
+                    .build_load_entry_field(entry, ENTRY_HASH_FIELD)?
+                    .into_int_value();
+                let (slot, _) = gen.build_table_probe(
+                    new_table,
+                    new_cap,
+                    hash,
+                    |gen, _index, _env| Ok(gen.ctx.bool_type().const_zero()),
+                    env,
+                )?;
+                let slot_ptr = gen.build_table_slot_ptr(new_table, slot)?;
+                gen.builder.build_store(slot_ptr, count)?;
+
+                let next_count = gen.builder.build_int_add(
+                    count,
+                    gen.len_type().const_int(1, false),
+                    "next_count",
+                )?;
+                gen.builder.build_store(count_var, next_count)?;
+
+                Ok(())
+            },
+            env,
+        )?;
+
+        self.builder.build_free(old_entries)?;
+        self.builder.build_free(old_table)?;
+
+        let count = self
+            .builder
+            .build_load(self.len_type(), count_var, "count")?;
+        self.build_store_hash_table_field(hash_table, tid, ENTRIES_FIELD, new_entries, env)?;
+        self.build_store_hash_table_field(hash_table, tid, TABLE_FIELD, new_table, env)?;
+        self.build_store_hash_table_field(hash_table, tid, CAP_FIELD, new_cap, env)?;
+        self.build_store_hash_table_field(hash_table, tid, USED_FIELD, count, env)?;
+
+        Ok(())
+    }
+
+    /// Calls `body` with a pointer to every entry that has not been deleted, in insertion order.
+    pub(super) fn build_hash_table_entry_loop(
+        &mut self,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        body: impl FnOnce(&mut Self, PointerValue<'ctx>, &mut Environment<'ctx>) -> Result<(), GenError>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let used = self
+            .build_load_hash_table_field(hash_table, tid, USED_FIELD, env)?
+            .into_int_value();
+
+        self.build_index_loop(
+            used,
+            |gen, index, env| {
+                let cur_fn = gen
+                    .builder
+                    .get_insert_block()
+                    .unwrap()
+                    .get_parent()
+                    .unwrap();
+                let live_block = gen.ctx.append_basic_block(cur_fn, "live_entry");
+                let next_block = gen.ctx.append_basic_block(cur_fn, "next_entry");
+
+                let entry = gen.build_hash_table_entry_ptr(hash_table, tid, index, env)?;
+                let entry_key = gen.build_load_entry_ptr(entry, ENTRY_KEY_FIELD)?;
+                let is_live = gen.builder.build_is_not_null(entry_key, "is_live")?;
+                gen.builder
+                    .build_conditional_branch(is_live, live_block, next_block)?;
+
+                gen.builder.position_at_end(live_block);
+                body(gen, entry, env)?;
+                gen.builder.build_unconditional_branch(next_block)?;
+
+                gen.builder.position_at_end(next_block);
+
+                Ok(())
+            },
+            env,
+        )
+    }
+
+    /// Appends every entry to the string stored in `result_var` using `append_entry`, separated
+    /// by commas.
+    pub(super) fn build_append_hash_table_entries(
+        &mut self,
+        result_var: PointerValue<'ctx>,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        append_entry: impl FnOnce(
+            &mut Self,
+            PointerValue<'ctx>,
+            &mut Environment<'ctx>,
+        ) -> Result<(), GenError>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        // Deleted entries are skipped, so the first entry is tracked separately
+        let bool_type = self.ctx.bool_type();
+        let is_first_var = self.builder.build_alloca(bool_type, "is_first_var")?;
+        self.builder
+            .build_store(is_first_var, bool_type.const_int(1, false))?;
+
+        self.build_hash_table_entry_loop(
+            hash_table,
+            tid,
+            |gen, entry, env| {
+                let cur_fn = gen
+                    .builder
+                    .get_insert_block()
+                    .unwrap()
+                    .get_parent()
+                    .unwrap();
+                let separator_block = gen.ctx.append_basic_block(cur_fn, "separator");
+                let entry_block = gen.ctx.append_basic_block(cur_fn, "entry_str");
+
+                let is_first = gen
+                    .builder
+                    .build_load(bool_type, is_first_var, "is_first")?
+                    .into_int_value();
+                gen.builder
+                    .build_conditional_branch(is_first, entry_block, separator_block)?;
+
+                gen.builder.position_at_end(separator_block);
+                let separator = gen.build_str_const(", ", env)?;
+                gen.build_append_str(result_var, separator, env)?;
+                gen.builder.build_unconditional_branch(entry_block)?;
+
+                gen.builder.position_at_end(entry_block);
+                gen.builder
+                    .build_store(is_first_var, bool_type.const_zero())?;
+
+                append_entry(gen, entry, env)
+            },
+            env,
+        )
+    }
+
+    /// Checks whether the hash table contains a key, without consuming the key.
+    pub(super) fn build_hash_table_contains(
+        &mut self,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        key_ptr: PointerValue<'ctx>,
+        key: TypeId,
+        key_fns: KeyFns,
+        env: &mut Environment<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        let hash = self.build_hash(key_ptr, key, key_fns, env)?;
+        let (_, index) =
+            self.build_hash_table_lookup(hash_table, tid, key_ptr, hash, key, key_fns, env)?;
+
+        let is_missing = self.build_is_empty_slot(index)?;
+        Ok(self.builder.build_not(is_missing, "contains")?)
+    }
+
+    /// Adds a new entry for a key that is not in the hash table yet, taking ownership of the key
+    /// and value. The slot must come from a lookup made after reserving space for the entry.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn build_hash_table_insert(
+        &mut self,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        slot: IntValue<'ctx>,
+        hash: IntValue<'ctx>,
+        key_ptr: PointerValue<'ctx>,
+        value_ptr: PointerValue<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let used = self
+            .build_load_hash_table_field(hash_table, tid, USED_FIELD, env)?
+            .into_int_value();
+        let entry = self.build_hash_table_entry_ptr(hash_table, tid, used, env)?;
+        self.build_store_entry_field(entry, ENTRY_HASH_FIELD, hash)?;
+        self.build_store_entry_field(entry, ENTRY_KEY_FIELD, key_ptr)?;
+        self.build_store_entry_field(entry, ENTRY_VALUE_FIELD, value_ptr)?;
+
+        let table = self
+            .build_load_hash_table_field(hash_table, tid, TABLE_FIELD, env)?
+            .into_pointer_value();
+        let slot_ptr = self.build_table_slot_ptr(table, slot)?;
+        self.builder.build_store(slot_ptr, used)?;
+
+        self.build_add_to_hash_table_field(hash_table, tid, USED_FIELD, 1, env)?;
+        self.build_add_to_hash_table_field(hash_table, tid, LEN_FIELD, 1, env)
+    }
+
+    /// Marks an entry as deleted, after its key and value have been released.
+    ///
+    /// The entry stays in the table, so probing continues past it.
+    pub(super) fn build_hash_table_remove(
+        &mut self,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        entry: PointerValue<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        self.build_store_entry_field(entry, ENTRY_KEY_FIELD, self.ptr_type().const_null())?;
+        self.build_add_to_hash_table_field(hash_table, tid, LEN_FIELD, -1, env)
+    }
+
+    /// Loads the number of entries that have not been deleted.
+    pub(super) fn build_hash_table_len(
+        &mut self,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        Ok(self
+            .build_load_hash_table_field(hash_table, tid, LEN_FIELD, env)?
+            .into_int_value())
+    }
+
+    /// Frees the buffers of a hash table, after its keys and values have been released.
+    pub(super) fn build_free_hash_table_buffers(
+        &mut self,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let entries = self
+            .build_load_hash_table_field(hash_table, tid, ENTRIES_FIELD, env)?
+            .into_pointer_value();
+        self.builder.build_free(entries)?;
+        let table = self
+            .build_load_hash_table_field(hash_table, tid, TABLE_FIELD, env)?
+            .into_pointer_value();
+        self.builder.build_free(table)?;
+
+        Ok(())
+    }
+
+    pub(super) fn build_is_empty_slot(
+        &mut self,
+        index: IntValue<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        Ok(self.builder.build_int_compare(
+            IntPredicate::EQ,
+            index,
+            self.len_type().const_int(EMPTY_SLOT as u64, true),
+            "is_empty_slot",
+        )?)
+    }
+
+    fn build_slot_count(&mut self, cap: IntValue<'ctx>) -> Result<IntValue<'ctx>, GenError> {
+        Ok(self
+            .builder
+            .build_int_mul(cap, self.len_type().const_int(2, false), "slot_count")?)
+    }
+
+    fn build_table_slot_ptr(
+        &mut self,
+        table: PointerValue<'ctx>,
+        slot: IntValue<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        Ok(unsafe {
+            self.builder
+                .build_gep(self.len_type(), table, &[slot], "slot_ptr")?
+        })
+    }
+
+    pub(super) fn build_hash_table_entry_ptr(
+        &mut self,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        index: IntValue<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        let entries = self
+            .build_load_hash_table_field(hash_table, tid, ENTRIES_FIELD, env)?
+            .into_pointer_value();
+
+        Ok(unsafe {
+            self.builder
+                .build_gep(self.hash_table_entry_type(), entries, &[index], "entry_ptr")?
+        })
+    }
+
+    fn build_load_entry_field(
+        &mut self,
+        entry: PointerValue<'ctx>,
+        field: u32,
+    ) -> Result<BasicValueEnum<'ctx>, GenError> {
+        let entry_type = self.hash_table_entry_type();
+        let field_ptr =
+            self.builder
+                .build_struct_gep(entry_type, entry, field, "entry_field_ptr")?;
+
+        Ok(self.builder.build_load(
+            entry_type.get_field_type_at_index(field).unwrap(),
+            field_ptr,
+            "entry_field",
+        )?)
+    }
+
+    /// Loads the key or value of an entry without changing its reference count.
+    pub(super) fn build_load_entry_ptr(
+        &mut self,
+        entry: PointerValue<'ctx>,
+        field: u32,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        Ok(self
+            .build_load_entry_field(entry, field)?
+            .into_pointer_value())
+    }
+
+    pub(super) fn build_store_entry_field(
+        &mut self,
+        entry: PointerValue<'ctx>,
+        field: u32,
+        value: impl BasicValue<'ctx>,
+    ) -> Result<(), GenError> {
+        let field_ptr = self.builder.build_struct_gep(
+            self.hash_table_entry_type(),
+            entry,
+            field,
+            "entry_field_ptr",
+        )?;
+        self.builder.build_store(field_ptr, value)?;
+
+        Ok(())
+    }
+
+    fn build_load_hash_table_field(
+        &mut self,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        field: u32,
+        env: &mut Environment<'ctx>,
+    ) -> Result<BasicValueEnum<'ctx>, GenError> {
+        let hash_table_type = tid.get_from(env).ink();
+        let field_ptr = self.builder.build_struct_gep(
+            hash_table_type,
+            hash_table,
+            field,
+            "hash_table_field_ptr",
+        )?;
+
+        Ok(self.builder.build_load(
+            hash_table_type.get_field_type_at_index(field).unwrap(),
+            field_ptr,
+            "hash_table_field",
+        )?)
+    }
+
+    fn build_store_hash_table_field(
+        &mut self,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        field: u32,
+        value: impl BasicValue<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let field_ptr = self.builder.build_struct_gep(
+            tid.get_from(env).ink(),
+            hash_table,
+            field,
+            "hash_table_field_ptr",
+        )?;
+        self.builder.build_store(field_ptr, value)?;
+
+        Ok(())
+    }
+
+    fn build_add_to_hash_table_field(
+        &mut self,
+        hash_table: PointerValue<'ctx>,
+        tid: TypeId,
+        field: u32,
+        change: i64,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let old_val = self
+            .build_load_hash_table_field(hash_table, tid, field, env)?
+            .into_int_value();
+        let new_val = self.builder.build_int_add(
+            old_val,
+            self.len_type().const_int(change as u64, true),
+            "new_val",
+        )?;
+
+        self.build_store_hash_table_field(hash_table, tid, field, new_val, env)
+    }
+
+    /// An entry of a hash table, which holds the hash of its key, its key and its value.
+    fn hash_table_entry_type(&self) -> StructType<'ctx> {
+        self.ctx.struct_type(
+            &[
+                self.len_type().into(),
+                self.ptr_type().into(),
+                self.ptr_type().into(),
+            ],
+            false,
+        )
+    }
+}
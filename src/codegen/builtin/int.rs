@@ -1,4 +1,8 @@
-use inkwell::{types::IntType, values::BasicValue};
+use inkwell::{
+    intrinsics::Intrinsic,
+    types::IntType,
+    values::{BasicValue, FunctionValue, IntValue},
+};
 
 use crate::{
     codegen::{
@@ -13,7 +17,7 @@ use crate::{
     parser::{BinaryFnOp, UnaryFnOp},
 };
 
-use super::{llvm_resources::LLVMResources, primitive_unalloc};
+use super::{primitive_unalloc, HASH_FN, TO_BOOL_FN};
 
 pub const INT_NAME: &str = "int";
 
@@ -28,11 +32,7 @@ impl<'ctx> CodeGen<'ctx> {
         Ok(())
     }
 
-    pub fn setup_int_primitive(
-        &mut self,
-        res: &LLVMResources<'ctx>,
-        env: &mut Environment<'ctx>,
-    ) -> Result<(), GenError> {
+    pub fn setup_int_primitive(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
         self.build_free_ptr_fn(INT_ID, primitive_unalloc, env)?;
         self.build_copy_ptr_fn(INT_ID, env)?;
         self.build_get_reference_count_fn(INT_ID, env)?;
@@ -42,23 +42,33 @@ impl<'ctx> CodeGen<'ctx> {
         self.setup_int_sub_int(env)?;
         self.setup_int_mul_int(env)?;
         self.setup_int_div_int(env)?;
+        self.setup_int_eq_int(env)?;
+        self.setup_int_ne_int(env)?;
         self.setup_int_gt_int(env)?;
         self.setup_int_lt_int(env)?;
         self.setup_int_ge_int(env)?;
         self.setup_int_le_int(env)?;
         self.setup_int_pow_int(env)?;
         self.setup_int_mod_int(env)?;
+        self.setup_int_and_int(env)?;
+        self.setup_int_or_int(env)?;
+        self.setup_int_xor_int(env)?;
 
         // Unary
         self.setup_negate_int(env)?;
 
         // Conversion
-        self.setup_int_to_str(res, env)?;
+        self.setup_int_to_bool(env)?;
+
+        // Hashing
+        self.setup_int_hash(env)?;
 
         Ok(())
     }
 
     fn setup_int_add_int(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        let checked_add = self.get_checked_int_intrinsic(CheckedIntOp::Add, env)?;
+
         self.create_primitive_binary_fn(
             BinaryFnOp::Add.fn_name(),
             INT_ID,
@@ -66,8 +76,12 @@ impl<'ctx> CodeGen<'ctx> {
             INT_ID,
             |gen, left, right| {
                 Ok(gen
-                    .builder
-                    .build_int_add(left.into_int_value(), right.into_int_value(), "int_add")?
+                    .build_int_arith(
+                        CheckedIntOp::Add,
+                        checked_add,
+                        left.into_int_value(),
+                        right.into_int_value(),
+                    )?
                     .as_basic_value_enum())
             },
             env,
@@ -75,6 +89,8 @@ impl<'ctx> CodeGen<'ctx> {
     }
 
     fn setup_int_sub_int(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        let checked_sub = self.get_checked_int_intrinsic(CheckedIntOp::Sub, env)?;
+
         self.create_primitive_binary_fn(
             BinaryFnOp::Subtract.fn_name(),
             INT_ID,
@@ -82,8 +98,12 @@ impl<'ctx> CodeGen<'ctx> {
             INT_ID,
             |gen, left, right| {
                 Ok(gen
-                    .builder
-                    .build_int_sub(left.into_int_value(), right.into_int_value(), "int_sub")?
+                    .build_int_arith(
+                        CheckedIntOp::Sub,
+                        checked_sub,
+                        left.into_int_value(),
+                        right.into_int_value(),
+                    )?
                     .as_basic_value_enum())
             },
             env,
@@ -91,6 +111,8 @@ impl<'ctx> CodeGen<'ctx> {
     }
 
     fn setup_int_mul_int(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        let checked_mul = self.get_checked_int_intrinsic(CheckedIntOp::Mul, env)?;
+
         self.create_primitive_binary_fn(
             BinaryFnOp::Multiply.fn_name(),
             INT_ID,
@@ -98,8 +120,12 @@ impl<'ctx> CodeGen<'ctx> {
             INT_ID,
             |gen, left, right| {
                 Ok(gen
-                    .builder
-                    .build_int_mul(left.into_int_value(), right.into_int_value(), "int_mul")?
+                    .build_int_arith(
+                        CheckedIntOp::Mul,
+                        checked_mul,
+                        left.into_int_value(),
+                        right.into_int_value(),
+                    )?
                     .as_basic_value_enum())
             },
             env,
@@ -124,6 +150,8 @@ impl<'ctx> CodeGen<'ctx> {
     }
 
     fn setup_int_pow_int(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        let checked_mul = self.get_checked_int_intrinsic(CheckedIntOp::Mul, env)?;
+
         self.create_primitive_binary_fn(
             BinaryFnOp::Exponentiate.fn_name(),
             INT_ID,
@@ -205,28 +233,51 @@ impl<'ctx> CodeGen<'ctx> {
                     .build_conditional_branch(is_odd, odd_exp_block, cont_block)?;
 
                 gen.builder.position_at_end(odd_exp_block);
-                let new_result = gen.builder.build_int_mul(
+                let new_result = gen.build_int_arith(
+                    CheckedIntOp::Pow,
+                    checked_mul,
                     current_result.into_int_value(),
                     current_base.into_int_value(),
-                    "new_result",
                 )?;
                 gen.builder.build_store(result, new_result)?;
                 gen.builder.build_unconditional_branch(cont_block)?;
 
                 gen.builder.position_at_end(cont_block);
-                let new_base = gen.builder.build_int_mul(
-                    current_base.into_int_value(),
-                    current_base.into_int_value(),
-                    "new_base",
-                )?;
-                gen.builder.build_store(base_var, new_base)?;
-
                 let new_exp = gen.builder.build_int_signed_div(
                     current_exp.into_int_value(),
                     gen.prim_int_type().const_int(2, false),
                     "new_exp",
                 )?;
                 gen.builder.build_store(exp_var, new_exp)?;
+
+                let new_base = match checked_mul {
+                    Some(checked_mul) => {
+                        // The base is squared once more than needed, so it may only overflow
+                        // if there is another iteration that uses it.
+                        let (new_base, overflowed) = gen.build_overflow_intrinsic_call(
+                            checked_mul,
+                            current_base.into_int_value(),
+                            current_base.into_int_value(),
+                        )?;
+                        let base_used = gen.builder.build_int_compare(
+                            inkwell::IntPredicate::SGT,
+                            new_exp,
+                            gen.prim_int_type().const_int(0, false),
+                            "base_used",
+                        )?;
+                        let overflowed =
+                            gen.builder
+                                .build_and(overflowed, base_used, "base_overflowed")?;
+                        gen.build_overflow_check(CheckedIntOp::Pow, overflowed)?;
+                        new_base
+                    }
+                    None => gen.builder.build_int_mul(
+                        current_base.into_int_value(),
+                        current_base.into_int_value(),
+                        "new_base",
+                    )?,
+                };
+                gen.builder.build_store(base_var, new_base)?;
                 gen.builder.build_unconditional_branch(loop_block)?;
 
                 gen.builder.position_at_end(exit_block);
@@ -255,6 +306,96 @@ impl<'ctx> CodeGen<'ctx> {
         )
     }
 
+    fn setup_int_and_int(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_binary_fn(
+            BinaryFnOp::BitAnd.fn_name(),
+            INT_ID,
+            INT_ID,
+            INT_ID,
+            |gen, left, right| {
+                Ok(gen
+                    .builder
+                    .build_and(left.into_int_value(), right.into_int_value(), "int_and")?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
+    fn setup_int_or_int(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_binary_fn(
+            BinaryFnOp::BitOr.fn_name(),
+            INT_ID,
+            INT_ID,
+            INT_ID,
+            |gen, left, right| {
+                Ok(gen
+                    .builder
+                    .build_or(left.into_int_value(), right.into_int_value(), "int_or")?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
+    fn setup_int_xor_int(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_binary_fn(
+            BinaryFnOp::BitXor.fn_name(),
+            INT_ID,
+            INT_ID,
+            INT_ID,
+            |gen, left, right| {
+                Ok(gen
+                    .builder
+                    .build_xor(left.into_int_value(), right.into_int_value(), "int_xor")?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
+    fn setup_int_eq_int(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_binary_fn(
+            BinaryFnOp::Equal.fn_name(),
+            INT_ID,
+            INT_ID,
+            BOOL_ID,
+            |gen, left, right| {
+                Ok(gen
+                    .builder
+                    .build_int_compare(
+                        inkwell::IntPredicate::EQ,
+                        left.into_int_value(),
+                        right.into_int_value(),
+                        "int_eq",
+                    )?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
+    fn setup_int_ne_int(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_binary_fn(
+            BinaryFnOp::NotEqual.fn_name(),
+            INT_ID,
+            INT_ID,
+            BOOL_ID,
+            |gen, left, right| {
+                Ok(gen
+                    .builder
+                    .build_int_compare(
+                        inkwell::IntPredicate::NE,
+                        left.into_int_value(),
+                        right.into_int_value(),
+                        "int_ne",
+                    )?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
     fn setup_int_gt_int(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
         self.create_primitive_binary_fn(
             BinaryFnOp::Greater.fn_name(),
@@ -340,29 +481,166 @@ impl<'ctx> CodeGen<'ctx> {
     }
 
     fn setup_negate_int(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        let checked_sub = self.get_checked_int_intrinsic(CheckedIntOp::Neg, env)?;
+
         self.create_primitive_unary_fn(
             UnaryFnOp::Negate.fn_name(),
             INT_ID,
             INT_ID,
+            |gen, expr| {
+                Ok(gen
+                    .build_int_arith(
+                        CheckedIntOp::Neg,
+                        checked_sub,
+                        gen.prim_int_type().const_zero(),
+                        expr.into_int_value(),
+                    )?
+                    .as_basic_value_enum())
+            },
+            env,
+        )
+    }
+
+    fn setup_int_to_bool(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_primitive_unary_fn(
+            TO_BOOL_FN,
+            INT_ID,
+            BOOL_ID,
             |gen, expr| {
                 Ok(gen
                     .builder
-                    .build_int_neg(expr.into_int_value(), "int_neg")?
+                    .build_int_compare(
+                        inkwell::IntPredicate::NE,
+                        expr.into_int_value(),
+                        gen.prim_int_type().const_zero(),
+                        "int_to_bool",
+                    )?
                     .as_basic_value_enum())
             },
             env,
         )
     }
 
-    fn setup_int_to_str(
-        &mut self,
-        res: &LLVMResources<'ctx>,
-        env: &mut Environment<'ctx>,
-    ) -> Result<(), GenError> {
-        self.create_primitive_to_str_fn(INT_ID, "%ld", res, env)
+    fn setup_int_hash(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        // Like Python, an int is its own hash
+        self.create_primitive_unary_fn(HASH_FN, INT_ID, INT_ID, |_gen, expr| Ok(expr), env)
     }
 
     pub fn prim_int_type(&self) -> IntType<'ctx> {
         self.ctx.i64_type()
     }
+
+    /// Returns the overflow intrinsic for the operation if overflow checking is enabled.
+    fn get_checked_int_intrinsic(
+        &self,
+        op: CheckedIntOp,
+        env: &Environment<'ctx>,
+    ) -> Result<Option<FunctionValue<'ctx>>, GenError> {
+        if !self.options.checked_overflow {
+            return Ok(None);
+        }
+
+        let intrinsic = Intrinsic::find(op.intrinsic_name()).ok_or(GenError::FunctionNotFound)?;
+        let fn_val = intrinsic
+            .get_declaration(env.module(), &[self.prim_int_type().into()])
+            .ok_or(GenError::FunctionNotFound)?;
+
+        Ok(Some(fn_val))
+    }
+
+    /// Builds a wrapping int operation, or a checked one if given an overflow intrinsic.
+    fn build_int_arith(
+        &mut self,
+        op: CheckedIntOp,
+        checked_fn: Option<FunctionValue<'ctx>>,
+        left: IntValue<'ctx>,
+        right: IntValue<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        if let Some(checked_fn) = checked_fn {
+            let (result, overflowed) =
+                self.build_overflow_intrinsic_call(checked_fn, left, right)?;
+            self.build_overflow_check(op, overflowed)?;
+            return Ok(result);
+        }
+
+        let result = match op {
+            CheckedIntOp::Add => self.builder.build_int_add(left, right, "int_add")?,
+            CheckedIntOp::Sub | CheckedIntOp::Neg => {
+                self.builder.build_int_sub(left, right, "int_sub")?
+            }
+            CheckedIntOp::Mul | CheckedIntOp::Pow => {
+                self.builder.build_int_mul(left, right, "int_mul")?
+            }
+        };
+
+        Ok(result)
+    }
+
+    /// Calls an `llvm.*.with.overflow` intrinsic, returning the result and overflow flag.
+    fn build_overflow_intrinsic_call(
+        &mut self,
+        checked_fn: FunctionValue<'ctx>,
+        left: IntValue<'ctx>,
+        right: IntValue<'ctx>,
+    ) -> Result<(IntValue<'ctx>, IntValue<'ctx>), GenError> {
+        let ret = self
+            .builder
+            .build_call(checked_fn, &[left.into(), right.into()], "checked_op")?
+            .try_as_basic_value()
+            .unwrap_left()
+            .into_struct_value();
+
+        let result = self
+            .builder
+            .build_extract_value(ret, 0, "result")?
+            .into_int_value();
+        let overflowed = self
+            .builder
+            .build_extract_value(ret, 1, "overflowed")?
+            .into_int_value();
+
+        Ok((result, overflowed))
+    }
+
+    /// Aborts with a runtime error naming the operation if `overflowed` is true.
+    fn build_overflow_check(
+        &mut self,
+        op: CheckedIntOp,
+        overflowed: IntValue<'ctx>,
+    ) -> Result<(), GenError> {
+        self.build_runtime_check(
+            overflowed,
+            &format!("integer overflow in `{}`", op.symbol()),
+        )
+    }
+}
+
+/// The int operations that can overflow.
+#[derive(Clone, Copy)]
+enum CheckedIntOp {
+    Add,
+    Sub,
+    Mul,
+    Pow,
+    Neg,
+}
+
+impl CheckedIntOp {
+    fn intrinsic_name(&self) -> &str {
+        match self {
+            CheckedIntOp::Add => "llvm.sadd.with.overflow",
+            CheckedIntOp::Sub | CheckedIntOp::Neg => "llvm.ssub.with.overflow",
+            CheckedIntOp::Mul | CheckedIntOp::Pow => "llvm.smul.with.overflow",
+        }
+    }
+
+    fn symbol(&self) -> &str {
+        match self {
+            CheckedIntOp::Add => "+",
+            CheckedIntOp::Sub => "-",
+            CheckedIntOp::Mul => "*",
+            CheckedIntOp::Pow => "**",
+            CheckedIntOp::Neg => "unary -",
+        }
+    }
 }
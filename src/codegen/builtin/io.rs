@@ -0,0 +1,143 @@
+use inkwell::values::PointerValue;
+
+use crate::{
+    codegen::{
+        env::{
+            id::{TypeId, NONE_ID, STR_ID},
+            type_def::TypeDef,
+            Environment,
+        },
+        err::GenError,
+        CodeGen,
+    },
+    parser::BinaryFnOp,
+};
+
+use super::{primitive_unalloc, TO_STR_FN};
+
+pub const FILE_NAME: &str = "file";
+pub const WRITE_FN: &str = "write";
+
+/// The files that are always open, which programs refer to by name, with their file descriptors.
+const STD_FILES: [(&str, u64); 2] = [("stdout", 1), ("stderr", 2)];
+
+/// The arguments of a call to `print`, where missing keyword arguments take Python's defaults.
+pub(in crate::codegen) struct PrintArgs<'ctx> {
+    pub values: Vec<(PointerValue<'ctx>, TypeId)>,
+    pub sep: Option<PointerValue<'ctx>>,
+    pub end: Option<PointerValue<'ctx>>,
+    pub file: Option<PointerValue<'ctx>>,
+}
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Sets up the functions that read from stdin, along with the `file` type of `stdout` and
+    /// `stderr`, which holds a file descriptor and is written to by the runtime.
+    pub(super) fn setup_io(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        // Unlike `input`, `read_line` returns None at the end of the input. All of them abort if
+        // stdin cannot be read.
+        let line_tid = self.optional_of(STR_ID, env)?;
+        let read_fns = [
+            env.declare_runtime_func(None, "input", "rt_input", &[], STR_ID)?,
+            env.declare_runtime_func(None, "input", "rt_input_prompt", &[STR_ID], STR_ID)?,
+            env.declare_runtime_func(None, "read_line", "rt_read_line", &[], line_tid)?,
+            env.declare_runtime_func(None, "read_all", "rt_read_all", &[], STR_ID)?,
+        ];
+        for fn_id in read_fns {
+            self.mark_fallible(env.get_func(fn_id).ink());
+        }
+
+        let struct_type = self.create_struct_type(FILE_NAME, vec![self.prim_int_type().into()]);
+        let tid = env.gen_type_id();
+        env.register_type(FILE_NAME, tid, TypeDef::new_prim(FILE_NAME, struct_type))?;
+
+        self.build_free_ptr_fn(tid, primitive_unalloc, env)?;
+        self.build_copy_ptr_fn(tid, env)?;
+        self.build_get_reference_count_fn(tid, env)?;
+
+        let write_fn = env.declare_runtime_func(
+            Some(tid),
+            WRITE_FN,
+            "rt_file_write",
+            &[tid, STR_ID],
+            NONE_ID,
+        )?;
+        self.mark_fallible(env.get_func(write_fn).ink());
+
+        Ok(())
+    }
+
+    /// Builds one of the files that are always open, such as `stderr`, or returns `None` if
+    /// there is no such file.
+    pub(in crate::codegen) fn build_std_file(
+        &mut self,
+        ident: &str,
+        env: &mut Environment<'ctx>,
+    ) -> Result<Option<(PointerValue<'ctx>, TypeId)>, GenError> {
+        let Some((_, fd)) = STD_FILES.iter().find(|(name, _)| *name == ident) else {
+            return Ok(None);
+        };
+
+        let tid = env.find_type(FILE_NAME)?;
+        let fd = self.prim_int_type().const_int(*fd, false);
+        let file = self.build_struct(tid.get_from(env).ink(), vec![fd.into()])?;
+
+        Ok(Some((file, tid)))
+    }
+
+    /// Builds a call to `print`, which converts every value to a string and writes them to the
+    /// file separated by `sep` and followed by `end`. Takes ownership of the arguments.
+    pub(in crate::codegen) fn build_print(
+        &mut self,
+        args: PrintArgs<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let str_add_fn =
+            env.find_func(BinaryFnOp::Add.fn_name(), Some(STR_ID), &[STR_ID, STR_ID])?;
+
+        let sep = match args.sep {
+            Some(sep) => sep,
+            None => self.build_str_const(" ", env)?,
+        };
+        let end = match args.end {
+            Some(end) => end,
+            None => self.build_str_const("\n", env)?,
+        };
+        let file = match args.file {
+            Some(file) => file,
+            None => self.build_std_file("stdout", env)?.unwrap().0,
+        };
+
+        let mut text = self.build_str_const("", env)?;
+        for (i, (value, tid)) in args.values.into_iter().enumerate() {
+            if i > 0 {
+                self.copy_pointer(sep, STR_ID, env)?;
+                text = self.call_func(str_add_fn, &[text, sep], env)?.0;
+            }
+
+            let value_str = self.build_to_str(value, tid, env)?;
+            text = self.call_func(str_add_fn, &[text, value_str], env)?.0;
+        }
+        text = self.call_func(str_add_fn, &[text, end], env)?.0;
+        self.free_pointer(sep, STR_ID, env)?;
+
+        let file_tid = env.find_type(FILE_NAME)?;
+        let write_fn = env.find_func(WRITE_FN, Some(file_tid), &[file_tid, STR_ID])?;
+        self.call_func(write_fn, &[file, text], env)
+    }
+
+    /// Converts a value to a string with its `__str__` method, taking ownership of it.
+    pub(in crate::codegen) fn build_to_str(
+        &mut self,
+        value: PointerValue<'ctx>,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        // Strings are used as they are, since str has no `__str__`
+        if tid == STR_ID {
+            return Ok(value);
+        }
+
+        let to_str_fn = self.find_method(TO_STR_FN, tid, &[tid], env)?;
+        Ok(self.call_func(to_str_fn, &[value], env)?.0)
+    }
+}
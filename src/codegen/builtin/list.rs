@@ -0,0 +1,1045 @@
+use inkwell::{
+    values::{BasicValueEnum, FunctionValue, IntValue, PointerValue},
+    IntPredicate,
+};
+
+use crate::{
+    codegen::{
+        env::{
+            id::{FunctionId, TypeId, BOOL_ID, INT_ID, NONE_ID, STR_ID},
+            type_def::{TypeDef, TypeInfo},
+            Environment,
+        },
+        err::GenError,
+        CodeGen,
+    },
+    parser::BinaryFnOp,
+};
+
+use super::{
+    slice::SLICE_NAME, CONTAINS_FN, DEL_ITEM_FN, GET_ITEM_FN, LEN_FN, SET_ITEM_FN, TO_BOOL_FN,
+    TO_STR_FN,
+};
+
+pub const LIST_NAME: &str = "list";
+
+// Fields of the list struct, which is followed by the reference count like every other type
+const DATA_FIELD: u32 = 0;
+const LEN_FIELD: u32 = 1;
+const CAP_FIELD: u32 = 2;
+
+const MIN_CAPACITY: u64 = 4;
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Returns the list type with the given element type, creating it if it does not exist yet.
+    ///
+    /// A list stores pointers to its elements in a growable buffer. Only the pointer functions
+    /// are created here, all other methods are created once they are used.
+    pub(in crate::codegen) fn list_of(
+        &mut self,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<TypeId, GenError> {
+        let ident = format!("{}[{}]", LIST_NAME, env.type_id_ident(elem));
+        if let Ok(tid) = env.find_type(&ident) {
+            return Ok(tid);
+        }
+
+        let struct_type = self.create_struct_type(
+            &ident,
+            vec![
+                self.ptr_type().into(),
+                self.len_type().into(),
+                self.len_type().into(),
+            ],
+        );
+        let tid = env.gen_type_id();
+        env.register_type(
+            &ident,
+            tid,
+            TypeDef::new_generic(&ident, struct_type, TypeInfo::List(elem)),
+        )?;
+
+        let prev_block = self.builder.get_insert_block().unwrap();
+
+        self.build_free_ptr_fn(tid, list_unalloc, env)?;
+        self.build_copy_ptr_fn(tid, env)?;
+        self.build_get_reference_count_fn(tid, env)?;
+
+        self.builder.position_at_end(prev_block);
+
+        Ok(tid)
+    }
+
+    /// Builds a list containing the given elements, taking ownership of them.
+    pub(in crate::codegen) fn build_list(
+        &mut self,
+        tid: TypeId,
+        elems: &[PointerValue<'ctx>],
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        let len = self.len_type().const_int(elems.len() as u64, false);
+
+        let data = if elems.is_empty() {
+            self.ptr_type().const_null()
+        } else {
+            self.builder
+                .build_array_malloc(self.ptr_type(), len, "list_data")?
+        };
+
+        for (i, elem) in elems.iter().enumerate() {
+            let index = self.len_type().const_int(i as u64, false);
+            let elem_ptr = self.build_list_elem_ptr(data, index)?;
+            self.builder.build_store(elem_ptr, *elem)?;
+        }
+
+        self.build_struct(
+            tid.get_from(env).ink(),
+            vec![data.into(), len.into(), len.into()],
+        )
+    }
+
+    /// Creates the method of a list type with the given name, returning false if lists do not
+    /// have a method with that name.
+    pub(super) fn create_list_method(
+        &mut self,
+        ident: &str,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<bool, GenError> {
+        match ident {
+            LEN_FN => self.setup_list_len(tid, env)?,
+            TO_BOOL_FN => self.setup_list_to_bool(tid, env)?,
+            TO_STR_FN => self.setup_list_to_str(tid, elem, env)?,
+            GET_ITEM_FN => self.setup_list_get_item(tid, elem, env)?,
+            SET_ITEM_FN => self.setup_list_set_item(tid, elem, env)?,
+            DEL_ITEM_FN => self.setup_list_del_item(tid, elem, env)?,
+            CONTAINS_FN => self.setup_list_contains(tid, elem, env)?,
+            "append" => self.setup_list_append(tid, elem, env)?,
+            "insert" => self.setup_list_insert(tid, elem, env)?,
+            "pop" => self.setup_list_pop(tid, elem, env)?,
+            _ if ident == BinaryFnOp::Equal.fn_name() => self.setup_list_eq(tid, elem, env)?,
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn setup_list_len(&mut self, tid: TypeId, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_unary_fn(
+            LEN_FN,
+            tid,
+            INT_ID,
+            true,
+            |gen, _fn_val, list, env| {
+                let len = gen.build_load_list_field(list, tid, LEN_FIELD, env)?;
+                gen.build_struct(INT_ID.get_from(env).ink(), vec![len])
+            },
+            env,
+        )
+    }
+
+    fn setup_list_to_bool(
+        &mut self,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        self.create_unary_fn(
+            TO_BOOL_FN,
+            tid,
+            BOOL_ID,
+            true,
+            |gen, _fn_val, list, env| {
+                let len = gen
+                    .build_load_list_field(list, tid, LEN_FIELD, env)?
+                    .into_int_value();
+                let not_empty = gen.builder.build_int_compare(
+                    IntPredicate::NE,
+                    len,
+                    gen.len_type().const_zero(),
+                    "not_empty",
+                )?;
+                gen.build_struct(BOOL_ID.get_from(env).ink(), vec![not_empty.into()])
+            },
+            env,
+        )
+    }
+
+    fn setup_list_to_str(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let elem_to_str_fn = self.find_method(TO_STR_FN, elem, &[elem], env)?;
+
+        self.create_unary_fn(
+            TO_STR_FN,
+            tid,
+            STR_ID,
+            true,
+            |gen, _fn_val, list, env| {
+                let result_var = gen.builder.build_alloca(gen.ptr_type(), "result_var")?;
+                let open = gen.build_str_const("[", env)?;
+                gen.builder.build_store(result_var, open)?;
+
+                let len = gen
+                    .build_load_list_field(list, tid, LEN_FIELD, env)?
+                    .into_int_value();
+                gen.build_index_loop(
+                    len,
+                    |gen, index, env| {
+                        let cur_fn = gen
+                            .builder
+                            .get_insert_block()
+                            .unwrap()
+                            .get_parent()
+                            .unwrap();
+                        let separator_block = gen.ctx.append_basic_block(cur_fn, "separator");
+                        let elem_block = gen.ctx.append_basic_block(cur_fn, "elem");
+
+                        let is_first = gen.builder.build_int_compare(
+                            IntPredicate::EQ,
+                            index,
+                            gen.len_type().const_zero(),
+                            "is_first",
+                        )?;
+                        gen.builder.build_conditional_branch(
+                            is_first,
+                            elem_block,
+                            separator_block,
+                        )?;
+
+                        gen.builder.position_at_end(separator_block);
+                        let separator = gen.build_str_const(", ", env)?;
+                        gen.build_append_str(result_var, separator, env)?;
+                        gen.builder.build_unconditional_branch(elem_block)?;
+
+                        gen.builder.position_at_end(elem_block);
+                        let elem_ptr = gen.build_load_list_elem(list, tid, index, env)?;
+                        gen.build_append_repr(result_var, elem_ptr, elem, elem_to_str_fn, env)
+                    },
+                    env,
+                )?;
+
+                let close = gen.build_str_const("]", env)?;
+                gen.build_append_str(result_var, close, env)?;
+
+                let result = gen
+                    .builder
+                    .build_load(gen.ptr_type(), result_var, "result")?
+                    .into_pointer_value();
+                Ok(result)
+            },
+            env,
+        )
+    }
+
+    fn setup_list_get_item(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        self.create_binary_fn(
+            GET_ITEM_FN,
+            tid,
+            INT_ID,
+            elem,
+            true,
+            |gen, list, index, env| {
+                let index = gen
+                    .extract_primitive(index, INT_ID.get_from(env).ink())?
+                    .into_int_value();
+                let index = gen.build_list_index(list, tid, index, env)?;
+
+                let elem_ptr = gen.build_load_list_elem(list, tid, index, env)?;
+                gen.copy_pointer(elem_ptr, elem, env)
+            },
+            env,
+        )?;
+
+        // Slicing copies the selected elements into a new list
+        let slice_tid = env.find_type(SLICE_NAME)?;
+        self.create_binary_fn(
+            GET_ITEM_FN,
+            tid,
+            slice_tid,
+            tid,
+            true,
+            |gen, list, slice, env| {
+                let len = gen
+                    .build_load_list_field(list, tid, LEN_FIELD, env)?
+                    .into_int_value();
+                let range = gen.build_slice_range(slice, slice_tid, len, env)?;
+
+                let data =
+                    gen.builder
+                        .build_array_malloc(gen.ptr_type(), range.len, "list_data")?;
+                gen.build_index_loop(
+                    range.len,
+                    |gen, offset, env| {
+                        let index = gen.build_slice_index(&range, offset)?;
+                        let elem_ptr = gen.build_load_list_elem(list, tid, index, env)?;
+                        gen.copy_pointer(elem_ptr, elem, env)?;
+
+                        let new_elem_ptr = gen.build_list_elem_ptr(data, offset)?;
+                        gen.builder.build_store(new_elem_ptr, elem_ptr)?;
+
+                        Ok(())
+                    },
+                    env,
+                )?;
+
+                gen.build_struct(
+                    tid.get_from(env).ink(),
+                    vec![data.into(), range.len.into(), range.len.into()],
+                )
+            },
+            env,
+        )
+    }
+
+    fn setup_list_set_item(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let (fn_val, ..) =
+            env.create_func(Some(tid), SET_ITEM_FN, &[tid, INT_ID, elem], NONE_ID, false)?;
+        let (list, index, value) = self.build_ternary_fn_entry(fn_val);
+
+        let index_val = self
+            .extract_primitive(index, INT_ID.get_from(env).ink())?
+            .into_int_value();
+        let index_val = self.build_list_index(list, tid, index_val, env)?;
+
+        // The list takes ownership of the new value and releases the old one
+        let old_value = self.build_load_list_elem(list, tid, index_val, env)?;
+        self.free_pointer(old_value, elem, env)?;
+
+        let data = self
+            .build_load_list_field(list, tid, DATA_FIELD, env)?
+            .into_pointer_value();
+        let elem_ptr = self.build_list_elem_ptr(data, index_val)?;
+        self.builder.build_store(elem_ptr, value)?;
+
+        self.free_pointer(list, tid, env)?;
+        self.free_pointer(index, INT_ID, env)?;
+
+        let none = self.build_none(env)?;
+        self.builder.build_return(Some(&none))?;
+
+        Ok(())
+    }
+
+    fn setup_list_del_item(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let pop_fn = self.find_method("pop", tid, &[tid, INT_ID], env)?;
+
+        // Deleting an index removes the element like pop(i) and releases it
+        self.create_binary_fn(
+            DEL_ITEM_FN,
+            tid,
+            INT_ID,
+            NONE_ID,
+            false, // pop(i) takes ownership of the list and index
+            |gen, list, index, env| {
+                let (value, value_tid) = gen.call_func(pop_fn, &[list, index], env)?;
+                gen.free_pointer(value, value_tid, env)?;
+                gen.build_none(env)
+            },
+            env,
+        )?;
+
+        // Deleting a slice releases the selected elements and moves the rest over them
+        let slice_tid = env.find_type(SLICE_NAME)?;
+        self.create_binary_fn(
+            DEL_ITEM_FN,
+            tid,
+            slice_tid,
+            NONE_ID,
+            true,
+            |gen, list, slice, env| {
+                let len = gen
+                    .build_load_list_field(list, tid, LEN_FIELD, env)?
+                    .into_int_value();
+                let range = gen.build_slice_range(slice, slice_tid, len, env)?;
+                let data = gen
+                    .build_load_list_field(list, tid, DATA_FIELD, env)?
+                    .into_pointer_value();
+
+                let kept_var = gen.builder.build_alloca(gen.len_type(), "kept_var")?;
+                gen.builder
+                    .build_store(kept_var, gen.len_type().const_zero())?;
+
+                gen.build_index_loop(
+                    len,
+                    |gen, index, env| {
+                        let cur_fn = gen
+                            .builder
+                            .get_insert_block()
+                            .unwrap()
+                            .get_parent()
+                            .unwrap();
+                        let remove_block = gen.ctx.append_basic_block(cur_fn, "remove_elem");
+                        let keep_block = gen.ctx.append_basic_block(cur_fn, "keep_elem");
+                        let next_block = gen.ctx.append_basic_block(cur_fn, "next_elem");
+
+                        let elem_ptr = gen.build_load_list_elem(list, tid, index, env)?;
+                        let is_removed = gen.build_slice_contains(&range, index)?;
+                        gen.builder.build_conditional_branch(
+                            is_removed,
+                            remove_block,
+                            keep_block,
+                        )?;
+
+                        gen.builder.position_at_end(remove_block);
+                        gen.free_pointer(elem_ptr, elem, env)?;
+                        gen.builder.build_unconditional_branch(next_block)?;
+
+                        gen.builder.position_at_end(keep_block);
+                        let kept = gen
+                            .builder
+                            .build_load(gen.len_type(), kept_var, "kept")?
+                            .into_int_value();
+                        let kept_elem_ptr = gen.build_list_elem_ptr(data, kept)?;
+                        gen.builder.build_store(kept_elem_ptr, elem_ptr)?;
+                        let kept = gen.builder.build_int_add(
+                            kept,
+                            gen.len_type().const_int(1, false),
+                            "kept",
+                        )?;
+                        gen.builder.build_store(kept_var, kept)?;
+                        gen.builder.build_unconditional_branch(next_block)?;
+
+                        gen.builder.position_at_end(next_block);
+                        Ok(())
+                    },
+                    env,
+                )?;
+
+                let kept = gen
+                    .builder
+                    .build_load(gen.len_type(), kept_var, "kept")?
+                    .into_int_value();
+                gen.build_store_list_len(list, tid, kept, 0, env)?;
+
+                gen.build_none(env)
+            },
+            env,
+        )
+    }
+
+    fn setup_list_contains(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let eq_fn = self.find_method(BinaryFnOp::Equal.fn_name(), elem, &[elem, elem], env)?;
+
+        self.create_binary_fn(
+            CONTAINS_FN,
+            tid,
+            elem,
+            BOOL_ID,
+            true,
+            |gen, list, value, env| {
+                let cur_fn = gen
+                    .builder
+                    .get_insert_block()
+                    .unwrap()
+                    .get_parent()
+                    .unwrap();
+                let found_block = gen.ctx.append_basic_block(cur_fn, "found");
+
+                let len = gen
+                    .build_load_list_field(list, tid, LEN_FIELD, env)?
+                    .into_int_value();
+                gen.build_index_loop(
+                    len,
+                    |gen, index, env| {
+                        let is_eq =
+                            gen.build_list_elem_eq(list, tid, index, value, elem, eq_fn, env)?;
+
+                        let continue_block = gen.ctx.append_basic_block(cur_fn, "not_found");
+                        gen.builder
+                            .build_conditional_branch(is_eq, found_block, continue_block)?;
+                        gen.builder.position_at_end(continue_block);
+
+                        Ok(())
+                    },
+                    env,
+                )?;
+
+                let after_loop_block = gen.builder.get_insert_block().unwrap();
+                let result_block = gen.ctx.append_basic_block(cur_fn, "contains_result");
+                gen.builder.build_unconditional_branch(result_block)?;
+
+                gen.builder.position_at_end(found_block);
+                gen.builder.build_unconditional_branch(result_block)?;
+
+                gen.builder.position_at_end(result_block);
+                let bool_type = gen.ctx.bool_type();
+                let found = gen.builder.build_phi(bool_type, "contains")?;
+                found.add_incoming(&[
+                    (&bool_type.const_int(1, false), found_block),
+                    (&bool_type.const_zero(), after_loop_block),
+                ]);
+
+                gen.build_struct(BOOL_ID.get_from(env).ink(), vec![found.as_basic_value()])
+            },
+            env,
+        )
+    }
+
+    fn setup_list_eq(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let eq_fn = self.find_method(BinaryFnOp::Equal.fn_name(), elem, &[elem, elem], env)?;
+
+        self.create_binary_fn(
+            BinaryFnOp::Equal.fn_name(),
+            tid,
+            tid,
+            BOOL_ID,
+            true,
+            |gen, left, right, env| {
+                let cur_fn = gen
+                    .builder
+                    .get_insert_block()
+                    .unwrap()
+                    .get_parent()
+                    .unwrap();
+                let compare_block = gen.ctx.append_basic_block(cur_fn, "compare_elems");
+                let not_equal_block = gen.ctx.append_basic_block(cur_fn, "not_equal");
+                let result_block = gen.ctx.append_basic_block(cur_fn, "eq_result");
+
+                let left_len = gen
+                    .build_load_list_field(left, tid, LEN_FIELD, env)?
+                    .into_int_value();
+                let right_len = gen
+                    .build_load_list_field(right, tid, LEN_FIELD, env)?
+                    .into_int_value();
+                let same_len = gen.builder.build_int_compare(
+                    IntPredicate::EQ,
+                    left_len,
+                    right_len,
+                    "same_len",
+                )?;
+                gen.builder
+                    .build_conditional_branch(same_len, compare_block, not_equal_block)?;
+
+                gen.builder.position_at_end(compare_block);
+                gen.build_index_loop(
+                    left_len,
+                    |gen, index, env| {
+                        let right_elem = gen.build_load_list_elem(right, tid, index, env)?;
+                        let is_eq =
+                            gen.build_list_elem_eq(left, tid, index, right_elem, elem, eq_fn, env)?;
+
+                        let continue_block = gen.ctx.append_basic_block(cur_fn, "elems_equal");
+                        gen.builder.build_conditional_branch(
+                            is_eq,
+                            continue_block,
+                            not_equal_block,
+                        )?;
+                        gen.builder.position_at_end(continue_block);
+
+                        Ok(())
+                    },
+                    env,
+                )?;
+                let equal_block = gen.builder.get_insert_block().unwrap();
+                gen.builder.build_unconditional_branch(result_block)?;
+
+                gen.builder.position_at_end(not_equal_block);
+                gen.builder.build_unconditional_branch(result_block)?;
+
+                gen.builder.position_at_end(result_block);
+                let bool_type = gen.ctx.bool_type();
+                let is_eq = gen.builder.build_phi(bool_type, "list_eq")?;
+                is_eq.add_incoming(&[
+                    (&bool_type.const_int(1, false), equal_block),
+                    (&bool_type.const_zero(), not_equal_block),
+                ]);
+
+                gen.build_struct(BOOL_ID.get_from(env).ink(), vec![is_eq.as_basic_value()])
+            },
+            env,
+        )
+    }
+
+    fn setup_list_append(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        self.create_binary_fn(
+            "append",
+            tid,
+            elem,
+            NONE_ID,
+            false, // The list takes ownership of the value
+            |gen, list, value, env| {
+                let len = gen.build_list_reserve_one(list, tid, env)?;
+
+                let data = gen
+                    .build_load_list_field(list, tid, DATA_FIELD, env)?
+                    .into_pointer_value();
+                let elem_ptr = gen.build_list_elem_ptr(data, len)?;
+                gen.builder.build_store(elem_ptr, value)?;
+
+                gen.build_store_list_len(list, tid, len, 1, env)?;
+
+                gen.free_pointer(list, tid, env)?;
+                gen.build_none(env)
+            },
+            env,
+        )
+    }
+
+    fn setup_list_insert(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let (fn_val, ..) =
+            env.create_func(Some(tid), "insert", &[tid, INT_ID, elem], NONE_ID, false)?;
+        let (list, index, value) = self.build_ternary_fn_entry(fn_val);
+
+        let len = self.build_list_reserve_one(list, tid, env)?;
+
+        // Like Python, out of range indices insert at the start or end of the list
+        let index_val = self
+            .extract_primitive(index, INT_ID.get_from(env).ink())?
+            .into_int_value();
+        let index_val = self.build_wrap_negative_index(index_val, len)?;
+        let zero = self.len_type().const_zero();
+        let below_start =
+            self.builder
+                .build_int_compare(IntPredicate::SLT, index_val, zero, "below_start")?;
+        let index_val = self
+            .builder
+            .build_select(below_start, zero, index_val, "index")?
+            .into_int_value();
+        let past_end =
+            self.builder
+                .build_int_compare(IntPredicate::SGT, index_val, len, "past_end")?;
+        let index_val = self
+            .builder
+            .build_select(past_end, len, index_val, "index")?
+            .into_int_value();
+
+        // Shift the elements after the index back by one
+        let data = self
+            .build_load_list_field(list, tid, DATA_FIELD, env)?
+            .into_pointer_value();
+        let elem_ptr = self.build_list_elem_ptr(data, index_val)?;
+        let next_index = self.builder.build_int_add(
+            index_val,
+            self.len_type().const_int(1, false),
+            "next_index",
+        )?;
+        let next_elem_ptr = self.build_list_elem_ptr(data, next_index)?;
+        let moved_count = self.builder.build_int_sub(len, index_val, "moved_count")?;
+        let moved_size = self.build_elems_size(moved_count)?;
+        self.builder
+            .build_memmove(next_elem_ptr, 8, elem_ptr, 8, moved_size)?;
+
+        self.builder.build_store(elem_ptr, value)?;
+        self.build_store_list_len(list, tid, len, 1, env)?;
+
+        self.free_pointer(list, tid, env)?;
+        self.free_pointer(index, INT_ID, env)?;
+
+        let none = self.build_none(env)?;
+        self.builder.build_return(Some(&none))?;
+
+        Ok(())
+    }
+
+    fn setup_list_pop(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        // pop() removes the last element
+        self.create_unary_fn(
+            "pop",
+            tid,
+            elem,
+            true,
+            |gen, _fn_val, list, env| {
+                let len = gen
+                    .build_load_list_field(list, tid, LEN_FIELD, env)?
+                    .into_int_value();
+                let is_empty = gen.builder.build_int_compare(
+                    IntPredicate::EQ,
+                    len,
+                    gen.len_type().const_zero(),
+                    "is_empty",
+                )?;
+                gen.build_runtime_check(is_empty, "pop from empty list")?;
+
+                let last_index = gen.builder.build_int_sub(
+                    len,
+                    gen.len_type().const_int(1, false),
+                    "last_index",
+                )?;
+                let value = gen.build_load_list_elem(list, tid, last_index, env)?;
+                gen.build_store_list_len(list, tid, len, -1, env)?;
+
+                // The caller takes ownership of the removed value
+                Ok(value)
+            },
+            env,
+        )?;
+
+        // pop(i) removes the element at the index
+        self.create_binary_fn(
+            "pop",
+            tid,
+            INT_ID,
+            elem,
+            true,
+            |gen, list, index, env| {
+                let index = gen
+                    .extract_primitive(index, INT_ID.get_from(env).ink())?
+                    .into_int_value();
+                let index = gen.build_list_index(list, tid, index, env)?;
+
+                let len = gen
+                    .build_load_list_field(list, tid, LEN_FIELD, env)?
+                    .into_int_value();
+                let value = gen.build_load_list_elem(list, tid, index, env)?;
+
+                // Shift the elements after the index forward by one
+                let data = gen
+                    .build_load_list_field(list, tid, DATA_FIELD, env)?
+                    .into_pointer_value();
+                let elem_ptr = gen.build_list_elem_ptr(data, index)?;
+                let next_index = gen.builder.build_int_add(
+                    index,
+                    gen.len_type().const_int(1, false),
+                    "next_index",
+                )?;
+                let next_elem_ptr = gen.build_list_elem_ptr(data, next_index)?;
+                let moved_count = gen.builder.build_int_sub(len, next_index, "moved_count")?;
+                let moved_size = gen.build_elems_size(moved_count)?;
+                gen.builder
+                    .build_memmove(elem_ptr, 8, next_elem_ptr, 8, moved_size)?;
+
+                gen.build_store_list_len(list, tid, len, -1, env)?;
+
+                Ok(value)
+            },
+            env,
+        )
+    }
+
+    /// Positions the builder in a new function with three parameters and returns them.
+    pub(super) fn build_ternary_fn_entry(
+        &mut self,
+        fn_val: FunctionValue<'ctx>,
+    ) -> (PointerValue<'ctx>, PointerValue<'ctx>, PointerValue<'ctx>) {
+        let entry = self.ctx.append_basic_block(fn_val, "entry");
+        self.builder.position_at_end(entry);
+
+        let param = |i| fn_val.get_nth_param(i).unwrap().into_pointer_value();
+        (param(0), param(1), param(2))
+    }
+
+    /// Calls `body` with every index from 0 up to `len`, leaving the builder after the loop.
+    pub(super) fn build_index_loop(
+        &mut self,
+        len: IntValue<'ctx>,
+        body: impl FnOnce(&mut Self, IntValue<'ctx>, &mut Environment<'ctx>) -> Result<(), GenError>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let start_block = self.builder.get_insert_block().unwrap();
+        let cur_fn = start_block.get_parent().unwrap();
+        let condition_block = self.ctx.append_basic_block(cur_fn, "loop_condition");
+        let body_block = self.ctx.append_basic_block(cur_fn, "loop_body");
+        let end_block = self.ctx.append_basic_block(cur_fn, "loop_end");
+
+        self.builder.build_unconditional_branch(condition_block)?;
+
+        self.builder.position_at_end(condition_block);
+        let index = self.builder.build_phi(self.len_type(), "index")?;
+        let index_val = index.as_basic_value().into_int_value();
+        let in_bounds =
+            self.builder
+                .build_int_compare(IntPredicate::SLT, index_val, len, "in_bounds")?;
+        self.builder
+            .build_conditional_branch(in_bounds, body_block, end_block)?;
+
+        self.builder.position_at_end(body_block);
+        body(self, index_val, env)?;
+        let next_index = self.builder.build_int_add(
+            index_val,
+            self.len_type().const_int(1, false),
+            "next_index",
+        )?;
+        let body_end_block = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(condition_block)?;
+
+        index.add_incoming(&[
+            (&self.len_type().const_zero(), start_block),
+            (&next_index, body_end_block),
+        ]);
+
+        self.builder.position_at_end(end_block);
+
+        Ok(())
+    }
+
+    /// Compares the element at an index with a value using `__eq__`, without consuming either.
+    #[allow(clippy::too_many_arguments)]
+    fn build_list_elem_eq(
+        &mut self,
+        list: PointerValue<'ctx>,
+        tid: TypeId,
+        index: IntValue<'ctx>,
+        value: PointerValue<'ctx>,
+        elem: TypeId,
+        eq_fn: FunctionId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        let elem_ptr = self.build_load_list_elem(list, tid, index, env)?;
+        self.copy_pointer(elem_ptr, elem, env)?;
+        self.copy_pointer(value, elem, env)?;
+
+        let (is_eq_ptr, is_eq_tid) = self.call_func(eq_fn, &[elem_ptr, value], env)?;
+        self.build_truthy(is_eq_ptr, is_eq_tid, env)
+    }
+
+    /// Converts a possibly negative index to a position in the list, aborting if it is out of range.
+    fn build_list_index(
+        &mut self,
+        list: PointerValue<'ctx>,
+        tid: TypeId,
+        index: IntValue<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        let len = self
+            .build_load_list_field(list, tid, LEN_FIELD, env)?
+            .into_int_value();
+        let index = self.build_wrap_negative_index(index, len)?;
+
+        // A negative index is out of range when compared as unsigned
+        let out_of_range =
+            self.builder
+                .build_int_compare(IntPredicate::UGE, index, len, "out_of_range")?;
+        self.build_runtime_check(out_of_range, "list index out of range")?;
+
+        Ok(index)
+    }
+
+    /// Negative indices count from the end, so `-1` is the last element.
+    fn build_wrap_negative_index(
+        &mut self,
+        index: IntValue<'ctx>,
+        len: IntValue<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        let is_negative = self.builder.build_int_compare(
+            IntPredicate::SLT,
+            index,
+            self.len_type().const_zero(),
+            "is_negative",
+        )?;
+        let wrapped = self.builder.build_int_add(index, len, "wrapped_index")?;
+
+        Ok(self
+            .builder
+            .build_select(is_negative, wrapped, index, "index")?
+            .into_int_value())
+    }
+
+    /// Makes sure there is space for one more element, returning the current length.
+    fn build_list_reserve_one(
+        &mut self,
+        list: PointerValue<'ctx>,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        let res = self.res();
+        let cur_fn = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let grow_block = self.ctx.append_basic_block(cur_fn, "grow");
+        let continue_block = self.ctx.append_basic_block(cur_fn, "has_capacity");
+
+        let len = self
+            .build_load_list_field(list, tid, LEN_FIELD, env)?
+            .into_int_value();
+        let cap = self
+            .build_load_list_field(list, tid, CAP_FIELD, env)?
+            .into_int_value();
+        let is_full = self
+            .builder
+            .build_int_compare(IntPredicate::EQ, len, cap, "is_full")?;
+        self.builder
+            .build_conditional_branch(is_full, grow_block, continue_block)?;
+
+        // Double the capacity, starting at a small minimum
+        self.builder.position_at_end(grow_block);
+        let doubled_cap =
+            self.builder
+                .build_int_mul(cap, self.len_type().const_int(2, false), "doubled_cap")?;
+        let min_cap = self.len_type().const_int(MIN_CAPACITY, false);
+        let below_min =
+            self.builder
+                .build_int_compare(IntPredicate::ULT, doubled_cap, min_cap, "below_min")?;
+        let new_cap = self
+            .builder
+            .build_select(below_min, min_cap, doubled_cap, "new_cap")?
+            .into_int_value();
+
+        let data = self
+            .build_load_list_field(list, tid, DATA_FIELD, env)?
+            .into_pointer_value();
+        let new_size = self.build_elems_size(new_cap)?;
+        let new_data = self
+            .builder
+            .build_call(res.realloc, &[data.into(), new_size.into()], "new_data")?
+            .try_as_basic_value()
+            .unwrap_left();
+
+        let list_type = tid.get_from(env).ink();
+        let data_ptr = self
+            .builder
+            .build_struct_gep(list_type, list, DATA_FIELD, "data_ptr")?;
+        self.builder.build_store(data_ptr, new_data)?;
+        let cap_ptr = self
+            .builder
+            .build_struct_gep(list_type, list, CAP_FIELD, "cap_ptr")?;
+        self.builder.build_store(cap_ptr, new_cap)?;
+        self.builder.build_unconditional_branch(continue_block)?;
+
+        self.builder.position_at_end(continue_block);
+
+        Ok(len)
+    }
+
+    fn build_store_list_len(
+        &mut self,
+        list: PointerValue<'ctx>,
+        tid: TypeId,
+        len: IntValue<'ctx>,
+        change: i64,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let new_len = self.builder.build_int_add(
+            len,
+            self.len_type().const_int(change as u64, true),
+            "new_len",
+        )?;
+        let len_ptr =
+            self.builder
+                .build_struct_gep(tid.get_from(env).ink(), list, LEN_FIELD, "len_ptr")?;
+        self.builder.build_store(len_ptr, new_len)?;
+
+        Ok(())
+    }
+
+    fn build_load_list_field(
+        &mut self,
+        list: PointerValue<'ctx>,
+        tid: TypeId,
+        field: u32,
+        env: &mut Environment<'ctx>,
+    ) -> Result<BasicValueEnum<'ctx>, GenError> {
+        let list_type = tid.get_from(env).ink();
+        let field_ptr = self
+            .builder
+            .build_struct_gep(list_type, list, field, "list_field_ptr")?;
+
+        Ok(self.builder.build_load(
+            list_type.get_field_type_at_index(field).unwrap(),
+            field_ptr,
+            "list_field",
+        )?)
+    }
+
+    /// Loads the element at an index without changing its reference count.
+    fn build_load_list_elem(
+        &mut self,
+        list: PointerValue<'ctx>,
+        tid: TypeId,
+        index: IntValue<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        let data = self
+            .build_load_list_field(list, tid, DATA_FIELD, env)?
+            .into_pointer_value();
+        let elem_ptr = self.build_list_elem_ptr(data, index)?;
+
+        Ok(self
+            .builder
+            .build_load(self.ptr_type(), elem_ptr, "elem")?
+            .into_pointer_value())
+    }
+
+    fn build_list_elem_ptr(
+        &mut self,
+        data: PointerValue<'ctx>,
+        index: IntValue<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        Ok(unsafe {
+            self.builder
+                .build_gep(self.ptr_type(), data, &[index], "elem_ptr")?
+        })
+    }
+
+    fn build_elems_size(&mut self, count: IntValue<'ctx>) -> Result<IntValue<'ctx>, GenError> {
+        let elem_size = self.ptr_type().size_of();
+        Ok(self.builder.build_int_mul(count, elem_size, "elems_size")?)
+    }
+}
+
+fn list_unalloc<'ctx>(
+    ptr: PointerValue<'ctx>,
+    tid: TypeId,
+    gen: &mut CodeGen<'ctx>,
+    env: &mut Environment<'ctx>,
+) -> Result<(), GenError> {
+    let TypeInfo::List(elem) = tid.get_from(env).info() else {
+        unreachable!()
+    };
+
+    let len = gen
+        .build_load_list_field(ptr, tid, LEN_FIELD, env)?
+        .into_int_value();
+    gen.build_index_loop(
+        len,
+        |gen, index, env| {
+            let elem_ptr = gen.build_load_list_elem(ptr, tid, index, env)?;
+            gen.free_pointer(elem_ptr, elem, env)
+        },
+        env,
+    )?;
+
+    let data = gen
+        .build_load_list_field(ptr, tid, DATA_FIELD, env)?
+        .into_pointer_value();
+    gen.builder.build_free(data)?;
+
+    Ok(())
+}
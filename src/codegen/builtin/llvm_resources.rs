@@ -1,21 +1,33 @@
 use inkwell::{
-    values::{BasicValueEnum, FunctionValue, IntValue, PointerValue},
+    module::Linkage,
+    values::{FunctionValue, IntValue, PointerValue},
     AddressSpace,
 };
 
-use crate::codegen::{env::Environment, err::GenError, CodeGen};
+use crate::codegen::{
+    env::{id::FunctionId, Environment},
+    err::GenError,
+    CodeGen,
+};
+
+pub const LINE_GLOBAL: &str = "$line";
+pub const RUNTIME_ERROR_FN: &str = "$runtime_error";
 
+#[derive(Clone, Copy)]
 pub struct LLVMResources<'ctx> {
-    pub str_format_spec: PointerValue<'ctx>,
     pub cstr_format_spec: PointerValue<'ctx>,
 
-    pub scanf: FunctionValue<'ctx>,
-    pub getchar: FunctionValue<'ctx>,
-    pub sscanf: FunctionValue<'ctx>,
+    /// The source line of the statement currently being executed.
+    pub line: PointerValue<'ctx>,
+    /// Prints the given C string along with the current line to stderr, then exits.
+    pub runtime_error: FunctionValue<'ctx>,
+
     pub printf: FunctionValue<'ctx>,
-    pub snprintf: FunctionValue<'ctx>,
     pub realloc: FunctionValue<'ctx>,
+    pub memcmp: FunctionValue<'ctx>,
     pub pow: FunctionValue<'ctx>,
+    pub dprintf: FunctionValue<'ctx>,
+    pub exit: FunctionValue<'ctx>,
 }
 
 impl<'ctx> CodeGen<'ctx> {
@@ -25,11 +37,6 @@ impl<'ctx> CodeGen<'ctx> {
     ) -> Result<LLVMResources<'ctx>, GenError> {
         let ptr_type = self.ctx.ptr_type(AddressSpace::default());
 
-        let str_format_spec = self
-            .builder
-            .build_global_string_ptr("%.*s", "print_string_format")?
-            .as_pointer_value();
-
         let cstr_format_spec = self
             .builder
             .build_global_string_ptr("%s", "print_string_format")?
@@ -41,32 +48,21 @@ impl<'ctx> CodeGen<'ctx> {
             .fn_type(&[ptr_type.into(), self.prim_int_type().into()], false);
         let realloc = env.module().add_function("realloc", realloc_type, None);
 
-        // Add scanf
-        let scanf_type = self.ctx.i32_type().fn_type(&[ptr_type.into()], true);
-        let scanf = env.module().add_function("scanf", scanf_type, None);
-
-        // Add getchar
-        let get_char_type = self.ctx.i32_type().fn_type(&[], false);
-        let get_char = env.module().add_function("getchar", get_char_type, None);
-
-        // Add sscanf
-        let sscanf_type = self
-            .ctx
-            .i32_type()
-            .fn_type(&[ptr_type.into(), ptr_type.into()], true);
-        let sscanf = env.module().add_function("sscanf", sscanf_type, None);
+        // Add memcmp
+        let memcmp_type = self.ctx.i32_type().fn_type(
+            &[
+                ptr_type.into(),
+                ptr_type.into(),
+                self.prim_int_type().into(),
+            ],
+            false,
+        );
+        let memcmp = env.module().add_function("memcmp", memcmp_type, None);
 
         // Add printf
         let printf_type = self.ctx.i32_type().fn_type(&[ptr_type.into()], true);
         let printf = env.module().add_function("printf", printf_type, None);
 
-        // Add snprintf
-        let snprintf_type = self.ctx.i32_type().fn_type(
-            &[ptr_type.into(), self.ctx.i64_type().into(), ptr_type.into()],
-            true,
-        );
-        let snprintf = env.module().add_function("snprintf", snprintf_type, None);
-
         // Add pow
         let pow_type = self.ctx.f64_type().fn_type(
             &[self.ctx.f64_type().into(), self.ctx.f64_type().into()],
@@ -74,50 +70,181 @@ impl<'ctx> CodeGen<'ctx> {
         );
         let pow = env.module().add_function("pow", pow_type, None);
 
+        // Add dprintf
+        let dprintf_type = self
+            .ctx
+            .i32_type()
+            .fn_type(&[self.ctx.i32_type().into(), ptr_type.into()], true);
+        let dprintf = env.module().add_function("dprintf", dprintf_type, None);
+
+        // Add exit
+        let exit_type = self
+            .ctx
+            .void_type()
+            .fn_type(&[self.ctx.i32_type().into()], false);
+        let exit = env.module().add_function("exit", exit_type, None);
+
+        // Add current line
+        let line_global = env
+            .module()
+            .add_global(self.ctx.i64_type(), None, LINE_GLOBAL);
+        line_global.set_linkage(Linkage::Internal);
+        line_global.set_initializer(&self.ctx.i64_type().const_zero());
+        let line = line_global.as_pointer_value();
+
+        let runtime_error = self.build_runtime_error_fn(line, dprintf, exit, env)?;
+
         // let ptr_type = self.ctx.ptr_type(AddressSpace::default());
         // let fd_type = self.ctx.opaque_struct_type("FILE");
         // let stdin_ptr = env.module.add_global(fd_type, None, "stdin").as_pointer_value();
 
         Ok(LLVMResources {
-            str_format_spec,
             cstr_format_spec,
 
-            scanf,
-            getchar: get_char,
-            sscanf,
+            line,
+            runtime_error,
+
             printf,
-            snprintf,
             realloc,
+            memcmp,
             pow,
+            dprintf,
+            exit,
         })
     }
 
-    pub(super) fn build_get_string_size(
+    fn build_runtime_error_fn(
+        &mut self,
+        line: PointerValue<'ctx>,
+        dprintf: FunctionValue<'ctx>,
+        exit: FunctionValue<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<FunctionValue<'ctx>, GenError> {
+        let prev_block = self.builder.get_insert_block().unwrap();
+
+        let fn_type = self
+            .ctx
+            .void_type()
+            .fn_type(&[self.ptr_type().into()], false);
+        let fn_val = env.module().add_function(RUNTIME_ERROR_FN, fn_type, None);
+
+        let entry = self.ctx.append_basic_block(fn_val, "entry");
+        self.builder.position_at_end(entry);
+
+        let format_spec = self
+            .builder
+            .build_global_string_ptr("Runtime error at line %ld: %s\n", "runtime_error_format")?
+            .as_pointer_value();
+
+        let msg = fn_val.get_nth_param(0).unwrap().into_pointer_value();
+        let cur_line = self
+            .builder
+            .build_load(self.ctx.i64_type(), line, "cur_line")?;
+
+        let stderr_fd = self.ctx.i32_type().const_int(2, false);
+        self.builder.build_call(
+            dprintf,
+            &[
+                stderr_fd.into(),
+                format_spec.into(),
+                cur_line.into(),
+                msg.into(),
+            ],
+            "_",
+        )?;
+        self.builder
+            .build_call(exit, &[self.ctx.i32_type().const_int(1, false).into()], "_")?;
+        self.builder.build_unreachable()?;
+
+        self.builder.position_at_end(prev_block);
+
+        Ok(fn_val)
+    }
+
+    /// Records the line of the statement being compiled as the current line at runtime,
+    /// which is used when reporting runtime errors.
+    pub(in crate::codegen) fn build_set_line(&mut self) -> Result<(), GenError> {
+        let res = self.res();
+        let line_val = self.ctx.i64_type().const_int(self.line as u64, false);
+        self.builder.build_store(res.line, line_val)?;
+
+        Ok(())
+    }
+
+    /// Records the current line before calling a function, if the function can abort with a
+    /// runtime error. Other calls leave the line alone, so code without runtime checks does not
+    /// pay for it.
+    pub(in crate::codegen) fn build_set_line_for(
         &mut self,
-        format_spec: PointerValue<'ctx>,
-        primitive: BasicValueEnum<'ctx>,
+        fn_id: FunctionId,
+        env: &Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        if self.fallible_fns.contains(&env.get_func(fn_id).ink()) {
+            self.build_set_line()?;
+        }
+
+        Ok(())
+    }
+
+    /// Marks a function that can abort with a runtime error, either itself or through a function
+    /// it calls, so that calls to it record the line first.
+    pub(in crate::codegen) fn mark_fallible(&mut self, fn_val: FunctionValue<'ctx>) {
+        self.fallible_fns.insert(fn_val);
+    }
+
+    /// Aborts the program with the given message.
+    ///
+    /// This terminates the current block, so the builder must be moved afterwards.
+    pub(in crate::codegen) fn build_runtime_error(
+        &mut self,
+        msg: &str,
         res: &LLVMResources<'ctx>,
-    ) -> Result<IntValue<'ctx>, GenError> {
-        let cstr_size = self
+    ) -> Result<(), GenError> {
+        let fn_val = self
             .builder
-            .build_call(
-                res.snprintf,
-                &[
-                    self.str_ptr_type().const_null().into(),
-                    self.len_type().const_int(0, false).into(),
-                    format_spec.into(),
-                    primitive.into(),
-                ],
-                "snprintf_size_i32",
-            )?
-            .try_as_basic_value()
-            .unwrap_left()
-            .into_int_value();
-
-        let cstr_size = self
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        self.mark_fallible(fn_val);
+
+        let msg_ptr = self
             .builder
-            .build_int_cast(cstr_size, self.len_type(), "snprintf_size")?;
+            .build_global_string_ptr(msg, "runtime_error_msg")?
+            .as_pointer_value();
+
+        self.builder
+            .build_call(res.runtime_error, &[msg_ptr.into()], "_")?;
+        self.builder.build_unreachable()?;
+
+        Ok(())
+    }
+
+    /// Aborts the program with the given message if `failed` is true, then continues in a new block.
+    pub(in crate::codegen) fn build_runtime_check(
+        &mut self,
+        failed: IntValue<'ctx>,
+        msg: &str,
+    ) -> Result<(), GenError> {
+        let res = self.res();
+        let fn_val = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+
+        let error_block = self.ctx.append_basic_block(fn_val, "check_failed");
+        let continue_block = self.ctx.append_basic_block(fn_val, "check_passed");
+
+        self.builder
+            .build_conditional_branch(failed, error_block, continue_block)?;
+
+        self.builder.position_at_end(error_block);
+        self.build_runtime_error(msg, &res)?;
+
+        self.builder.position_at_end(continue_block);
 
-        Ok(cstr_size)
+        Ok(())
     }
 }
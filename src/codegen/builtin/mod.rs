@@ -3,51 +3,112 @@ use inkwell::{
     values::{BasicValueEnum, FunctionValue, PointerValue},
     AddressSpace,
 };
-use llvm_resources::LLVMResources;
 
 use super::{
     env::{
-        id::{TypeId, STR_ID},
+        id::{FunctionId, TypeId, STR_ID},
+        type_def::TypeInfo,
         Environment,
     },
     err::GenError,
     CodeGen,
 };
+use crate::parser::BinaryFnOp;
 
 pub mod bool;
+mod derive;
+pub mod dict;
 pub mod float;
-pub mod functions;
+pub mod function;
+mod hash_table;
 pub mod int;
+pub mod io;
+pub mod list;
 pub mod llvm_resources;
 pub mod none;
+pub mod optional;
+pub mod runtime;
+pub mod set;
+pub mod slice;
+mod str_methods;
 pub mod string;
+pub mod tuple;
 
 pub const TO_STR_FN: &str = "__str__";
 pub const TO_BOOL_FN: &str = "__bool__";
 pub const TO_INT_FN: &str = "__int__";
 pub const TO_FLOAT_FN: &str = "__float__";
+pub const LEN_FN: &str = "__len__";
+pub const GET_ITEM_FN: &str = "__getitem__";
+pub const SET_ITEM_FN: &str = "__setitem__";
+pub const DEL_ITEM_FN: &str = "__delitem__";
+pub const CONTAINS_FN: &str = "__contains__";
+pub const HASH_FN: &str = "__hash__";
+/// Returns a list of the elements to iterate over, for types that cannot be indexed like sets.
+pub const ITER_FN: &str = "$iter";
 
 impl<'ctx> CodeGen<'ctx> {
     pub(super) fn setup_builtins(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
         let res = self.setup_llvm_resources(env)?;
+        self.res = Some(res);
 
         self.declare_int_primitive(env)?;
         self.declare_float_primitive(env)?;
         self.declare_bool_primitive(env)?;
         self.declare_none_primitive(env)?;
         self.declare_str_primitive(env)?;
+        self.declare_runtime_fns(env)?;
 
-        self.setup_int_primitive(&res, env)?;
+        self.setup_int_primitive(env)?;
         self.setup_float_primitive(&res, env)?;
         self.setup_bool_primitive(&res, env)?;
         self.setup_none_primitive(env)?;
         self.setup_str_primitive(&res, env)?;
-
-        self.setup_functions(&res, env)?;
+        self.setup_slice(env)?;
+        self.setup_str_methods(env)?;
+        self.setup_io(env)?;
 
         Ok(())
     }
 
+    /// Finds the method of a type to call with the given arguments, where the first argument is
+    /// the type itself.
+    ///
+    /// Methods of builtin generic types, such as `list[int]`, are only created once they are used,
+    /// since the methods of their type arguments may not have been declared when they were created.
+    pub(in crate::codegen) fn find_method(
+        &mut self,
+        ident: &str,
+        tid: TypeId,
+        arg_tids: &[TypeId],
+        env: &mut Environment<'ctx>,
+    ) -> Result<FunctionId, GenError> {
+        if let Ok(fn_id) = env.resolve_func(ident, Some(tid), arg_tids) {
+            return Ok(fn_id);
+        }
+
+        let prev_block = self.builder.get_insert_block().unwrap();
+
+        let created = match env.get_type(tid).info() {
+            TypeInfo::Optional(inner) => self.create_optional_method(ident, tid, inner, env)?,
+            TypeInfo::List(elem) => self.create_list_method(ident, tid, elem, env)?,
+            TypeInfo::Dict(key, value) => self.create_dict_method(ident, tid, key, value, env)?,
+            TypeInfo::Set(elem) => self.create_set_method(ident, tid, elem, env)?,
+            TypeInfo::Tuple => self.create_tuple_method(ident, tid, env)?,
+            TypeInfo::Primitive if tid == STR_ID => self.create_str_method(ident, arg_tids, env)?,
+            TypeInfo::Struct => self.create_derived_method(ident, tid, env)?,
+            TypeInfo::Primitive | TypeInfo::Func => false,
+        };
+
+        self.builder.position_at_end(prev_block);
+
+        if !created {
+            return Err(GenError::FunctionNotFound);
+        }
+
+        env.resolve_func(ident, Some(tid), arg_tids)
+    }
+
     pub fn extract_primitive(
         &mut self,
         struct_ptr: PointerValue<'ctx>,
@@ -198,55 +259,52 @@ impl<'ctx> CodeGen<'ctx> {
         )
     }
 
-    fn create_primitive_to_str_fn(
+    /// Appends a string to the string stored in `result_var`, consuming it.
+    fn build_append_str(
         &mut self,
-        tid: TypeId,
-        format_spec_str: &str,
-        res: &LLVMResources<'ctx>,
+        result_var: PointerValue<'ctx>,
+        str_ptr: PointerValue<'ctx>,
         env: &mut Environment<'ctx>,
     ) -> Result<(), GenError> {
-        let type_def = env.get_type(tid);
-        let expr_type = type_def.ink();
-        let type_ident = type_def.ident().to_owned();
+        let str_add_fn =
+            env.find_func(BinaryFnOp::Add.fn_name(), Some(STR_ID), &[STR_ID, STR_ID])?;
 
-        let format_spec = self
+        let result = self
             .builder
-            .build_global_string_ptr(format_spec_str, &(type_ident + "_format_specifier"))?
-            .as_pointer_value();
+            .build_load(self.ptr_type(), result_var, "result")?
+            .into_pointer_value();
+        let (result, ..) = self.call_func(str_add_fn, &[result, str_ptr], env)?;
+        self.builder.build_store(result_var, result)?;
 
-        self.create_unary_fn(
-            TO_STR_FN,
-            tid,
-            STR_ID,
-            true,
-            |gen, _fn_val, param, env| {
-                let prim = gen.extract_primitive(param, expr_type)?;
-
-                let str_size = gen.build_get_string_size(format_spec, prim, res)?;
-                let cstr_size = gen.builder.build_int_add(
-                    str_size,
-                    gen.len_type().const_int(1, false),
-                    "str_size",
-                )?;
-
-                let str_data_ptr = gen.build_str_data_malloc(cstr_size, "new_str_data_ptr")?;
-
-                // load data into str
-                gen.builder.build_call(
-                    res.snprintf,
-                    &[
-                        str_data_ptr.into(),
-                        cstr_size.into(),
-                        format_spec.into(),
-                        prim.into(),
-                    ],
-                    "_",
-                )?;
-
-                gen.build_str_struct(str_data_ptr, str_size, env)
-            },
-            env,
-        )
+        Ok(())
+    }
+
+    /// Appends a value to the string stored in `result_var` as it is shown inside a collection,
+    /// where strings are quoted like in Python. The value is not consumed.
+    fn build_append_repr(
+        &mut self,
+        result_var: PointerValue<'ctx>,
+        value: PointerValue<'ctx>,
+        tid: TypeId,
+        to_str_fn: FunctionId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let quote = if tid == STR_ID { "'" } else { "" };
+
+        self.copy_pointer(value, tid, env)?;
+        let (value_str, ..) = self.call_func(to_str_fn, &[value], env)?;
+
+        if !quote.is_empty() {
+            let open_quote = self.build_str_const(quote, env)?;
+            self.build_append_str(result_var, open_quote, env)?;
+        }
+        self.build_append_str(result_var, value_str, env)?;
+        if !quote.is_empty() {
+            let close_quote = self.build_str_const(quote, env)?;
+            self.build_append_str(result_var, close_quote, env)?;
+        }
+
+        Ok(())
     }
 
     pub(super) fn ptr_type(&self) -> PointerType<'ctx> {
@@ -1,11 +1,17 @@
 use inkwell::{values::PointerValue, AddressSpace};
 
 use crate::codegen::{
-    env::{id::NONE_ID, type_def::TypeDef, Environment},
+    env::{
+        id::{NONE_ID, STR_ID},
+        type_def::TypeDef,
+        Environment,
+    },
     err::GenError,
     CodeGen,
 };
 
+use super::TO_STR_FN;
+
 pub const NONE_NAME: &str = "None";
 pub const NONE_CONST: &str = "const_none";
 
@@ -23,7 +29,15 @@ impl<'ctx> CodeGen<'ctx> {
     pub fn setup_none_primitive(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
         self.build_noop_copy_ptr_fn(NONE_ID, env)?;
         self.build_noop_free_ptr_fn(NONE_ID, env)?;
-        self.build_copy_ptr_fn(NONE_ID, env)?;
+
+        self.create_unary_fn(
+            TO_STR_FN,
+            NONE_ID,
+            STR_ID,
+            false,
+            |gen, _fn_val, _param, env| gen.build_str_const(NONE_NAME, env),
+            env,
+        )?;
 
         Ok(())
     }
@@ -32,7 +46,6 @@ impl<'ctx> CodeGen<'ctx> {
         &mut self,
         _env: &mut Environment<'ctx>,
     ) -> Result<PointerValue<'ctx>, GenError> {
-        // TODO: Real None values?
         Ok(self.ctx.ptr_type(AddressSpace::default()).const_null())
     }
 }
@@ -0,0 +1,169 @@
+use inkwell::values::PointerValue;
+
+use crate::codegen::{
+    env::{
+        id::{TypeId, INT_ID, NONE_ID, STR_ID},
+        type_def::{TypeDef, TypeInfo},
+        Environment,
+    },
+    err::GenError,
+    util::{COPY_PTR_IDENT, FREE_PTR_IDENT, RCOUNT_IDENT},
+    CodeGen,
+};
+
+use super::{none::NONE_NAME, TO_STR_FN};
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Returns the optional version of a type, creating it if it does not exist yet.
+    ///
+    /// An optional value uses the same representation as the inner type, with `None` stored as a
+    /// null pointer.
+    pub(in crate::codegen) fn optional_of(
+        &mut self,
+        inner: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<TypeId, GenError> {
+        // `None` and optional types can already hold `None`
+        if inner == NONE_ID || env.get_type(inner).optional_inner().is_some() {
+            return Ok(inner);
+        }
+
+        let ident = format!("Optional[{}]", env.type_id_ident(inner));
+        if let Ok(tid) = env.find_type(&ident) {
+            return Ok(tid);
+        }
+
+        let inner_type = env.get_type(inner).ink();
+        let tid = env.gen_type_id();
+        env.register_type(
+            &ident,
+            tid,
+            TypeDef::new_generic(&ident, inner_type, TypeInfo::Optional(inner)),
+        )?;
+
+        let prev_block = self.builder.get_insert_block().unwrap();
+
+        self.create_optional_fn(
+            COPY_PTR_IDENT,
+            tid,
+            tid,
+            |gen, ptr, env| gen.copy_pointer(ptr, inner, env),
+            |_gen, ptr, _env| Ok(ptr),
+            env,
+        )?;
+        self.create_optional_fn(
+            FREE_PTR_IDENT,
+            tid,
+            NONE_ID,
+            |gen, ptr, env| {
+                gen.free_pointer(ptr, inner, env)?;
+                gen.build_none(env)
+            },
+            |gen, _ptr, env| gen.build_none(env),
+            env,
+        )?;
+        self.create_optional_fn(
+            RCOUNT_IDENT,
+            tid,
+            INT_ID,
+            |gen, ptr, env| {
+                let rcount_fn = env.find_func(RCOUNT_IDENT, Some(inner), &[inner])?;
+                Ok(gen.call_func(rcount_fn, &[ptr], env)?.0)
+            },
+            |gen, _ptr, env| {
+                let zero = gen.prim_int_type().const_zero();
+                gen.build_struct(INT_ID.get_from(env).ink(), vec![zero.into()])
+            },
+            env,
+        )?;
+
+        self.builder.position_at_end(prev_block);
+
+        Ok(tid)
+    }
+
+    /// Creates the method of an optional type with the given name, returning false if optional
+    /// types do not have a method with that name.
+    pub(super) fn create_optional_method(
+        &mut self,
+        ident: &str,
+        tid: TypeId,
+        inner: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<bool, GenError> {
+        match ident {
+            TO_STR_FN => {
+                let inner_to_str_fn = self.find_method(TO_STR_FN, inner, &[inner], env)?;
+
+                self.create_optional_fn(
+                    TO_STR_FN,
+                    tid,
+                    STR_ID,
+                    |gen, ptr, env| Ok(gen.call_func(inner_to_str_fn, &[ptr], env)?.0),
+                    |gen, _ptr, env| gen.build_str_const(NONE_NAME, env),
+                    env,
+                )?;
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Creates a function on an optional type that branches on whether its parameter is `None`.
+    ///
+    /// The parameter is not freed, so `build_some` is responsible for consuming it.
+    fn create_optional_fn(
+        &mut self,
+        ident: &str,
+        tid: TypeId,
+        ret_tid: TypeId,
+        build_some: impl FnOnce(
+            &mut Self,
+            PointerValue<'ctx>,
+            &mut Environment<'ctx>,
+        ) -> Result<PointerValue<'ctx>, GenError>,
+        build_none: impl FnOnce(
+            &mut Self,
+            PointerValue<'ctx>,
+            &mut Environment<'ctx>,
+        ) -> Result<PointerValue<'ctx>, GenError>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        self.create_unary_fn(
+            ident,
+            tid,
+            ret_tid,
+            false,
+            |gen, fn_val, param, env| {
+                let some_block = gen.ctx.append_basic_block(fn_val, "some");
+                let none_block = gen.ctx.append_basic_block(fn_val, "none");
+                let merge_block = gen.ctx.append_basic_block(fn_val, "merge");
+
+                let is_none = gen.builder.build_is_null(param, "is_none")?;
+                gen.builder
+                    .build_conditional_branch(is_none, none_block, some_block)?;
+
+                gen.builder.position_at_end(some_block);
+                let some_result = build_some(gen, param, env)?;
+                let some_end_block = gen.builder.get_insert_block().unwrap();
+                gen.builder.build_unconditional_branch(merge_block)?;
+
+                gen.builder.position_at_end(none_block);
+                let none_result = build_none(gen, param, env)?;
+                let none_end_block = gen.builder.get_insert_block().unwrap();
+                gen.builder.build_unconditional_branch(merge_block)?;
+
+                gen.builder.position_at_end(merge_block);
+                let result = gen.builder.build_phi(gen.ptr_type(), "result")?;
+                result.add_incoming(&[
+                    (&some_result, some_end_block),
+                    (&none_result, none_end_block),
+                ]);
+
+                Ok(result.as_basic_value().into_pointer_value())
+            },
+            env,
+        )
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{
+    codegen::{
+        env::{
+            id::{FLOAT_ID, INT_ID, NONE_ID, STR_ID},
+            Environment,
+        },
+        err::GenError,
+        util::FREE_PTR_IDENT,
+        CodeGen,
+    },
+    parser::BinaryFnOp,
+};
+
+use super::{TO_FLOAT_FN, TO_INT_FN, TO_STR_FN};
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Declares the builtins implemented by the `reticulated-runtime` library, which is linked
+    /// into every program. They must be declared before any code that calls them is built.
+    pub(super) fn declare_runtime_fns(
+        &mut self,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        // Strings
+        let str_owner = Some(STR_ID);
+        env.declare_runtime_func(str_owner, FREE_PTR_IDENT, "rt_str_free", &[STR_ID], NONE_ID)?;
+        env.declare_runtime_func(
+            str_owner,
+            BinaryFnOp::Add.fn_name(),
+            "rt_str_add",
+            &[STR_ID, STR_ID],
+            STR_ID,
+        )?;
+
+        // Conversions, where parsing aborts when the string is not a number
+        let to_int_fn =
+            env.declare_runtime_func(str_owner, TO_INT_FN, "rt_str_to_int", &[STR_ID], INT_ID)?;
+        self.mark_fallible(env.get_func(to_int_fn).ink());
+        let to_float_fn = env.declare_runtime_func(
+            str_owner,
+            TO_FLOAT_FN,
+            "rt_str_to_float",
+            &[STR_ID],
+            FLOAT_ID,
+        )?;
+        self.mark_fallible(env.get_func(to_float_fn).ink());
+        env.declare_runtime_func(Some(INT_ID), TO_STR_FN, "rt_int_to_str", &[INT_ID], STR_ID)?;
+        env.declare_runtime_func(
+            Some(FLOAT_ID),
+            TO_STR_FN,
+            "rt_float_to_str",
+            &[FLOAT_ID],
+            STR_ID,
+        )?;
+
+        Ok(())
+    }
+}
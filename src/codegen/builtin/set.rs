@@ -0,0 +1,655 @@
+use inkwell::{values::PointerValue, IntPredicate};
+
+use crate::{
+    codegen::{
+        env::{
+            id::{FunctionId, TypeId, BOOL_ID, INT_ID, NONE_ID, STR_ID},
+            type_def::{TypeDef, TypeInfo},
+            Environment,
+        },
+        err::GenError,
+        CodeGen,
+    },
+    parser::BinaryFnOp,
+};
+
+use super::{
+    hash_table::{KeyFns, ENTRY_KEY_FIELD},
+    CONTAINS_FN, ITER_FN, LEN_FN, TO_BOOL_FN, TO_STR_FN,
+};
+
+pub const SET_NAME: &str = "set";
+
+const ELEM_NOT_FOUND_MSG: &str = "element not found in set";
+
+/// Which elements of two sets end up in the result of a set operator.
+#[derive(Clone, Copy)]
+enum SetOp {
+    Union,
+    Intersection,
+    Difference,
+    SymmetricDifference,
+}
+
+/// Which elements of one operand of a set operator are added to the result.
+#[derive(Clone, Copy, PartialEq)]
+enum Keep {
+    All,
+    InOther,
+    NotInOther,
+    Nothing,
+}
+
+impl SetOp {
+    /// Which elements of the left and right operands are added to the result.
+    fn keeps(&self) -> (Keep, Keep) {
+        match self {
+            // Adding an element the result already has does nothing
+            SetOp::Union => (Keep::All, Keep::All),
+            SetOp::Intersection => (Keep::InOther, Keep::Nothing),
+            SetOp::Difference => (Keep::NotInOther, Keep::Nothing),
+            SetOp::SymmetricDifference => (Keep::NotInOther, Keep::NotInOther),
+        }
+    }
+}
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Returns the set type with the given element type, creating it if it does not exist yet.
+    ///
+    /// A set is a hash table whose entries have no values. Only the pointer functions are created
+    /// here, all other methods are created once they are used.
+    pub(in crate::codegen) fn set_of(
+        &mut self,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<TypeId, GenError> {
+        let ident = format!("{}[{}]", SET_NAME, env.type_id_ident(elem));
+        if let Ok(tid) = env.find_type(&ident) {
+            return Ok(tid);
+        }
+
+        let struct_type = self.create_struct_type(&ident, self.hash_table_fields());
+        let tid = env.gen_type_id();
+        env.register_type(
+            &ident,
+            tid,
+            TypeDef::new_generic(&ident, struct_type, TypeInfo::Set(elem)),
+        )?;
+
+        let prev_block = self.builder.get_insert_block().unwrap();
+
+        self.build_free_ptr_fn(tid, set_unalloc, env)?;
+        self.build_copy_ptr_fn(tid, env)?;
+        self.build_get_reference_count_fn(tid, env)?;
+
+        self.builder.position_at_end(prev_block);
+
+        Ok(tid)
+    }
+
+    /// Builds a set containing the given elements, taking ownership of them.
+    pub(in crate::codegen) fn build_set(
+        &mut self,
+        tid: TypeId,
+        elems: &[PointerValue<'ctx>],
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        let TypeInfo::Set(elem) = tid.get_from(env).info() else {
+            unreachable!()
+        };
+        let add_fn = self.find_method("add", tid, &[tid, elem], env)?;
+
+        let set = self.build_empty_hash_table(tid, elems.len(), env)?;
+
+        for elem_ptr in elems {
+            self.copy_pointer(set, tid, env)?;
+            let (none, ..) = self.call_func(add_fn, &[set, *elem_ptr], env)?;
+            self.free_pointer(none, NONE_ID, env)?;
+        }
+
+        Ok(set)
+    }
+
+    /// Creates the method of a set type with the given name, returning false if sets do not
+    /// have a method with that name.
+    pub(super) fn create_set_method(
+        &mut self,
+        ident: &str,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<bool, GenError> {
+        match ident {
+            LEN_FN => self.setup_set_len(tid, env)?,
+            TO_BOOL_FN => self.setup_set_to_bool(tid, env)?,
+            TO_STR_FN => self.setup_set_to_str(tid, elem, env)?,
+            CONTAINS_FN => self.setup_set_contains(tid, elem, env)?,
+            ITER_FN => self.setup_set_iter(tid, elem, env)?,
+            "add" => self.setup_set_add(tid, elem, env)?,
+            "remove" => self.setup_set_remove(tid, elem, env)?,
+            "discard" => self.setup_set_discard(tid, elem, env)?,
+            _ if ident == BinaryFnOp::Equal.fn_name() => self.setup_set_eq(tid, elem, env)?,
+            _ if ident == BinaryFnOp::BitOr.fn_name() => {
+                self.setup_set_op(ident, SetOp::Union, tid, elem, env)?
+            }
+            _ if ident == BinaryFnOp::BitAnd.fn_name() => {
+                self.setup_set_op(ident, SetOp::Intersection, tid, elem, env)?
+            }
+            _ if ident == BinaryFnOp::Subtract.fn_name() => {
+                self.setup_set_op(ident, SetOp::Difference, tid, elem, env)?
+            }
+            _ if ident == BinaryFnOp::BitXor.fn_name() => {
+                self.setup_set_op(ident, SetOp::SymmetricDifference, tid, elem, env)?
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn setup_set_len(&mut self, tid: TypeId, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_unary_fn(
+            LEN_FN,
+            tid,
+            INT_ID,
+            true,
+            |gen, _fn_val, set, env| {
+                let len = gen.build_hash_table_len(set, tid, env)?;
+                gen.build_struct(INT_ID.get_from(env).ink(), vec![len.into()])
+            },
+            env,
+        )
+    }
+
+    fn setup_set_to_bool(
+        &mut self,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        self.create_unary_fn(
+            TO_BOOL_FN,
+            tid,
+            BOOL_ID,
+            true,
+            |gen, _fn_val, set, env| {
+                let len = gen.build_hash_table_len(set, tid, env)?;
+                let not_empty = gen.builder.build_int_compare(
+                    IntPredicate::NE,
+                    len,
+                    gen.len_type().const_zero(),
+                    "not_empty",
+                )?;
+                gen.build_struct(BOOL_ID.get_from(env).ink(), vec![not_empty.into()])
+            },
+            env,
+        )
+    }
+
+    fn setup_set_to_str(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let elem_to_str_fn = self.find_method(TO_STR_FN, elem, &[elem], env)?;
+
+        self.create_unary_fn(
+            TO_STR_FN,
+            tid,
+            STR_ID,
+            true,
+            |gen, fn_val, set, env| {
+                let empty_block = gen.ctx.append_basic_block(fn_val, "empty_set");
+                let elems_block = gen.ctx.append_basic_block(fn_val, "set_elems");
+                let end_block = gen.ctx.append_basic_block(fn_val, "set_str");
+
+                // Like Python, `{}` is an empty dict, so empty sets are shown as `set()`
+                let len = gen.build_hash_table_len(set, tid, env)?;
+                let is_empty = gen.builder.build_int_compare(
+                    IntPredicate::EQ,
+                    len,
+                    gen.len_type().const_zero(),
+                    "is_empty",
+                )?;
+                gen.builder
+                    .build_conditional_branch(is_empty, empty_block, elems_block)?;
+
+                gen.builder.position_at_end(empty_block);
+                let empty_str = gen.build_str_const("set()", env)?;
+                gen.builder.build_unconditional_branch(end_block)?;
+
+                gen.builder.position_at_end(elems_block);
+                let result_var = gen.builder.build_alloca(gen.ptr_type(), "result_var")?;
+                let open = gen.build_str_const("{", env)?;
+                gen.builder.build_store(result_var, open)?;
+
+                gen.build_append_hash_table_entries(
+                    result_var,
+                    set,
+                    tid,
+                    |gen, entry, env| {
+                        let elem_ptr = gen.build_load_entry_ptr(entry, ENTRY_KEY_FIELD)?;
+                        gen.build_append_repr(result_var, elem_ptr, elem, elem_to_str_fn, env)
+                    },
+                    env,
+                )?;
+
+                let close = gen.build_str_const("}", env)?;
+                gen.build_append_str(result_var, close, env)?;
+
+                let elems_str = gen
+                    .builder
+                    .build_load(gen.ptr_type(), result_var, "elems_str")?
+                    .into_pointer_value();
+                let elems_end_block = gen.builder.get_insert_block().unwrap();
+                gen.builder.build_unconditional_branch(end_block)?;
+
+                gen.builder.position_at_end(end_block);
+                let result = gen.builder.build_phi(gen.ptr_type(), "result")?;
+                result.add_incoming(&[(&empty_str, empty_block), (&elems_str, elems_end_block)]);
+
+                Ok(result.as_basic_value().into_pointer_value())
+            },
+            env,
+        )
+    }
+
+    fn setup_set_contains(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let key_fns = self.find_key_fns(elem, env)?;
+
+        self.create_binary_fn(
+            CONTAINS_FN,
+            tid,
+            elem,
+            BOOL_ID,
+            true,
+            |gen, set, elem_ptr, env| {
+                let contains =
+                    gen.build_hash_table_contains(set, tid, elem_ptr, elem, key_fns, env)?;
+                gen.build_struct(BOOL_ID.get_from(env).ink(), vec![contains.into()])
+            },
+            env,
+        )
+    }
+
+    fn setup_set_iter(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let list_tid = self.list_of(elem, env)?;
+        let append_fn = self.find_method("append", list_tid, &[list_tid, elem], env)?;
+
+        self.create_unary_fn(
+            ITER_FN,
+            tid,
+            list_tid,
+            true,
+            |gen, _fn_val, set, env| {
+                let list = gen.build_list(list_tid, &[], env)?;
+
+                gen.build_hash_table_entry_loop(
+                    set,
+                    tid,
+                    |gen, entry, env| {
+                        let elem_ptr = gen.build_load_entry_ptr(entry, ENTRY_KEY_FIELD)?;
+                        gen.copy_pointer(elem_ptr, elem, env)?;
+
+                        gen.copy_pointer(list, list_tid, env)?;
+                        let (none, ..) = gen.call_func(append_fn, &[list, elem_ptr], env)?;
+                        gen.free_pointer(none, NONE_ID, env)
+                    },
+                    env,
+                )?;
+
+                Ok(list)
+            },
+            env,
+        )
+    }
+
+    fn setup_set_add(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let key_fns = self.find_key_fns(elem, env)?;
+
+        let (fn_val, ..) = env.create_func(Some(tid), "add", &[tid, elem], NONE_ID, false)?;
+        let entry = self.ctx.append_basic_block(fn_val, "entry");
+        self.builder.position_at_end(entry);
+        let set = fn_val.get_nth_param(0).unwrap().into_pointer_value();
+        let elem_ptr = fn_val.get_nth_param(1).unwrap().into_pointer_value();
+
+        // Make space first, since growing the set moves its entries
+        self.build_hash_table_reserve_one(set, tid, env)?;
+
+        let hash = self.build_hash(elem_ptr, elem, key_fns, env)?;
+        let (slot, index) =
+            self.build_hash_table_lookup(set, tid, elem_ptr, hash, elem, key_fns, env)?;
+
+        let existing_block = self.ctx.append_basic_block(fn_val, "existing");
+        let insert_block = self.ctx.append_basic_block(fn_val, "insert");
+        let end_block = self.ctx.append_basic_block(fn_val, "end");
+
+        let is_missing = self.build_is_empty_slot(index)?;
+        self.builder
+            .build_conditional_branch(is_missing, insert_block, existing_block)?;
+
+        // The set keeps the element it already has
+        self.builder.position_at_end(existing_block);
+        self.free_pointer(elem_ptr, elem, env)?;
+        self.builder.build_unconditional_branch(end_block)?;
+
+        self.builder.position_at_end(insert_block);
+        let no_value = self.ptr_type().const_null();
+        self.build_hash_table_insert(set, tid, slot, hash, elem_ptr, no_value, env)?;
+        self.builder.build_unconditional_branch(end_block)?;
+
+        self.builder.position_at_end(end_block);
+        self.free_pointer(set, tid, env)?;
+
+        let none = self.build_none(env)?;
+        self.builder.build_return(Some(&none))?;
+
+        Ok(())
+    }
+
+    fn setup_set_remove(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let key_fns = self.find_key_fns(elem, env)?;
+
+        self.create_binary_fn(
+            "remove",
+            tid,
+            elem,
+            NONE_ID,
+            true,
+            |gen, set, elem_ptr, env| {
+                let entry = gen.build_hash_table_find_existing(
+                    set,
+                    tid,
+                    elem_ptr,
+                    elem,
+                    key_fns,
+                    ELEM_NOT_FOUND_MSG,
+                    env,
+                )?;
+                gen.build_set_remove_entry(set, tid, entry, elem, env)?;
+
+                gen.build_none(env)
+            },
+            env,
+        )
+    }
+
+    fn setup_set_discard(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let key_fns = self.find_key_fns(elem, env)?;
+
+        // Unlike remove, discarding an element the set does not contain does nothing
+        self.create_binary_fn(
+            "discard",
+            tid,
+            elem,
+            NONE_ID,
+            true,
+            |gen, set, elem_ptr, env| {
+                let cur_fn = gen
+                    .builder
+                    .get_insert_block()
+                    .unwrap()
+                    .get_parent()
+                    .unwrap();
+                let found_block = gen.ctx.append_basic_block(cur_fn, "found");
+                let end_block = gen.ctx.append_basic_block(cur_fn, "end");
+
+                let hash = gen.build_hash(elem_ptr, elem, key_fns, env)?;
+                let (_, index) =
+                    gen.build_hash_table_lookup(set, tid, elem_ptr, hash, elem, key_fns, env)?;
+                let is_missing = gen.build_is_empty_slot(index)?;
+                gen.builder
+                    .build_conditional_branch(is_missing, end_block, found_block)?;
+
+                gen.builder.position_at_end(found_block);
+                let entry = gen.build_hash_table_entry_ptr(set, tid, index, env)?;
+                gen.build_set_remove_entry(set, tid, entry, elem, env)?;
+                gen.builder.build_unconditional_branch(end_block)?;
+
+                gen.builder.position_at_end(end_block);
+                gen.build_none(env)
+            },
+            env,
+        )
+    }
+
+    fn setup_set_eq(
+        &mut self,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let key_fns = self.find_key_fns(elem, env)?;
+
+        // Sets are equal when they have the same length and one contains every element of the other
+        self.create_binary_fn(
+            BinaryFnOp::Equal.fn_name(),
+            tid,
+            tid,
+            BOOL_ID,
+            true,
+            |gen, left, right, env| {
+                let cur_fn = gen
+                    .builder
+                    .get_insert_block()
+                    .unwrap()
+                    .get_parent()
+                    .unwrap();
+                let compare_block = gen.ctx.append_basic_block(cur_fn, "compare_elems");
+                let not_equal_block = gen.ctx.append_basic_block(cur_fn, "not_equal");
+                let result_block = gen.ctx.append_basic_block(cur_fn, "eq_result");
+
+                let left_len = gen.build_hash_table_len(left, tid, env)?;
+                let right_len = gen.build_hash_table_len(right, tid, env)?;
+                let same_len = gen.builder.build_int_compare(
+                    IntPredicate::EQ,
+                    left_len,
+                    right_len,
+                    "same_len",
+                )?;
+                gen.builder
+                    .build_conditional_branch(same_len, compare_block, not_equal_block)?;
+
+                gen.builder.position_at_end(compare_block);
+                gen.build_hash_table_entry_loop(
+                    left,
+                    tid,
+                    |gen, entry, env| {
+                        let elem_ptr = gen.build_load_entry_ptr(entry, ENTRY_KEY_FIELD)?;
+                        let contains = gen
+                            .build_hash_table_contains(right, tid, elem_ptr, elem, key_fns, env)?;
+
+                        let continue_block = gen.ctx.append_basic_block(cur_fn, "elem_found");
+                        gen.builder.build_conditional_branch(
+                            contains,
+                            continue_block,
+                            not_equal_block,
+                        )?;
+                        gen.builder.position_at_end(continue_block);
+
+                        Ok(())
+                    },
+                    env,
+                )?;
+                let equal_block = gen.builder.get_insert_block().unwrap();
+                gen.builder.build_unconditional_branch(result_block)?;
+
+                gen.builder.position_at_end(not_equal_block);
+                gen.builder.build_unconditional_branch(result_block)?;
+
+                gen.builder.position_at_end(result_block);
+                let bool_type = gen.ctx.bool_type();
+                let is_eq = gen.builder.build_phi(bool_type, "set_eq")?;
+                is_eq.add_incoming(&[
+                    (&bool_type.const_int(1, false), equal_block),
+                    (&bool_type.const_zero(), not_equal_block),
+                ]);
+
+                gen.build_struct(BOOL_ID.get_from(env).ink(), vec![is_eq.as_basic_value()])
+            },
+            env,
+        )
+    }
+
+    /// Creates an operator that builds a new set from the elements of two sets.
+    fn setup_set_op(
+        &mut self,
+        ident: &str,
+        op: SetOp,
+        tid: TypeId,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let key_fns = self.find_key_fns(elem, env)?;
+        let add_fn = self.find_method("add", tid, &[tid, elem], env)?;
+
+        self.create_binary_fn(
+            ident,
+            tid,
+            tid,
+            tid,
+            true,
+            |gen, left, right, env| {
+                let result = gen.build_empty_hash_table(tid, 0, env)?;
+
+                let (keep_left, keep_right) = op.keeps();
+                for (source, other, keep) in [(left, right, keep_left), (right, left, keep_right)] {
+                    if keep == Keep::Nothing {
+                        continue;
+                    }
+
+                    gen.build_hash_table_entry_loop(
+                        source,
+                        tid,
+                        |gen, entry, env| {
+                            let elem_ptr = gen.build_load_entry_ptr(entry, ENTRY_KEY_FIELD)?;
+                            gen.build_set_add_if(
+                                result, tid, elem_ptr, elem, other, keep, key_fns, add_fn, env,
+                            )
+                        },
+                        env,
+                    )?;
+                }
+
+                Ok(result)
+            },
+            env,
+        )
+    }
+
+    /// Adds a copy of an element to the result of a set operator if `keep` accepts it, which
+    /// depends on whether the other operand contains it.
+    #[allow(clippy::too_many_arguments)]
+    fn build_set_add_if(
+        &mut self,
+        result: PointerValue<'ctx>,
+        tid: TypeId,
+        elem_ptr: PointerValue<'ctx>,
+        elem: TypeId,
+        other: PointerValue<'ctx>,
+        keep: Keep,
+        key_fns: KeyFns,
+        add_fn: FunctionId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let cur_fn = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let add_block = self.ctx.append_basic_block(cur_fn, "add_elem");
+        let end_block = self.ctx.append_basic_block(cur_fn, "elem_done");
+
+        match keep {
+            Keep::All => {
+                self.builder.build_unconditional_branch(add_block)?;
+            }
+            Keep::InOther | Keep::NotInOther => {
+                let in_other =
+                    self.build_hash_table_contains(other, tid, elem_ptr, elem, key_fns, env)?;
+                let (then_block, else_block) = if keep == Keep::InOther {
+                    (add_block, end_block)
+                } else {
+                    (end_block, add_block)
+                };
+                self.builder
+                    .build_conditional_branch(in_other, then_block, else_block)?;
+            }
+            Keep::Nothing => unreachable!(),
+        }
+
+        self.builder.position_at_end(add_block);
+        self.copy_pointer(elem_ptr, elem, env)?;
+        self.copy_pointer(result, tid, env)?;
+        let (none, ..) = self.call_func(add_fn, &[result, elem_ptr], env)?;
+        self.free_pointer(none, NONE_ID, env)?;
+        self.builder.build_unconditional_branch(end_block)?;
+
+        self.builder.position_at_end(end_block);
+
+        Ok(())
+    }
+
+    /// Releases the element of an entry and marks the entry as deleted.
+    fn build_set_remove_entry(
+        &mut self,
+        set: PointerValue<'ctx>,
+        tid: TypeId,
+        entry: PointerValue<'ctx>,
+        elem: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let old_elem = self.build_load_entry_ptr(entry, ENTRY_KEY_FIELD)?;
+        self.free_pointer(old_elem, elem, env)?;
+        self.build_hash_table_remove(set, tid, entry, env)
+    }
+}
+
+fn set_unalloc<'ctx>(
+    ptr: PointerValue<'ctx>,
+    tid: TypeId,
+    gen: &mut CodeGen<'ctx>,
+    env: &mut Environment<'ctx>,
+) -> Result<(), GenError> {
+    let TypeInfo::Set(elem) = tid.get_from(env).info() else {
+        unreachable!()
+    };
+
+    gen.build_hash_table_entry_loop(
+        ptr,
+        tid,
+        |gen, entry, env| {
+            let elem_ptr = gen.build_load_entry_ptr(entry, ENTRY_KEY_FIELD)?;
+            gen.free_pointer(elem_ptr, elem, env)
+        },
+        env,
+    )?;
+
+    gen.build_free_hash_table_buffers(ptr, tid, env)
+}
@@ -0,0 +1,299 @@
+use inkwell::{
+    types::BasicType,
+    values::{IntValue, PointerValue},
+    IntPredicate,
+};
+
+use crate::codegen::{
+    env::{
+        id::{TypeId, INT_ID},
+        type_def::{Field, TypeDef},
+        Environment,
+    },
+    err::GenError,
+    CodeGen,
+};
+
+pub const SLICE_NAME: &str = "slice";
+
+// Fields of the slice struct, which are all `Optional[int]`
+const START_FIELD: u32 = 0;
+const STOP_FIELD: u32 = 1;
+const STEP_FIELD: u32 = 2;
+
+/// The positions selected by a slice of a sequence, after the bounds have been clamped to its length.
+pub(in crate::codegen) struct SliceRange<'ctx> {
+    pub start: IntValue<'ctx>,
+    pub step: IntValue<'ctx>,
+    pub len: IntValue<'ctx>,
+}
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Sets up the `slice` type created by `start:stop:step` indexes, which is a struct with
+    /// optional int fields so that user types can read its bounds in `__getitem__`.
+    pub(super) fn setup_slice(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        let bound_tid = self.optional_of(INT_ID, env)?;
+
+        let struct_type =
+            self.create_struct_type(SLICE_NAME, vec![self.ptr_type().as_basic_type_enum(); 3]);
+        let fields = ["start", "stop", "step"]
+            .iter()
+            .enumerate()
+            .map(|(i, ident)| Field::new(i as u32, ident, bound_tid))
+            .collect();
+        let tid = env.gen_type_id();
+        env.register_type(
+            SLICE_NAME,
+            tid,
+            TypeDef::new(SLICE_NAME, struct_type, fields),
+        )?;
+
+        let prev_block = self.builder.get_insert_block().unwrap();
+
+        self.build_free_ptr_fn(tid, Self::struct_unalloc, env)?;
+        self.build_copy_ptr_fn(tid, env)?;
+        self.build_get_reference_count_fn(tid, env)?;
+        self.build_struct_constructor(SLICE_NAME, tid, &[], env)?;
+
+        self.builder.position_at_end(prev_block);
+
+        Ok(())
+    }
+
+    /// Builds a slice from its bounds, taking ownership of them. Missing bounds are `None`.
+    pub(in crate::codegen) fn build_slice(
+        &mut self,
+        bounds: [PointerValue<'ctx>; 3],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let tid = env.find_type(SLICE_NAME)?;
+        let slice = self.build_struct(
+            tid.get_from(env).ink(),
+            bounds.iter().map(|bound| (*bound).into()).collect(),
+        )?;
+
+        Ok((slice, tid))
+    }
+
+    /// Works out which positions of a sequence with the given length a slice selects, following
+    /// Python's rules for missing, negative and out of range bounds. Aborts if the step is zero.
+    pub(in crate::codegen) fn build_slice_range(
+        &mut self,
+        slice: PointerValue<'ctx>,
+        tid: TypeId,
+        seq_len: IntValue<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<SliceRange<'ctx>, GenError> {
+        let int_type = self.len_type();
+        let zero = int_type.const_zero();
+        let one = int_type.const_int(1, false);
+        let minus_one = int_type.const_int(-1i64 as u64, true);
+
+        let (has_step, step) = self.build_load_slice_bound(slice, tid, STEP_FIELD, env)?;
+        let step = self
+            .builder
+            .build_select(has_step, step, one, "step")?
+            .into_int_value();
+        let step_is_zero =
+            self.builder
+                .build_int_compare(IntPredicate::EQ, step, zero, "step_is_zero")?;
+        self.build_runtime_check(step_is_zero, "slice step cannot be zero")?;
+
+        let is_reversed =
+            self.builder
+                .build_int_compare(IntPredicate::SLT, step, zero, "is_reversed")?;
+        let last_index = self.builder.build_int_sub(seq_len, one, "last_index")?;
+
+        // Bounds below the start or past the end stop at the first or last position in the
+        // direction of the step, where -1 is before the start rather than the last element
+        let lower = self
+            .builder
+            .build_select(is_reversed, minus_one, zero, "lower")?
+            .into_int_value();
+        let upper = self
+            .builder
+            .build_select(is_reversed, last_index, seq_len, "upper")?
+            .into_int_value();
+
+        let (has_start, start) = self.build_load_slice_bound(slice, tid, START_FIELD, env)?;
+        let start = self.build_clamp_slice_bound(start, seq_len, lower, upper)?;
+        let default_start =
+            self.builder
+                .build_select(is_reversed, last_index, zero, "default_start")?;
+        let start = self
+            .builder
+            .build_select(has_start, start.into(), default_start, "start")?
+            .into_int_value();
+
+        let (has_stop, stop) = self.build_load_slice_bound(slice, tid, STOP_FIELD, env)?;
+        let stop = self.build_clamp_slice_bound(stop, seq_len, lower, upper)?;
+        let default_stop =
+            self.builder
+                .build_select(is_reversed, minus_one, seq_len, "default_stop")?;
+        let stop = self
+            .builder
+            .build_select(has_stop, stop.into(), default_stop, "stop")?
+            .into_int_value();
+
+        // len = (distance - 1) / |step| + 1 when the stop is ahead of the start
+        let forward_distance = self
+            .builder
+            .build_int_sub(stop, start, "forward_distance")?;
+        let reverse_distance = self
+            .builder
+            .build_int_sub(start, stop, "reverse_distance")?;
+        let distance = self
+            .builder
+            .build_select(is_reversed, reverse_distance, forward_distance, "distance")?
+            .into_int_value();
+        let negated_step = self.builder.build_int_neg(step, "negated_step")?;
+        let step_size = self
+            .builder
+            .build_select(is_reversed, negated_step, step, "step_size")?
+            .into_int_value();
+
+        let last_offset = self.builder.build_int_sub(distance, one, "last_offset")?;
+        let steps = self
+            .builder
+            .build_int_signed_div(last_offset, step_size, "steps")?;
+        let len = self.builder.build_int_add(steps, one, "len")?;
+        let is_empty =
+            self.builder
+                .build_int_compare(IntPredicate::SLE, distance, zero, "is_empty")?;
+        let len = self
+            .builder
+            .build_select(is_empty, zero, len, "slice_len")?
+            .into_int_value();
+
+        Ok(SliceRange { start, step, len })
+    }
+
+    /// Returns the position of the element a slice selects at the given offset.
+    pub(in crate::codegen) fn build_slice_index(
+        &mut self,
+        range: &SliceRange<'ctx>,
+        offset: IntValue<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        let distance = self.builder.build_int_mul(offset, range.step, "distance")?;
+        Ok(self
+            .builder
+            .build_int_add(range.start, distance, "slice_index")?)
+    }
+
+    /// Whether a slice selects the element at the given position.
+    pub(in crate::codegen) fn build_slice_contains(
+        &mut self,
+        range: &SliceRange<'ctx>,
+        index: IntValue<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        let zero = self.len_type().const_zero();
+
+        let distance = self.builder.build_int_sub(index, range.start, "distance")?;
+        let offset = self
+            .builder
+            .build_int_signed_div(distance, range.step, "offset")?;
+        let remainder = self
+            .builder
+            .build_int_signed_rem(distance, range.step, "remainder")?;
+
+        let on_step =
+            self.builder
+                .build_int_compare(IntPredicate::EQ, remainder, zero, "on_step")?;
+        let after_start =
+            self.builder
+                .build_int_compare(IntPredicate::SGE, offset, zero, "after_start")?;
+        let before_stop =
+            self.builder
+                .build_int_compare(IntPredicate::SLT, offset, range.len, "before_stop")?;
+
+        let in_range = self
+            .builder
+            .build_and(after_start, before_stop, "in_range")?;
+        Ok(self.builder.build_and(on_step, in_range, "in_slice")?)
+    }
+
+    /// Loads a bound of a slice, returning whether it was given and its value, which is zero
+    /// when it was not.
+    fn build_load_slice_bound(
+        &mut self,
+        slice: PointerValue<'ctx>,
+        tid: TypeId,
+        field: u32,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(IntValue<'ctx>, IntValue<'ctx>), GenError> {
+        let cur_fn = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let given_block = self.ctx.append_basic_block(cur_fn, "bound_given");
+        let merge_block = self.ctx.append_basic_block(cur_fn, "bound");
+
+        let bound_ptr_ptr = self.builder.build_struct_gep(
+            tid.get_from(env).ink(),
+            slice,
+            field,
+            "bound_ptr_ptr",
+        )?;
+        let bound_ptr = self
+            .builder
+            .build_load(self.ptr_type(), bound_ptr_ptr, "bound_ptr")?
+            .into_pointer_value();
+        let is_given = self.builder.build_is_not_null(bound_ptr, "is_given")?;
+        let missing_block = self.builder.get_insert_block().unwrap();
+        self.builder
+            .build_conditional_branch(is_given, given_block, merge_block)?;
+
+        self.builder.position_at_end(given_block);
+        let given = self
+            .extract_primitive(bound_ptr, INT_ID.get_from(env).ink())?
+            .into_int_value();
+        self.builder.build_unconditional_branch(merge_block)?;
+
+        self.builder.position_at_end(merge_block);
+        let bound = self.builder.build_phi(self.len_type(), "bound_val")?;
+        bound.add_incoming(&[
+            (&given, given_block),
+            (&self.len_type().const_zero(), missing_block),
+        ]);
+
+        Ok((is_given, bound.as_basic_value().into_int_value()))
+    }
+
+    /// Wraps a negative bound around the end of the sequence, then clamps it between the limits.
+    fn build_clamp_slice_bound(
+        &mut self,
+        bound: IntValue<'ctx>,
+        seq_len: IntValue<'ctx>,
+        lower: IntValue<'ctx>,
+        upper: IntValue<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        let zero = self.len_type().const_zero();
+
+        let is_negative =
+            self.builder
+                .build_int_compare(IntPredicate::SLT, bound, zero, "is_negative")?;
+        let wrapped = self.builder.build_int_add(bound, seq_len, "wrapped")?;
+        let bound = self
+            .builder
+            .build_select(is_negative, wrapped, bound, "wrapped_bound")?
+            .into_int_value();
+
+        let below_start =
+            self.builder
+                .build_int_compare(IntPredicate::SLT, bound, zero, "below_start")?;
+        let bound = self
+            .builder
+            .build_select(below_start, lower, bound, "lower_bound")?
+            .into_int_value();
+
+        let past_end =
+            self.builder
+                .build_int_compare(IntPredicate::SGT, bound, upper, "past_end")?;
+        Ok(self
+            .builder
+            .build_select(past_end, upper, bound, "clamped_bound")?
+            .into_int_value())
+    }
+}
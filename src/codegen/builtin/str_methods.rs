@@ -0,0 +1,1133 @@
+use inkwell::{
+    values::{IntValue, PointerValue},
+    IntPredicate,
+};
+
+use crate::{
+    codegen::{
+        env::{
+            id::{FunctionId, TypeId, BOOL_ID, INT_ID, NONE_ID, STR_ID},
+            Environment,
+        },
+        err::GenError,
+        CodeGen,
+    },
+    parser::BinaryFnOp,
+};
+
+use super::{slice::SLICE_NAME, CONTAINS_FN, GET_ITEM_FN, LEN_FN, TO_STR_FN};
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Sets up the methods of `str` that do not depend on the types of their arguments.
+    pub(super) fn setup_str_methods(
+        &mut self,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        self.setup_str_len(env)?;
+        self.setup_str_get_item(env)?;
+        self.setup_str_contains(env)?;
+        self.setup_str_mul(env)?;
+
+        self.setup_str_find(env)?;
+        self.setup_str_replace(env)?;
+        self.setup_str_split(env)?;
+        self.setup_str_join(env)?;
+
+        self.setup_str_strip("strip", true, true, env)?;
+        self.setup_str_strip("lstrip", true, false, env)?;
+        self.setup_str_strip("rstrip", false, true, env)?;
+        self.setup_str_change_case("upper", b'a', env)?;
+        self.setup_str_change_case("lower", b'A', env)?;
+        self.setup_str_affix("startswith", false, env)?;
+        self.setup_str_affix("endswith", true, env)?;
+
+        Ok(())
+    }
+
+    /// Creates the method of `str` with the given name for the given argument types, returning
+    /// false if `str` does not have a method with that name.
+    ///
+    /// Only `format` is created on use, since it takes any number of arguments of any type.
+    pub(super) fn create_str_method(
+        &mut self,
+        ident: &str,
+        arg_tids: &[TypeId],
+        env: &mut Environment<'ctx>,
+    ) -> Result<bool, GenError> {
+        match ident {
+            "format" => self.setup_str_format(&arg_tids[1..], env)?,
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn setup_str_len(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_unary_fn(
+            LEN_FN,
+            STR_ID,
+            INT_ID,
+            true,
+            |gen, _fn_val, string, env| {
+                let (_, len) = gen.build_extract_string(string, STR_ID.get_from(env).ink())?;
+                gen.build_struct(INT_ID.get_from(env).ink(), vec![len.into()])
+            },
+            env,
+        )
+    }
+
+    fn setup_str_get_item(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        // Indexing returns the character at the index as a string
+        self.create_binary_fn(
+            GET_ITEM_FN,
+            STR_ID,
+            INT_ID,
+            STR_ID,
+            true,
+            |gen, string, index, env| {
+                let (data, len) = gen.build_extract_string(string, STR_ID.get_from(env).ink())?;
+                let index = gen
+                    .extract_primitive(index, INT_ID.get_from(env).ink())?
+                    .into_int_value();
+
+                // Negative indices count from the end and are out of range when compared as unsigned
+                let is_negative = gen.builder.build_int_compare(
+                    IntPredicate::SLT,
+                    index,
+                    gen.len_type().const_zero(),
+                    "is_negative",
+                )?;
+                let wrapped = gen.builder.build_int_add(index, len, "wrapped_index")?;
+                let index = gen
+                    .builder
+                    .build_select(is_negative, wrapped, index, "index")?
+                    .into_int_value();
+                let out_of_range =
+                    gen.builder
+                        .build_int_compare(IntPredicate::UGE, index, len, "out_of_range")?;
+                gen.build_runtime_check(out_of_range, "string index out of range")?;
+
+                gen.build_substr(data, index, gen.len_type().const_int(1, false), env)
+            },
+            env,
+        )?;
+
+        // Slicing copies the selected characters into a new string
+        let slice_tid = env.find_type(SLICE_NAME)?;
+        self.create_binary_fn(
+            GET_ITEM_FN,
+            STR_ID,
+            slice_tid,
+            STR_ID,
+            true,
+            |gen, string, slice, env| {
+                let (data, len) = gen.build_extract_string(string, STR_ID.get_from(env).ink())?;
+                let range = gen.build_slice_range(slice, slice_tid, len, env)?;
+
+                let new_data = gen.build_str_data_malloc(range.len, "sliced_str_data")?;
+                gen.build_index_loop(
+                    range.len,
+                    |gen, offset, _env| {
+                        let index = gen.build_slice_index(&range, offset)?;
+                        let char_val = unsafe { gen.build_extract_char(data, index)? };
+                        let char_ptr = gen.build_char_ptr(new_data, offset)?;
+                        gen.builder.build_store(char_ptr, char_val)?;
+                        Ok(())
+                    },
+                    env,
+                )?;
+
+                gen.build_str_struct(new_data, range.len, env)
+            },
+            env,
+        )
+    }
+
+    fn setup_str_contains(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        self.create_binary_fn(
+            CONTAINS_FN,
+            STR_ID,
+            STR_ID,
+            BOOL_ID,
+            true,
+            |gen, string, sub, env| {
+                let str_type = STR_ID.get_from(env).ink();
+                let (data, len) = gen.build_extract_string(string, str_type)?;
+                let (sub_data, sub_len) = gen.build_extract_string(sub, str_type)?;
+
+                let zero = gen.len_type().const_zero();
+                let index = gen.build_str_find(data, len, sub_data, sub_len, zero)?;
+                let found =
+                    gen.builder
+                        .build_int_compare(IntPredicate::SGE, index, zero, "found")?;
+
+                gen.build_struct(BOOL_ID.get_from(env).ink(), vec![found.into()])
+            },
+            env,
+        )
+    }
+
+    fn setup_str_mul(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        // Repeats the string, where repeating it zero or fewer times gives an empty string
+        self.create_binary_fn(
+            BinaryFnOp::Multiply.fn_name(),
+            STR_ID,
+            INT_ID,
+            STR_ID,
+            true,
+            |gen, string, times, env| {
+                let (data, len) = gen.build_extract_string(string, STR_ID.get_from(env).ink())?;
+                let times = gen
+                    .extract_primitive(times, INT_ID.get_from(env).ink())?
+                    .into_int_value();
+
+                let zero = gen.len_type().const_zero();
+                let is_negative =
+                    gen.builder
+                        .build_int_compare(IntPredicate::SLT, times, zero, "is_negative")?;
+                let times = gen
+                    .builder
+                    .build_select(is_negative, zero, times, "times")?
+                    .into_int_value();
+
+                let new_len = gen.builder.build_int_mul(len, times, "new_len")?;
+                let new_data = gen.build_str_data_malloc(new_len, "repeated_str_data")?;
+                gen.build_index_loop(
+                    times,
+                    |gen, i, _env| {
+                        let offset = gen.builder.build_int_mul(i, len, "offset")?;
+                        let dest = gen.build_char_ptr(new_data, offset)?;
+                        gen.builder.build_memcpy(dest, 1, data, 1, len)?;
+                        Ok(())
+                    },
+                    env,
+                )?;
+
+                gen.build_str_struct(new_data, new_len, env)
+            },
+            env,
+        )
+    }
+
+    fn setup_str_find(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        // Returns the index of the first occurrence of the substring, or -1 if there is none
+        self.create_binary_fn(
+            "find",
+            STR_ID,
+            STR_ID,
+            INT_ID,
+            true,
+            |gen, string, sub, env| {
+                let str_type = STR_ID.get_from(env).ink();
+                let (data, len) = gen.build_extract_string(string, str_type)?;
+                let (sub_data, sub_len) = gen.build_extract_string(sub, str_type)?;
+
+                let zero = gen.len_type().const_zero();
+                let index = gen.build_str_find(data, len, sub_data, sub_len, zero)?;
+
+                gen.build_struct(INT_ID.get_from(env).ink(), vec![index.into()])
+            },
+            env,
+        )
+    }
+
+    fn setup_str_replace(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        let str_type = STR_ID.get_from(env).ink();
+
+        let (fn_val, ..) = env.create_func(Some(STR_ID), "replace", &[STR_ID; 3], STR_ID, false)?;
+        let (string, old, new) = self.build_ternary_fn_entry(fn_val);
+
+        let (data, len) = self.build_extract_string(string, str_type)?;
+        let (old_data, old_len) = self.build_extract_string(old, str_type)?;
+
+        let result_var = self.builder.build_alloca(self.ptr_type(), "result_var")?;
+        let empty = self.build_str_const("", env)?;
+        self.builder.build_store(result_var, empty)?;
+        let pos_var = self.builder.build_alloca(self.len_type(), "pos_var")?;
+        self.builder
+            .build_store(pos_var, self.len_type().const_zero())?;
+
+        let search_block = self.ctx.append_basic_block(fn_val, "search");
+        let found_block = self.ctx.append_basic_block(fn_val, "found");
+        let done_block = self.ctx.append_basic_block(fn_val, "done");
+        self.builder.build_unconditional_branch(search_block)?;
+
+        self.builder.position_at_end(search_block);
+        let pos = self
+            .builder
+            .build_load(self.len_type(), pos_var, "pos")?
+            .into_int_value();
+        let index = self.build_str_find(data, len, old_data, old_len, pos)?;
+        let found = self.builder.build_int_compare(
+            IntPredicate::SGE,
+            index,
+            self.len_type().const_zero(),
+            "found",
+        )?;
+        self.builder
+            .build_conditional_branch(found, found_block, done_block)?;
+
+        // Copy the text before the match, then the replacement
+        self.builder.position_at_end(found_block);
+        let before_len = self.builder.build_int_sub(index, pos, "before_len")?;
+        let before = self.build_substr(data, pos, before_len, env)?;
+        self.build_append_str(result_var, before, env)?;
+        self.copy_pointer(new, STR_ID, env)?;
+        self.build_append_str(result_var, new, env)?;
+
+        // Like Python, an empty pattern matches between every character, so the character after
+        // the match is kept and the search moves past it
+        let one = self.len_type().const_int(1, false);
+        let is_empty = self.builder.build_int_compare(
+            IntPredicate::EQ,
+            old_len,
+            self.len_type().const_zero(),
+            "is_empty",
+        )?;
+        let skip = self
+            .builder
+            .build_select(is_empty, one, old_len, "skip")?
+            .into_int_value();
+        let next_pos = self.builder.build_int_add(index, skip, "next_pos")?;
+        let kept_start = self.builder.build_int_add(index, old_len, "kept_start")?;
+        let kept_end = self.build_int_min(next_pos, len)?;
+        let kept_len = self
+            .builder
+            .build_int_sub(kept_end, kept_start, "kept_len")?;
+        let kept = self.build_substr(data, kept_start, kept_len, env)?;
+        self.build_append_str(result_var, kept, env)?;
+
+        self.builder.build_store(pos_var, next_pos)?;
+        self.builder.build_unconditional_branch(search_block)?;
+
+        // Copy the text after the last match
+        self.builder.position_at_end(done_block);
+        let pos = self
+            .builder
+            .build_load(self.len_type(), pos_var, "pos")?
+            .into_int_value();
+        let rest_start = self.build_int_min(pos, len)?;
+        let rest_len = self.builder.build_int_sub(len, rest_start, "rest_len")?;
+        let rest = self.build_substr(data, rest_start, rest_len, env)?;
+        self.build_append_str(result_var, rest, env)?;
+
+        self.free_pointer(string, STR_ID, env)?;
+        self.free_pointer(old, STR_ID, env)?;
+        self.free_pointer(new, STR_ID, env)?;
+
+        let result = self
+            .builder
+            .build_load(self.ptr_type(), result_var, "result")?
+            .into_pointer_value();
+        self.builder.build_return(Some(&result))?;
+
+        Ok(())
+    }
+
+    fn setup_str_split(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        let list_tid = self.list_of(STR_ID, env)?;
+        let append_fn = self.find_method("append", list_tid, &[list_tid, STR_ID], env)?;
+
+        // split() splits on runs of whitespace, ignoring any at the start or end
+        self.create_unary_fn(
+            "split",
+            STR_ID,
+            list_tid,
+            true,
+            |gen, fn_val, string, env| {
+                let (data, len) = gen.build_extract_string(string, STR_ID.get_from(env).ink())?;
+                let list = gen.build_list(list_tid, &[], env)?;
+
+                // The start of the current word, or -1 between words
+                let minus_one = gen.len_type().const_int(-1i64 as u64, true);
+                let word_start_var = gen.builder.build_alloca(gen.len_type(), "word_start_var")?;
+                gen.builder.build_store(word_start_var, minus_one)?;
+
+                gen.build_index_loop(
+                    len,
+                    |gen, index, env| {
+                        let end_word_block = gen.ctx.append_basic_block(fn_val, "end_word");
+                        let start_word_block = gen.ctx.append_basic_block(fn_val, "start_word");
+                        let next_block = gen.ctx.append_basic_block(fn_val, "next_char");
+
+                        let word_start = gen
+                            .builder
+                            .build_load(gen.len_type(), word_start_var, "word_start")?
+                            .into_int_value();
+                        let in_word = gen.builder.build_int_compare(
+                            IntPredicate::SGE,
+                            word_start,
+                            gen.len_type().const_zero(),
+                            "in_word",
+                        )?;
+                        let char_val = unsafe { gen.build_extract_char(data, index)? };
+                        let is_space = gen.build_is_space(char_val)?;
+
+                        let ends_word = gen.builder.build_and(in_word, is_space, "ends_word")?;
+                        let not_in_word = gen.builder.build_not(in_word, "not_in_word")?;
+                        let not_space = gen.builder.build_not(is_space, "not_space")?;
+                        let starts_word =
+                            gen.builder
+                                .build_and(not_in_word, not_space, "starts_word")?;
+                        let check_start_block = gen.ctx.append_basic_block(fn_val, "check_start");
+                        gen.builder.build_conditional_branch(
+                            ends_word,
+                            end_word_block,
+                            check_start_block,
+                        )?;
+
+                        gen.builder.position_at_end(check_start_block);
+                        gen.builder.build_conditional_branch(
+                            starts_word,
+                            start_word_block,
+                            next_block,
+                        )?;
+
+                        gen.builder.position_at_end(end_word_block);
+                        let word_len = gen.builder.build_int_sub(index, word_start, "word_len")?;
+                        let word = gen.build_substr(data, word_start, word_len, env)?;
+                        gen.build_list_push(list, list_tid, append_fn, word, env)?;
+                        gen.builder.build_store(word_start_var, minus_one)?;
+                        gen.builder.build_unconditional_branch(next_block)?;
+
+                        gen.builder.position_at_end(start_word_block);
+                        gen.builder.build_store(word_start_var, index)?;
+                        gen.builder.build_unconditional_branch(next_block)?;
+
+                        gen.builder.position_at_end(next_block);
+                        Ok(())
+                    },
+                    env,
+                )?;
+
+                // The string may end in the middle of a word
+                let last_word_block = gen.ctx.append_basic_block(fn_val, "last_word");
+                let done_block = gen.ctx.append_basic_block(fn_val, "done");
+                let word_start = gen
+                    .builder
+                    .build_load(gen.len_type(), word_start_var, "word_start")?
+                    .into_int_value();
+                let in_word = gen.builder.build_int_compare(
+                    IntPredicate::SGE,
+                    word_start,
+                    gen.len_type().const_zero(),
+                    "in_word",
+                )?;
+                gen.builder
+                    .build_conditional_branch(in_word, last_word_block, done_block)?;
+
+                gen.builder.position_at_end(last_word_block);
+                let word_len = gen.builder.build_int_sub(len, word_start, "word_len")?;
+                let word = gen.build_substr(data, word_start, word_len, env)?;
+                gen.build_list_push(list, list_tid, append_fn, word, env)?;
+                gen.builder.build_unconditional_branch(done_block)?;
+
+                gen.builder.position_at_end(done_block);
+                Ok(list)
+            },
+            env,
+        )?;
+
+        // split(sep) splits on every occurrence of the separator
+        self.create_binary_fn(
+            "split",
+            STR_ID,
+            STR_ID,
+            list_tid,
+            true,
+            |gen, string, sep, env| {
+                let str_type = STR_ID.get_from(env).ink();
+                let (data, len) = gen.build_extract_string(string, str_type)?;
+                let (sep_data, sep_len) = gen.build_extract_string(sep, str_type)?;
+
+                let is_empty = gen.builder.build_int_compare(
+                    IntPredicate::EQ,
+                    sep_len,
+                    gen.len_type().const_zero(),
+                    "is_empty",
+                )?;
+                gen.build_runtime_check(is_empty, "empty separator")?;
+
+                let list = gen.build_list(list_tid, &[], env)?;
+                let pos_var = gen.builder.build_alloca(gen.len_type(), "pos_var")?;
+                gen.builder
+                    .build_store(pos_var, gen.len_type().const_zero())?;
+
+                let cur_fn = gen
+                    .builder
+                    .get_insert_block()
+                    .unwrap()
+                    .get_parent()
+                    .unwrap();
+                let search_block = gen.ctx.append_basic_block(cur_fn, "search");
+                let found_block = gen.ctx.append_basic_block(cur_fn, "found");
+                let done_block = gen.ctx.append_basic_block(cur_fn, "done");
+                gen.builder.build_unconditional_branch(search_block)?;
+
+                gen.builder.position_at_end(search_block);
+                let pos = gen
+                    .builder
+                    .build_load(gen.len_type(), pos_var, "pos")?
+                    .into_int_value();
+                let index = gen.build_str_find(data, len, sep_data, sep_len, pos)?;
+                let found = gen.builder.build_int_compare(
+                    IntPredicate::SGE,
+                    index,
+                    gen.len_type().const_zero(),
+                    "found",
+                )?;
+                gen.builder
+                    .build_conditional_branch(found, found_block, done_block)?;
+
+                gen.builder.position_at_end(found_block);
+                let part_len = gen.builder.build_int_sub(index, pos, "part_len")?;
+                let part = gen.build_substr(data, pos, part_len, env)?;
+                gen.build_list_push(list, list_tid, append_fn, part, env)?;
+                let next_pos = gen.builder.build_int_add(index, sep_len, "next_pos")?;
+                gen.builder.build_store(pos_var, next_pos)?;
+                gen.builder.build_unconditional_branch(search_block)?;
+
+                gen.builder.position_at_end(done_block);
+                let rest_len = gen.builder.build_int_sub(len, pos, "rest_len")?;
+                let rest = gen.build_substr(data, pos, rest_len, env)?;
+                gen.build_list_push(list, list_tid, append_fn, rest, env)?;
+
+                Ok(list)
+            },
+            env,
+        )
+    }
+
+    fn setup_str_join(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        let list_tid = self.list_of(STR_ID, env)?;
+        let len_fn = self.find_method(LEN_FN, list_tid, &[list_tid], env)?;
+        let get_item_fn = self.find_method(GET_ITEM_FN, list_tid, &[list_tid, INT_ID], env)?;
+
+        self.create_binary_fn(
+            "join",
+            STR_ID,
+            list_tid,
+            STR_ID,
+            true,
+            |gen, sep, list, env| {
+                let int_type = INT_ID.get_from(env).ink();
+
+                gen.copy_pointer(list, list_tid, env)?;
+                let (len_ptr, ..) = gen.call_func(len_fn, &[list], env)?;
+                let len = gen.extract_primitive(len_ptr, int_type)?.into_int_value();
+                gen.free_pointer(len_ptr, INT_ID, env)?;
+
+                let result_var = gen.builder.build_alloca(gen.ptr_type(), "result_var")?;
+                let empty = gen.build_str_const("", env)?;
+                gen.builder.build_store(result_var, empty)?;
+
+                gen.build_index_loop(
+                    len,
+                    |gen, index, env| {
+                        let cur_fn = gen
+                            .builder
+                            .get_insert_block()
+                            .unwrap()
+                            .get_parent()
+                            .unwrap();
+                        let separator_block = gen.ctx.append_basic_block(cur_fn, "separator");
+                        let item_block = gen.ctx.append_basic_block(cur_fn, "item");
+
+                        let is_first = gen.builder.build_int_compare(
+                            IntPredicate::EQ,
+                            index,
+                            gen.len_type().const_zero(),
+                            "is_first",
+                        )?;
+                        gen.builder.build_conditional_branch(
+                            is_first,
+                            item_block,
+                            separator_block,
+                        )?;
+
+                        gen.builder.position_at_end(separator_block);
+                        gen.copy_pointer(sep, STR_ID, env)?;
+                        gen.build_append_str(result_var, sep, env)?;
+                        gen.builder.build_unconditional_branch(item_block)?;
+
+                        gen.builder.position_at_end(item_block);
+                        gen.copy_pointer(list, list_tid, env)?;
+                        let index_ptr = gen.build_struct(int_type, vec![index.into()])?;
+                        let (item, ..) = gen.call_func(get_item_fn, &[list, index_ptr], env)?;
+                        gen.build_append_str(result_var, item, env)
+                    },
+                    env,
+                )?;
+
+                Ok(gen
+                    .builder
+                    .build_load(gen.ptr_type(), result_var, "result")?
+                    .into_pointer_value())
+            },
+            env,
+        )
+    }
+
+    /// Sets up a method that removes whitespace from the start and/or end of the string.
+    fn setup_str_strip(
+        &mut self,
+        ident: &str,
+        left: bool,
+        right: bool,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        self.create_unary_fn(
+            ident,
+            STR_ID,
+            STR_ID,
+            true,
+            |gen, _fn_val, string, env| {
+                let (data, len) = gen.build_extract_string(string, STR_ID.get_from(env).ink())?;
+
+                let zero = gen.len_type().const_zero();
+                let start = if left {
+                    gen.build_skip_spaces(data, zero, len, true)?
+                } else {
+                    zero
+                };
+                let end = if right {
+                    gen.build_skip_spaces(data, len, start, false)?
+                } else {
+                    len
+                };
+
+                let stripped_len = gen.builder.build_int_sub(end, start, "stripped_len")?;
+                gen.build_substr(data, start, stripped_len, env)
+            },
+            env,
+        )
+    }
+
+    /// Sets up `upper` or `lower`, which shift the letters from `first` to 26 letters later into
+    /// the other case.
+    fn setup_str_change_case(
+        &mut self,
+        ident: &str,
+        first: u8,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        self.create_unary_fn(
+            ident,
+            STR_ID,
+            STR_ID,
+            true,
+            |gen, _fn_val, string, env| {
+                let (data, len) = gen.build_extract_string(string, STR_ID.get_from(env).ink())?;
+                let char_type = gen.char_type();
+
+                let new_data = gen.build_str_data_malloc(len, "new_str_data")?;
+                gen.build_index_loop(
+                    len,
+                    |gen, index, _env| {
+                        let char_val = unsafe { gen.build_extract_char(data, index)? };
+
+                        // Subtracting the first letter makes every other char at least 26 as unsigned
+                        let offset = gen.builder.build_int_sub(
+                            char_val,
+                            char_type.const_int(first as u64, false),
+                            "offset",
+                        )?;
+                        let is_letter = gen.builder.build_int_compare(
+                            IntPredicate::ULT,
+                            offset,
+                            char_type.const_int(26, false),
+                            "is_letter",
+                        )?;
+                        let changed = gen.builder.build_xor(
+                            char_val,
+                            char_type.const_int(0x20, false),
+                            "changed",
+                        )?;
+                        let new_char = gen
+                            .builder
+                            .build_select(is_letter, changed, char_val, "new_char")?;
+
+                        let char_ptr = gen.build_char_ptr(new_data, index)?;
+                        gen.builder.build_store(char_ptr, new_char)?;
+                        Ok(())
+                    },
+                    env,
+                )?;
+
+                gen.build_str_struct(new_data, len, env)
+            },
+            env,
+        )
+    }
+
+    /// Sets up `startswith` or `endswith`.
+    fn setup_str_affix(
+        &mut self,
+        ident: &str,
+        at_end: bool,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        self.create_binary_fn(
+            ident,
+            STR_ID,
+            STR_ID,
+            BOOL_ID,
+            true,
+            |gen, string, affix, env| {
+                let str_type = STR_ID.get_from(env).ink();
+                let (data, len) = gen.build_extract_string(string, str_type)?;
+                let (affix_data, affix_len) = gen.build_extract_string(affix, str_type)?;
+
+                let fits =
+                    gen.builder
+                        .build_int_compare(IntPredicate::ULE, affix_len, len, "fits")?;
+                let start = if at_end {
+                    let end_start = gen.builder.build_int_sub(len, affix_len, "end_start")?;
+                    gen.builder
+                        .build_select(fits, end_start, gen.len_type().const_zero(), "start")?
+                        .into_int_value()
+                } else {
+                    gen.len_type().const_zero()
+                };
+
+                // Nothing is compared when the affix does not fit, so no memory past the end is read
+                let compared_len = gen
+                    .builder
+                    .build_select(fits, affix_len, gen.len_type().const_zero(), "compared_len")?
+                    .into_int_value();
+                let matches = gen.build_str_matches_at(data, start, affix_data, compared_len)?;
+                let result = gen.builder.build_and(fits, matches, "result")?;
+
+                gen.build_struct(BOOL_ID.get_from(env).ink(), vec![result.into()])
+            },
+            env,
+        )
+    }
+
+    /// Sets up `format` for the given argument types, which replaces each `{}` in the string with
+    /// the next argument. Like Python, `{{` and `}}` are literal braces.
+    fn setup_str_format(
+        &mut self,
+        arg_tids: &[TypeId],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        // Strings are used as they are, since str has no `__str__`
+        let to_str_fns = arg_tids
+            .iter()
+            .map(|tid| match *tid {
+                STR_ID => Ok(None),
+                tid => self.find_method(TO_STR_FN, tid, &[tid], env).map(Some),
+            })
+            .collect::<Result<Vec<_>, GenError>>()?;
+
+        let mut param_tids = vec![STR_ID];
+        param_tids.extend_from_slice(arg_tids);
+        let (fn_val, ..) = env.create_func(Some(STR_ID), "format", &param_tids, STR_ID, false)?;
+        let entry = self.ctx.append_basic_block(fn_val, "entry");
+        self.builder.position_at_end(entry);
+
+        let string = fn_val.get_nth_param(0).unwrap().into_pointer_value();
+        let (data, len) = self.build_extract_string(string, STR_ID.get_from(env).ink())?;
+
+        // Convert every argument to a string up front, since the fields are only found at runtime
+        let arg_count = self.len_type().const_int(arg_tids.len() as u64, false);
+        let arg_strs = self
+            .builder
+            .build_array_alloca(self.ptr_type(), arg_count, "arg_strs")?;
+        for (i, to_str_fn) in to_str_fns.iter().enumerate() {
+            let arg = fn_val
+                .get_nth_param(i as u32 + 1)
+                .unwrap()
+                .into_pointer_value();
+            let arg_str = match to_str_fn {
+                Some(to_str_fn) => self.call_func(*to_str_fn, &[arg], env)?.0,
+                None => arg,
+            };
+
+            let index = self.len_type().const_int(i as u64, false);
+            let arg_str_ptr = self.build_ptr_array_elem(arg_strs, index)?;
+            self.builder.build_store(arg_str_ptr, arg_str)?;
+        }
+
+        let result_var = self.builder.build_alloca(self.ptr_type(), "result_var")?;
+        let empty = self.build_str_const("", env)?;
+        self.builder.build_store(result_var, empty)?;
+
+        let len_type = self.len_type();
+        let zero = len_type.const_zero();
+        let one = len_type.const_int(1, false);
+        let two = len_type.const_int(2, false);
+        let index_var = self.builder.build_alloca(len_type, "index_var")?;
+        self.builder.build_store(index_var, zero)?;
+        // The start of the text that has not been copied to the result yet
+        let text_start_var = self.builder.build_alloca(len_type, "text_start_var")?;
+        self.builder.build_store(text_start_var, zero)?;
+        let next_arg_var = self.builder.build_alloca(len_type, "next_arg_var")?;
+        self.builder.build_store(next_arg_var, zero)?;
+
+        let condition_block = self.ctx.append_basic_block(fn_val, "condition");
+        let body_block = self.ctx.append_basic_block(fn_val, "body");
+        let check_escape_block = self.ctx.append_basic_block(fn_val, "check_escape");
+        let field_block = self.ctx.append_basic_block(fn_val, "field");
+        let escape_block = self.ctx.append_basic_block(fn_val, "escape");
+        let next_char_block = self.ctx.append_basic_block(fn_val, "next_char");
+        let done_block = self.ctx.append_basic_block(fn_val, "done");
+        self.builder.build_unconditional_branch(condition_block)?;
+
+        // Stop before the last character, since every special sequence is two characters long
+        self.builder.position_at_end(condition_block);
+        let index = self
+            .builder
+            .build_load(len_type, index_var, "index")?
+            .into_int_value();
+        let next_index = self.builder.build_int_add(index, one, "next_index")?;
+        let has_pair =
+            self.builder
+                .build_int_compare(IntPredicate::SLT, next_index, len, "has_pair")?;
+        self.builder
+            .build_conditional_branch(has_pair, body_block, done_block)?;
+
+        self.builder.position_at_end(body_block);
+        let char_val = unsafe { self.build_extract_char(data, index)? };
+        let next_char = unsafe { self.build_extract_char(data, next_index)? };
+        let is_open = self.build_is_char(char_val, b'{')?;
+        let is_field = self.build_is_char(next_char, b'}')?;
+        let is_field = self.builder.build_and(is_open, is_field, "is_field")?;
+        self.builder
+            .build_conditional_branch(is_field, field_block, check_escape_block)?;
+
+        self.builder.position_at_end(check_escape_block);
+        let is_close = self.build_is_char(char_val, b'}')?;
+        let is_brace = self.builder.build_or(is_open, is_close, "is_brace")?;
+        let is_doubled =
+            self.builder
+                .build_int_compare(IntPredicate::EQ, char_val, next_char, "is_doubled")?;
+        let is_escape = self.builder.build_and(is_brace, is_doubled, "is_escape")?;
+        self.builder
+            .build_conditional_branch(is_escape, escape_block, next_char_block)?;
+
+        // Copy the text before the field, then the next argument
+        self.builder.position_at_end(field_block);
+        self.build_append_text(result_var, data, text_start_var, index, env)?;
+        let next_arg = self
+            .builder
+            .build_load(len_type, next_arg_var, "next_arg")?
+            .into_int_value();
+        let missing_arg = self.builder.build_int_compare(
+            IntPredicate::UGE,
+            next_arg,
+            arg_count,
+            "missing_arg",
+        )?;
+        self.build_runtime_check(missing_arg, "not enough arguments for format string")?;
+        let arg_str_ptr = self.build_ptr_array_elem(arg_strs, next_arg)?;
+        let arg_str = self
+            .builder
+            .build_load(self.ptr_type(), arg_str_ptr, "arg_str")?
+            .into_pointer_value();
+        self.copy_pointer(arg_str, STR_ID, env)?;
+        self.build_append_str(result_var, arg_str, env)?;
+        let next_arg = self.builder.build_int_add(next_arg, one, "next_arg")?;
+        self.builder.build_store(next_arg_var, next_arg)?;
+        let after_field = self.builder.build_int_add(index, two, "after_field")?;
+        self.builder.build_store(text_start_var, after_field)?;
+        self.builder.build_store(index_var, after_field)?;
+        self.builder.build_unconditional_branch(condition_block)?;
+
+        // Copy the text up to and including the first brace, then skip the second one
+        self.builder.position_at_end(escape_block);
+        self.build_append_text(result_var, data, text_start_var, next_index, env)?;
+        let after_escape = self.builder.build_int_add(index, two, "after_escape")?;
+        self.builder.build_store(text_start_var, after_escape)?;
+        self.builder.build_store(index_var, after_escape)?;
+        self.builder.build_unconditional_branch(condition_block)?;
+
+        self.builder.position_at_end(next_char_block);
+        self.builder.build_store(index_var, next_index)?;
+        self.builder.build_unconditional_branch(condition_block)?;
+
+        self.builder.position_at_end(done_block);
+        self.build_append_text(result_var, data, text_start_var, len, env)?;
+
+        for i in 0..arg_tids.len() {
+            let index = self.len_type().const_int(i as u64, false);
+            let arg_str_ptr = self.build_ptr_array_elem(arg_strs, index)?;
+            let arg_str = self
+                .builder
+                .build_load(self.ptr_type(), arg_str_ptr, "arg_str")?
+                .into_pointer_value();
+            self.free_pointer(arg_str, STR_ID, env)?;
+        }
+        self.free_pointer(string, STR_ID, env)?;
+
+        let result = self
+            .builder
+            .build_load(self.ptr_type(), result_var, "result")?
+            .into_pointer_value();
+        self.builder.build_return(Some(&result))?;
+
+        Ok(())
+    }
+
+    /// Appends the text from the position stored in `text_start_var` up to `end` to the string
+    /// stored in `result_var`.
+    fn build_append_text(
+        &mut self,
+        result_var: PointerValue<'ctx>,
+        data: PointerValue<'ctx>,
+        text_start_var: PointerValue<'ctx>,
+        end: IntValue<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let text_start = self
+            .builder
+            .build_load(self.len_type(), text_start_var, "text_start")?
+            .into_int_value();
+        let text_len = self.builder.build_int_sub(end, text_start, "text_len")?;
+        let text = self.build_substr(data, text_start, text_len, env)?;
+        self.build_append_str(result_var, text, env)
+    }
+
+    /// Returns the index of the first occurrence of the needle at or after `from`, or -1 if
+    /// there is none.
+    fn build_str_find(
+        &mut self,
+        data: PointerValue<'ctx>,
+        len: IntValue<'ctx>,
+        needle: PointerValue<'ctx>,
+        needle_len: IntValue<'ctx>,
+        from: IntValue<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        let start_block = self.builder.get_insert_block().unwrap();
+        let cur_fn = start_block.get_parent().unwrap();
+        let condition_block = self.ctx.append_basic_block(cur_fn, "find_condition");
+        let compare_block = self.ctx.append_basic_block(cur_fn, "find_compare");
+        let next_block = self.ctx.append_basic_block(cur_fn, "find_next");
+        let result_block = self.ctx.append_basic_block(cur_fn, "find_result");
+
+        // Every position where the needle fits is checked in order
+        let last_start = self.builder.build_int_sub(len, needle_len, "last_start")?;
+        self.builder.build_unconditional_branch(condition_block)?;
+
+        self.builder.position_at_end(condition_block);
+        let index = self.builder.build_phi(self.len_type(), "index")?;
+        let index_val = index.as_basic_value().into_int_value();
+        let fits =
+            self.builder
+                .build_int_compare(IntPredicate::SLE, index_val, last_start, "fits")?;
+        self.builder
+            .build_conditional_branch(fits, compare_block, result_block)?;
+
+        self.builder.position_at_end(compare_block);
+        let matches = self.build_str_matches_at(data, index_val, needle, needle_len)?;
+        self.builder
+            .build_conditional_branch(matches, result_block, next_block)?;
+
+        self.builder.position_at_end(next_block);
+        let next_index = self.builder.build_int_add(
+            index_val,
+            self.len_type().const_int(1, false),
+            "next_index",
+        )?;
+        self.builder.build_unconditional_branch(condition_block)?;
+
+        index.add_incoming(&[(&from, start_block), (&next_index, next_block)]);
+
+        self.builder.position_at_end(result_block);
+        let result = self.builder.build_phi(self.len_type(), "found_index")?;
+        result.add_incoming(&[
+            (&index_val, compare_block),
+            (
+                &self.len_type().const_int(-1i64 as u64, true),
+                condition_block,
+            ),
+        ]);
+
+        Ok(result.as_basic_value().into_int_value())
+    }
+
+    /// Whether the needle appears at the index, which must leave room for the whole needle.
+    fn build_str_matches_at(
+        &mut self,
+        data: PointerValue<'ctx>,
+        index: IntValue<'ctx>,
+        needle: PointerValue<'ctx>,
+        needle_len: IntValue<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        let res = self.res();
+
+        let start = self.build_char_ptr(data, index)?;
+        let cmp = self
+            .builder
+            .build_call(
+                res.memcmp,
+                &[start.into(), needle.into(), needle_len.into()],
+                "cmp",
+            )?
+            .try_as_basic_value()
+            .unwrap_left()
+            .into_int_value();
+
+        Ok(self.builder.build_int_compare(
+            IntPredicate::EQ,
+            cmp,
+            self.ctx.i32_type().const_zero(),
+            "matches",
+        )?)
+    }
+
+    /// Moves from `from` towards `limit` while the characters are whitespace, returning where it
+    /// stopped. Moving backwards checks the character before each position.
+    fn build_skip_spaces(
+        &mut self,
+        data: PointerValue<'ctx>,
+        from: IntValue<'ctx>,
+        limit: IntValue<'ctx>,
+        forwards: bool,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        let start_block = self.builder.get_insert_block().unwrap();
+        let cur_fn = start_block.get_parent().unwrap();
+        let condition_block = self.ctx.append_basic_block(cur_fn, "skip_condition");
+        let check_block = self.ctx.append_basic_block(cur_fn, "skip_check");
+        let next_block = self.ctx.append_basic_block(cur_fn, "skip_next");
+        let end_block = self.ctx.append_basic_block(cur_fn, "skip_end");
+        self.builder.build_unconditional_branch(condition_block)?;
+
+        self.builder.position_at_end(condition_block);
+        let pos = self.builder.build_phi(self.len_type(), "pos")?;
+        let pos_val = pos.as_basic_value().into_int_value();
+        let predicate = if forwards {
+            IntPredicate::SLT
+        } else {
+            IntPredicate::SGT
+        };
+        let in_range = self
+            .builder
+            .build_int_compare(predicate, pos_val, limit, "in_range")?;
+        self.builder
+            .build_conditional_branch(in_range, check_block, end_block)?;
+
+        self.builder.position_at_end(check_block);
+        let one = self.len_type().const_int(1, false);
+        let char_index = if forwards {
+            pos_val
+        } else {
+            self.builder.build_int_sub(pos_val, one, "char_index")?
+        };
+        let char_val = unsafe { self.build_extract_char(data, char_index)? };
+        let is_space = self.build_is_space(char_val)?;
+        self.builder
+            .build_conditional_branch(is_space, next_block, end_block)?;
+
+        self.builder.position_at_end(next_block);
+        let next_pos = if forwards {
+            self.builder.build_int_add(pos_val, one, "next_pos")?
+        } else {
+            self.builder.build_int_sub(pos_val, one, "next_pos")?
+        };
+        self.builder.build_unconditional_branch(condition_block)?;
+
+        pos.add_incoming(&[(&from, start_block), (&next_pos, next_block)]);
+
+        self.builder.position_at_end(end_block);
+        Ok(pos_val)
+    }
+
+    /// Whether a character is ASCII whitespace, which is a space or `\t` through `\r`.
+    fn build_is_space(&mut self, char_val: IntValue<'ctx>) -> Result<IntValue<'ctx>, GenError> {
+        let char_type = self.char_type();
+
+        let is_blank = self.build_is_char(char_val, b' ')?;
+        let offset = self.builder.build_int_sub(
+            char_val,
+            char_type.const_int(b'\t' as u64, false),
+            "offset",
+        )?;
+        let is_control = self.builder.build_int_compare(
+            IntPredicate::ULE,
+            offset,
+            char_type.const_int((b'\r' - b'\t') as u64, false),
+            "is_control",
+        )?;
+
+        Ok(self.builder.build_or(is_blank, is_control, "is_space")?)
+    }
+
+    fn build_is_char(
+        &mut self,
+        char_val: IntValue<'ctx>,
+        expected: u8,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        Ok(self.builder.build_int_compare(
+            IntPredicate::EQ,
+            char_val,
+            self.char_type().const_int(expected as u64, false),
+            "is_char",
+        )?)
+    }
+
+    /// Builds a new string from `len` characters of the string data starting at `start`.
+    fn build_substr(
+        &mut self,
+        data: PointerValue<'ctx>,
+        start: IntValue<'ctx>,
+        len: IntValue<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        let new_data = self.build_str_data_malloc(len, "substr_data")?;
+        let start_ptr = self.build_char_ptr(data, start)?;
+        self.builder.build_memcpy(new_data, 1, start_ptr, 1, len)?;
+
+        self.build_str_struct(new_data, len, env)
+    }
+
+    fn build_char_ptr(
+        &mut self,
+        data: PointerValue<'ctx>,
+        index: IntValue<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        Ok(unsafe {
+            self.builder
+                .build_gep(self.char_type(), data, &[index], "char_ptr")?
+        })
+    }
+
+    fn build_ptr_array_elem(
+        &mut self,
+        array: PointerValue<'ctx>,
+        index: IntValue<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        Ok(unsafe {
+            self.builder
+                .build_gep(self.ptr_type(), array, &[index], "elem_ptr")?
+        })
+    }
+
+    fn build_int_min(
+        &mut self,
+        left: IntValue<'ctx>,
+        right: IntValue<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        let is_less = self
+            .builder
+            .build_int_compare(IntPredicate::SLT, left, right, "is_less")?;
+        Ok(self
+            .builder
+            .build_select(is_less, left, right, "min")?
+            .into_int_value())
+    }
+
+    /// Appends a value to a list, taking ownership of the value but not the list.
+    fn build_list_push(
+        &mut self,
+        list: PointerValue<'ctx>,
+        list_tid: TypeId,
+        append_fn: FunctionId,
+        value: PointerValue<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        self.copy_pointer(list, list_tid, env)?;
+        let (none, ..) = self.call_func(append_fn, &[list, value], env)?;
+        self.free_pointer(none, NONE_ID, env)
+    }
+}
@@ -7,7 +7,7 @@ use inkwell::{
 use crate::{
     codegen::{
         env::{
-            id::{TypeId, BOOL_ID, FLOAT_ID, INT_ID, STR_ID},
+            id::{BOOL_ID, INT_ID, STR_ID},
             type_def::TypeDef,
             Environment,
         },
@@ -17,7 +17,7 @@ use crate::{
     parser::BinaryFnOp,
 };
 
-use super::{llvm_resources::LLVMResources, TO_BOOL_FN, TO_FLOAT_FN, TO_INT_FN};
+use super::{llvm_resources::LLVMResources, HASH_FN, TO_BOOL_FN};
 
 pub const STR_NAME: &str = "str";
 
@@ -42,19 +42,19 @@ impl<'ctx> CodeGen<'ctx> {
     ) -> Result<(), GenError> {
         let str_struct_type = STR_ID.get_from(env).ink();
 
-        self.build_free_ptr_fn(STR_ID, str_unalloc, env)?;
         self.build_copy_ptr_fn(STR_ID, env)?;
         self.build_get_reference_count_fn(STR_ID, env)?;
 
         // Binary Functions
         self.setup_str_eq_str(str_struct_type, env)?;
-        self.setup_str_add_str(str_struct_type, env)?;
+        self.setup_str_ne_str(env)?;
 
         // Conversion Functions
-        self.setup_str_to_int(str_struct_type, &res, env)?;
-        self.setup_str_to_float(str_struct_type, &res, env)?;
         self.setup_str_to_bool(str_struct_type, &res, env)?;
 
+        // Hashing
+        self.setup_str_hash(str_struct_type, env)?;
+
         Ok(())
     }
 
@@ -70,7 +70,7 @@ impl<'ctx> CodeGen<'ctx> {
             BinaryFnOp::Equal.fn_name(),
             STR_ID,
             STR_ID,
-            STR_ID,
+            BOOL_ID,
             true,
             |gen, left, right, _env| {
                 let (left_str_ptr, left_str_len) =
@@ -154,177 +154,101 @@ impl<'ctx> CodeGen<'ctx> {
         )
     }
 
-    fn setup_str_add_str(
-        &mut self,
-        str_struct_type: StructType<'ctx>,
-        env: &mut Environment<'ctx>,
-    ) -> Result<(), GenError> {
+    fn setup_str_ne_str(&mut self, env: &mut Environment<'ctx>) -> Result<(), GenError> {
+        let bool_struct_type = env.get_type(BOOL_ID).ink();
+
         self.create_binary_fn(
-            BinaryFnOp::Add.fn_name(),
-            STR_ID,
+            BinaryFnOp::NotEqual.fn_name(),
             STR_ID,
             STR_ID,
-            true,
+            BOOL_ID,
+            false, // __eq__ frees both strings
             |gen, left, right, env| {
-                let (left_str_ptr, left_str_len) =
-                    gen.build_extract_string(left, str_struct_type)?;
-                let (right_str_ptr, right_str_len) =
-                    gen.build_extract_string(right, str_struct_type)?;
-
-                let str_data_size =
-                    gen.builder
-                        .build_int_add(left_str_len, right_str_len, "new_str_data_size")?;
-
-                let str_data_ptr = gen.build_str_data_malloc(str_data_size, "new_str_data_ptr")?;
-
-                // Copy left into string data
-                // TODO: figure out if there's a better way to determine alignment
-                gen.builder.build_memcpy(
-                    str_data_ptr,
-                    1, // dest_align_bytes
-                    left_str_ptr,
-                    1, // src_align_bytes
-                    left_str_len,
-                )?;
+                let eq_fn =
+                    env.find_func(BinaryFnOp::Equal.fn_name(), Some(STR_ID), &[STR_ID, STR_ID])?;
+                let (is_eq_ptr, ..) = gen.call_func(eq_fn, &[left, right], env)?;
 
-                // Copy right into string data
-                let str_data_ptr_to_right = unsafe {
-                    gen.builder.build_gep(
-                        gen.char_type(),
-                        str_data_ptr,
-                        &[left_str_len],
-                        "str_data_ptr_to_right",
-                    )?
-                };
-                gen.builder.build_memcpy(
-                    str_data_ptr_to_right,
-                    1, // dest_align_bytes
-                    right_str_ptr,
-                    1, // src_align_bytes
-                    right_str_len,
-                )?;
+                let is_eq = gen
+                    .extract_primitive(is_eq_ptr, bool_struct_type)?
+                    .into_int_value();
+                gen.free_pointer(is_eq_ptr, BOOL_ID, env)?;
 
-                gen.build_str_struct(str_data_ptr, str_data_size, env)
+                let is_ne = gen.builder.build_not(is_eq, "str_ne")?;
+                gen.build_struct(bool_struct_type, vec![is_ne.into()])
             },
             env,
         )
     }
 
-    fn setup_str_to_int(
+    fn setup_str_to_bool(
         &mut self,
-        str_struct: StructType<'ctx>,
-        res: &LLVMResources<'ctx>,
+        _str_struct: StructType<'ctx>,
+        _res: &LLVMResources<'ctx>,
         env: &mut Environment<'ctx>,
     ) -> Result<(), GenError> {
-        let int_struct = INT_ID.get_from(env).ink();
-        let int_type = self.prim_int_type();
-
         self.create_unary_fn(
-            TO_INT_FN,
+            TO_BOOL_FN,
             STR_ID,
-            INT_ID,
+            BOOL_ID,
             true,
-            |gen, _fn_val, val, _env| {
-                let (str_ptr, str_len) = gen.build_extract_string(val, str_struct)?;
-
-                let format_spec = gen
-                    .builder
-                    .build_global_string_ptr("%ld", "str_to_int_format_specifier")?
-                    .as_pointer_value();
-
-                let int_struct_ptr =
-                    gen.build_struct(int_struct, vec![int_type.const_int(0, false).into()])?;
-                let int_data_ptr =
-                    gen.builder
-                        .build_struct_gep(int_struct, int_struct_ptr, 0, "int_data")?;
-
-                // call sscanf
-                // TODO: Throw exception if does not match
-                gen.builder.build_call(
-                    res.sscanf,
-                    &[
-                        str_ptr.into(),
-                        format_spec.into(),
-                        int_data_ptr.into(),
-                        str_len.into(),
-                    ],
-                    "_",
-                )?;
-
-                Ok(int_struct_ptr)
+            |gen, _fn_val, val, env| {
+                let eq_fn =
+                    env.find_func(BinaryFnOp::Equal.fn_name(), Some(STR_ID), &[STR_ID, STR_ID])?;
+                let true_str = gen.build_str_const("True", env)?;
+                let (is_eq, ..) = gen.call_func(eq_fn, &[val, true_str], env)?;
+                Ok(is_eq)
             },
             env,
         )
     }
 
-    fn setup_str_to_float(
+    /// Hashes the bytes of a string with FNV-1a.
+    fn setup_str_hash(
         &mut self,
         str_struct: StructType<'ctx>,
-        res: &LLVMResources<'ctx>,
         env: &mut Environment<'ctx>,
     ) -> Result<(), GenError> {
-        let float_struct = FLOAT_ID.get_from(env).ink();
-        let float_type = self.prim_float_type();
+        const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
 
         self.create_unary_fn(
-            TO_FLOAT_FN,
+            HASH_FN,
             STR_ID,
-            FLOAT_ID,
+            INT_ID,
             true,
-            |gen, _fn_val, val, _env| {
+            |gen, _fn_val, val, env| {
                 let (str_ptr, str_len) = gen.build_extract_string(val, str_struct)?;
 
-                // TODO: Make a shared global constant for format specifiers
-                let format_spec = gen
-                    .builder
-                    .build_global_string_ptr("%lf", "str_to_float_format_specifier")?
-                    .as_pointer_value();
-
-                let float_struct_ptr =
-                    gen.build_struct(float_struct, vec![float_type.const_float(0.0).into()])?;
-                let float_data_ptr = gen.builder.build_struct_gep(
-                    float_struct,
-                    float_struct_ptr,
-                    0,
-                    "float_data",
-                )?;
-
-                // call sscanf
-                // TODO: Throw exception if does not match
-                gen.builder.build_call(
-                    res.sscanf,
-                    &[
-                        str_ptr.into(),
-                        format_spec.into(),
-                        float_data_ptr.into(),
-                        str_len.into(),
-                    ],
-                    "_",
+                let int_type = gen.prim_int_type();
+                let hash_var = gen.builder.build_alloca(int_type, "hash_var")?;
+                gen.builder
+                    .build_store(hash_var, int_type.const_int(FNV_OFFSET_BASIS, false))?;
+
+                gen.build_index_loop(
+                    str_len,
+                    |gen, index, _env| {
+                        let char_val = unsafe { gen.build_extract_char(str_ptr, index)? };
+                        let byte = gen.builder.build_int_z_extend(char_val, int_type, "byte")?;
+
+                        let hash = gen
+                            .builder
+                            .build_load(int_type, hash_var, "hash")?
+                            .into_int_value();
+                        let hash = gen.builder.build_xor(hash, byte, "hash")?;
+                        let hash = gen.builder.build_int_mul(
+                            hash,
+                            int_type.const_int(FNV_PRIME, false),
+                            "hash",
+                        )?;
+                        gen.builder.build_store(hash_var, hash)?;
+
+                        Ok(())
+                    },
+                    env,
                 )?;
 
-                Ok(float_struct_ptr)
-            },
-            env,
-        )
-    }
-
-    fn setup_str_to_bool(
-        &mut self,
-        _str_struct: StructType<'ctx>,
-        _res: &LLVMResources<'ctx>,
-        env: &mut Environment<'ctx>,
-    ) -> Result<(), GenError> {
-        self.create_unary_fn(
-            TO_BOOL_FN,
-            STR_ID,
-            BOOL_ID,
-            true,
-            |gen, _fn_val, val, env| {
-                let eq_fn =
-                    env.find_func(BinaryFnOp::Equal.fn_name(), Some(STR_ID), &[STR_ID, STR_ID])?;
-                let true_str = gen.build_str_const("True", env)?;
-                let (is_eq, ..) = gen.call_func(eq_fn, &[val, true_str], env)?;
-                Ok(is_eq)
+                let hash = gen.builder.build_load(int_type, hash_var, "hash")?;
+                gen.build_struct(INT_ID.get_from(env).ink(), vec![hash])
             },
             env,
         )
@@ -356,7 +280,7 @@ impl<'ctx> CodeGen<'ctx> {
         Ok((str_ptr, str_len))
     }
 
-    unsafe fn build_extract_char(
+    pub(super) unsafe fn build_extract_char(
         &self,
         str_ptr: PointerValue<'ctx>,
         idx: IntValue<'ctx>,
@@ -431,25 +355,3 @@ impl<'ctx> CodeGen<'ctx> {
         self.ctx.ptr_type(AddressSpace::default())
     }
 }
-
-fn str_unalloc(
-    ptr: PointerValue<'_>,
-    tid: TypeId,
-    gen: &mut CodeGen<'_>,
-    env: &mut Environment<'_>,
-) -> Result<(), GenError> {
-    let ink_type = tid.get_from(env).ink();
-    let ptr_ptr_str = gen
-        .builder
-        .build_struct_gep(ink_type, ptr, 0, "ptr_ptr_str_data")?;
-    let ptr_str = gen
-        .builder
-        .build_load(
-            ink_type.get_field_type_at_index(0).unwrap(),
-            ptr_ptr_str,
-            "ptr_str_data",
-        )?
-        .into_pointer_value();
-    gen.builder.build_free(ptr_str)?;
-    Ok(())
-}
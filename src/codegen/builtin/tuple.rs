@@ -0,0 +1,251 @@
+use inkwell::{types::BasicTypeEnum, values::PointerValue};
+
+use crate::{
+    codegen::{
+        env::{
+            id::{TypeId, BOOL_ID, INT_ID, STR_ID},
+            type_def::{Field, TypeDef, TypeInfo},
+            Environment,
+        },
+        err::GenError,
+        CodeGen,
+    },
+    parser::BinaryFnOp,
+};
+
+use super::{HASH_FN, TO_STR_FN};
+
+pub const TUPLE_NAME: &str = "tuple";
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Returns the tuple type with the given element types, creating it if it does not exist yet.
+    ///
+    /// A tuple is stored like a struct whose fields are named after their position.
+    pub(in crate::codegen) fn tuple_of(
+        &mut self,
+        elems: &[TypeId],
+        env: &mut Environment<'ctx>,
+    ) -> Result<TypeId, GenError> {
+        let elem_idents: Vec<&str> = elems.iter().map(|tid| env.type_id_ident(*tid)).collect();
+        let ident = format!("{}[{}]", TUPLE_NAME, elem_idents.join(", "));
+        if let Ok(tid) = env.find_type(&ident) {
+            return Ok(tid);
+        }
+
+        let field_types: Vec<BasicTypeEnum> =
+            elems.iter().map(|_| self.ptr_type().into()).collect();
+        let struct_type = self.create_struct_type(&ident, field_types);
+
+        let tid = env.gen_type_id();
+        let mut type_def = TypeDef::new_generic(&ident, struct_type, TypeInfo::Tuple);
+        type_def.set_fields(
+            elems
+                .iter()
+                .enumerate()
+                .map(|(i, elem)| Field::new(i as u32, i, *elem))
+                .collect(),
+        );
+        env.register_type(&ident, tid, type_def)?;
+
+        let prev_block = self.builder.get_insert_block().unwrap();
+
+        self.build_free_ptr_fn(tid, Self::struct_unalloc, env)?;
+        self.build_copy_ptr_fn(tid, env)?;
+        self.build_get_reference_count_fn(tid, env)?;
+
+        self.builder.position_at_end(prev_block);
+
+        Ok(tid)
+    }
+
+    /// Builds a tuple containing the given elements, taking ownership of them.
+    pub(in crate::codegen) fn build_tuple(
+        &mut self,
+        tid: TypeId,
+        elems: &[PointerValue<'ctx>],
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        let values = elems.iter().map(|elem| (*elem).into()).collect();
+        self.build_struct(tid.get_from(env).ink(), values)
+    }
+
+    /// Returns the element types of a tuple type.
+    pub(in crate::codegen) fn tuple_elems(
+        &self,
+        tid: TypeId,
+        env: &Environment<'ctx>,
+    ) -> Vec<TypeId> {
+        env.get_type(tid)
+            .fields()
+            .iter()
+            .map(|field| field.tid())
+            .collect()
+    }
+
+    /// Loads the element of a tuple at the given position without changing its reference count.
+    pub(in crate::codegen) fn build_load_tuple_elem(
+        &mut self,
+        tuple: PointerValue<'ctx>,
+        tid: TypeId,
+        index: u32,
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        let elem_ptr_ptr =
+            self.builder
+                .build_struct_gep(tid.get_from(env).ink(), tuple, index, "elem_ptr_ptr")?;
+
+        Ok(self
+            .builder
+            .build_load(self.ptr_type(), elem_ptr_ptr, "elem_ptr")?
+            .into_pointer_value())
+    }
+
+    /// Creates the method of a tuple type with the given name, returning false if tuples do not
+    /// have a method with that name.
+    pub(super) fn create_tuple_method(
+        &mut self,
+        ident: &str,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<bool, GenError> {
+        match ident {
+            TO_STR_FN => self.setup_tuple_to_str(tid, env)?,
+            HASH_FN => self.setup_fieldwise_hash(tid, env)?,
+            _ if ident == BinaryFnOp::Equal.fn_name() => self.setup_fieldwise_eq(tid, env)?,
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn setup_tuple_to_str(
+        &mut self,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let elems = self.tuple_elems(tid, env);
+        let mut elem_to_str_fns = Vec::new();
+        for elem in &elems {
+            elem_to_str_fns.push(self.find_method(TO_STR_FN, *elem, &[*elem], env)?);
+        }
+
+        self.create_unary_fn(
+            TO_STR_FN,
+            tid,
+            STR_ID,
+            true,
+            |gen, _fn_val, tuple, env| {
+                let result_var = gen.builder.build_alloca(gen.ptr_type(), "result_var")?;
+                let open = gen.build_str_const("(", env)?;
+                gen.builder.build_store(result_var, open)?;
+
+                for (i, (elem, to_str_fn)) in elems.iter().zip(elem_to_str_fns).enumerate() {
+                    if i > 0 {
+                        let separator = gen.build_str_const(", ", env)?;
+                        gen.build_append_str(result_var, separator, env)?;
+                    }
+
+                    let elem_ptr = gen.build_load_tuple_elem(tuple, tid, i as u32, env)?;
+                    gen.build_append_repr(result_var, elem_ptr, *elem, to_str_fn, env)?;
+                }
+
+                // Like Python, a tuple with one element keeps a trailing comma
+                let close = if elems.len() == 1 { ",)" } else { ")" };
+                let close = gen.build_str_const(close, env)?;
+                gen.build_append_str(result_var, close, env)?;
+
+                let result = gen
+                    .builder
+                    .build_load(gen.ptr_type(), result_var, "result")?
+                    .into_pointer_value();
+                Ok(result)
+            },
+            env,
+        )
+    }
+
+    /// Creates `__eq__` for a tuple or a struct deriving `eq`, which compares the fields in order.
+    ///
+    /// The methods of the fields are looked up once the method is declared, so that a struct can
+    /// contain itself.
+    pub(super) fn setup_fieldwise_eq(
+        &mut self,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let elems = self.tuple_elems(tid, env);
+
+        self.create_binary_fn(
+            BinaryFnOp::Equal.fn_name(),
+            tid,
+            tid,
+            BOOL_ID,
+            true,
+            |gen, left, right, env| {
+                // Values are equal when all of their fields are
+                let mut is_eq = gen.ctx.bool_type().const_int(1, false);
+                for (i, elem) in elems.iter().enumerate() {
+                    let eq_fn = BinaryFnOp::Equal.fn_name();
+                    let eq_fn = gen.find_method(eq_fn, *elem, &[*elem, *elem], env)?;
+
+                    let left_elem = gen.build_load_tuple_elem(left, tid, i as u32, env)?;
+                    let right_elem = gen.build_load_tuple_elem(right, tid, i as u32, env)?;
+                    gen.copy_pointer(left_elem, *elem, env)?;
+                    gen.copy_pointer(right_elem, *elem, env)?;
+
+                    let (elem_eq_ptr, elem_eq_tid) =
+                        gen.call_func(eq_fn, &[left_elem, right_elem], env)?;
+                    let elem_eq = gen.build_truthy(elem_eq_ptr, elem_eq_tid, env)?;
+                    is_eq = gen.builder.build_and(is_eq, elem_eq, "fields_eq")?;
+                }
+
+                gen.build_struct(BOOL_ID.get_from(env).ink(), vec![is_eq.into()])
+            },
+            env,
+        )
+    }
+
+    /// Creates `__hash__` for a tuple or a struct deriving `hash`, which combines the hashes of
+    /// the fields.
+    pub(super) fn setup_fieldwise_hash(
+        &mut self,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let elems = self.tuple_elems(tid, env);
+
+        self.create_unary_fn(
+            HASH_FN,
+            tid,
+            INT_ID,
+            true,
+            |gen, _fn_val, tuple, env| {
+                let int_type = INT_ID.get_from(env).ink();
+
+                // Combines the hashes of the fields, so that their order matters
+                let multiplier = gen.prim_int_type().const_int(31, false);
+                let mut hash = gen.prim_int_type().const_zero();
+                for (i, elem) in elems.iter().enumerate() {
+                    let hash_fn = gen.find_method(HASH_FN, *elem, &[*elem], env)?;
+
+                    let elem_ptr = gen.build_load_tuple_elem(tuple, tid, i as u32, env)?;
+                    gen.copy_pointer(elem_ptr, *elem, env)?;
+
+                    let (elem_hash_ptr, ..) = gen.call_func(hash_fn, &[elem_ptr], env)?;
+                    let elem_hash = gen
+                        .extract_primitive(elem_hash_ptr, int_type)?
+                        .into_int_value();
+                    gen.free_pointer(elem_hash_ptr, INT_ID, env)?;
+
+                    let scaled = gen.builder.build_int_mul(hash, multiplier, "scaled_hash")?;
+                    hash = gen
+                        .builder
+                        .build_int_add(scaled, elem_hash, "fields_hash")?;
+                }
+
+                gen.build_struct(int_type, vec![hash.into()])
+            },
+            env,
+        )
+    }
+}
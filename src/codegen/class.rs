@@ -0,0 +1,376 @@
+use inkwell::{
+    types::BasicTypeEnum,
+    values::{BasicMetadataValueEnum, BasicValueEnum, PointerValue},
+};
+
+use crate::parser::{Expression, FuncDeclaration, Primary, StructConst, StructField};
+
+use super::{
+    env::{
+        class_def::{ClassDef, VirtualMethod},
+        fn_def::ArgSource,
+        id::{FunctionId, TypeId, INT_ID, INVALID_FN_ID, NONE_ID},
+        type_def::{Field, TypeDef},
+        Environment,
+    },
+    err::GenError,
+    structs::INIT_FN,
+    util::{COPY_PTR_IDENT, FREE_PTR_IDENT, RCOUNT_IDENT},
+    CodeGen,
+};
+
+/// Classes keep a pointer to their vtable in front of their fields.
+const VTABLE_FIELD: u32 = 0;
+
+/// The entries of a vtable before the methods. Classes lay their fields out differently, so
+/// freeing, copying and counting references to a value depend on its class just like its methods.
+const FREE_PTR_SLOT: u32 = 0;
+const COPY_PTR_SLOT: u32 = 1;
+const RCOUNT_SLOT: u32 = 2;
+const FIRST_METHOD_SLOT: u32 = 3;
+
+/// Appended to the name of a method for the function holding its body, which the vtable points
+/// to.
+const IMPL_SUFFIX: &str = "$impl";
+
+fn impl_ident(ident: &str) -> String {
+    format!("{}{}", ident, IMPL_SUFFIX)
+}
+
+/// Whether a method is called through the vtable. Constructors are not inherited and static
+/// methods have no value to look the vtable up on, so both are called directly.
+fn is_virtual(fn_dec: &FuncDeclaration) -> bool {
+    fn_dec.takes_self && fn_dec.identifier != INIT_FN
+}
+
+/// Whether an expression is `super()`, which calls the methods of the base of the class.
+pub(super) fn is_super(expr: &Expression) -> bool {
+    match expr {
+        Expression::Invoke(callee, args, kwargs) if args.is_empty() && kwargs.is_empty() => {
+            match callee.as_ref() {
+                Expression::Primary(Primary::Identifier(ident)) => ident == "super",
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Declares a class along with its methods and vtable. A class starts with the fields of its
+    /// base, in the same places, so that a value of the class can be used as a value of the base.
+    pub(super) fn preprocess_class_definition(
+        &mut self,
+        ident: &str,
+        bases: &[String],
+        field_defs: &[StructField],
+        consts: &[StructConst],
+        fns: &[FuncDeclaration],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        // Bases that are not classes are protocols. Both are preprocessed before the class, so
+        // any other name is an error
+        let mut base_tids = Vec::new();
+        for base in bases {
+            if env.find_protocol(base).is_some() {
+                continue;
+            }
+            let base_tid = env.find_type(base)?;
+            if env.class_def(base_tid).is_none() {
+                return Err(GenError::InvalidType);
+            }
+            base_tids.push(base_tid);
+        }
+        if base_tids.len() > 1 {
+            return Err(GenError::InvalidType);
+        }
+        let base_tid = base_tids.first().copied();
+
+        // Register the type before resolving its fields so that fields can refer to it
+        let struct_type = self.ctx.opaque_struct_type(ident);
+        let tid = env.gen_type_id();
+        env.register_type(ident, tid, TypeDef::new(ident, struct_type, Vec::new()))?;
+
+        // The pointer functions go through the vtable, while their implementations only handle
+        // values of exactly this class
+        for ident in [FREE_PTR_IDENT.to_string(), impl_ident(FREE_PTR_IDENT)] {
+            env.create_func(Some(tid), &ident, &[tid], NONE_ID, false)?;
+        }
+        for ident in [COPY_PTR_IDENT.to_string(), impl_ident(COPY_PTR_IDENT)] {
+            env.create_func(Some(tid), &ident, &[tid], tid, false)?;
+        }
+        for ident in [RCOUNT_IDENT.to_string(), impl_ident(RCOUNT_IDENT)] {
+            env.create_func(Some(tid), &ident, &[tid], INT_ID, false)?;
+        }
+
+        let (mut all_field_defs, mut fields) = match base_tid {
+            Some(base) => (
+                env.class_def(base).unwrap().fields().to_vec(),
+                base.get_from(env).fields().to_vec(),
+            ),
+            None => (Vec::new(), Vec::new()),
+        };
+        for field_def in field_defs {
+            if fields
+                .iter()
+                .any(|field| field.ident() == field_def.identifier)
+            {
+                return Err(GenError::IdentConflict);
+            }
+
+            let field_type = self.resolve_type(&field_def.type_identifier, env)?;
+            let index = VTABLE_FIELD + 1 + fields.len() as u32;
+            fields.push(Field::new(index, &field_def.identifier, field_type));
+            all_field_defs.push(field_def.clone());
+        }
+
+        let mut field_types: Vec<BasicTypeEnum> = vec![self.ptr_type().into(); fields.len() + 1];
+        field_types.push(self.ctx.i64_type().into());
+
+        struct_type.set_body(&field_types, false);
+        env.get_type_mut(tid).set_fields(fields);
+        self.preprocess_consts(tid, consts, fns, env)?;
+
+        let mut methods = match base_tid {
+            Some(base) => env.class_def(base).unwrap().methods().to_vec(),
+            None => Vec::new(),
+        };
+        for fn_dec in fns {
+            if !is_virtual(fn_dec) {
+                self.preprocess_fn(Some(tid), fn_dec, env)?;
+                continue;
+            }
+
+            let mut impl_dec = fn_dec.clone();
+            impl_dec.identifier = impl_ident(&fn_dec.identifier);
+            self.preprocess_fn(Some(tid), &impl_dec, env)?;
+
+            let params = fn_dec
+                .params
+                .iter()
+                .map(|param| self.resolve_param_type(param, env))
+                .collect::<Result<Vec<_>, GenError>>()?;
+            let mut impl_params = vec![tid];
+            impl_params.extend(&params);
+            let implementation = env.find_func(&impl_dec.identifier, Some(tid), &impl_params)?;
+            let ret_type = env.get_func(implementation).ret_type;
+
+            // A method overrides the one of the base with the same name and parameter types
+            let overridden = methods
+                .iter_mut()
+                .find(|method| method.ident == fn_dec.identifier && method.params == params);
+            match overridden {
+                Some(method) if method.ret_type != ret_type => {
+                    return Err(GenError::InvalidFunctionDefinition)
+                }
+                Some(method) => method.implementation = implementation,
+                None => methods.push(VirtualMethod {
+                    ident: fn_dec.identifier.clone(),
+                    params,
+                    ret_type,
+                    dispatch: INVALID_FN_ID,
+                    implementation,
+                }),
+            }
+        }
+
+        // Every method, inherited or not, is called through a function of this class
+        for method in &mut methods {
+            let mut params = vec![tid];
+            params.extend(&method.params);
+            let (_, dispatch) =
+                env.create_func(Some(tid), &method.ident, &params, method.ret_type, false)?;
+
+            let implementation = env.get_func(method.implementation);
+            let param_idents = implementation.param_idents.clone();
+            let defaults = implementation.defaults.clone();
+            let var_args = implementation.var_args;
+            env.set_fn_params(dispatch, param_idents, defaults, var_args);
+
+            method.dispatch = dispatch;
+        }
+
+        let vtable_type = self
+            .ptr_type()
+            .array_type(FIRST_METHOD_SLOT + methods.len() as u32);
+        let vtable = env
+            .module
+            .add_global(vtable_type, None, &format!("{}$vtable", ident));
+        vtable.set_constant(true);
+
+        env.register_class(
+            tid,
+            ClassDef::new(base_tid, all_field_defs, vtable, methods),
+        );
+
+        Ok(())
+    }
+
+    pub(super) fn compile_class_definition(
+        &mut self,
+        ident: &str,
+        bases: &[String],
+        fns: &[FuncDeclaration],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let tid = env.find_type(ident)?;
+        let prev_block = self.builder.get_insert_block().unwrap();
+
+        self.check_special_fns(fns, env)?;
+        let class_base = env.class_def(tid).and_then(ClassDef::base);
+        for base in bases {
+            match env.find_type(base) {
+                Ok(base_tid) if class_base == Some(base_tid) => {}
+                Ok(_) => return Err(GenError::InvalidType),
+                Err(_) => self.check_conformance(tid, base, env)?,
+            }
+        }
+
+        // Pointer functions
+        let free_impl = env.find_func(&impl_ident(FREE_PTR_IDENT), Some(tid), &[tid])?;
+        let free_impl = env.get_func(free_impl).ink();
+        self.build_free_ptr_body(free_impl, tid, Self::struct_unalloc, env)?;
+        let copy_impl = env.find_func(&impl_ident(COPY_PTR_IDENT), Some(tid), &[tid])?;
+        let copy_impl = env.get_func(copy_impl).ink();
+        self.build_copy_ptr_body(copy_impl, tid, env)?;
+        let rcount_impl = env.find_func(&impl_ident(RCOUNT_IDENT), Some(tid), &[tid])?;
+        let rcount_impl = env.get_func(rcount_impl).ink();
+        self.build_get_reference_count_body(rcount_impl, tid, env)?;
+
+        for (ident, slot) in [
+            (FREE_PTR_IDENT, FREE_PTR_SLOT),
+            (COPY_PTR_IDENT, COPY_PTR_SLOT),
+            (RCOUNT_IDENT, RCOUNT_SLOT),
+        ] {
+            let dispatch = env.find_func(ident, Some(tid), &[tid])?;
+            self.build_dispatch(dispatch, tid, slot, env)?;
+        }
+
+        let class_def = env.class_def(tid).unwrap();
+        let methods = class_def.methods().to_vec();
+        let field_defs = class_def.fields().to_vec();
+        let vtable = class_def.vtable();
+
+        for (i, method) in methods.iter().enumerate() {
+            self.build_dispatch(method.dispatch, tid, FIRST_METHOD_SLOT + i as u32, env)?;
+        }
+
+        let mut entries = vec![free_impl, copy_impl, rcount_impl];
+        entries.extend(
+            methods
+                .iter()
+                .map(|method| env.get_func(method.implementation).ink()),
+        );
+        let entries: Vec<PointerValue> = entries
+            .iter()
+            .map(|entry| entry.as_global_value().as_pointer_value())
+            .collect();
+        vtable.set_initializer(&self.ptr_type().const_array(&entries));
+
+        // Constructor
+        if fns.iter().any(|fn_dec| fn_dec.identifier == INIT_FN) {
+            self.build_init_constructors(ident, tid, &field_defs, fns, env)?;
+        } else {
+            self.build_struct_constructor(ident, tid, &field_defs, env)?;
+        }
+
+        // Compile all user functions
+        for fn_dec in fns {
+            if !is_virtual(fn_dec) {
+                self.compile_fn(Some(tid), fn_dec, env)?;
+            } else {
+                let mut impl_dec = fn_dec.clone();
+                impl_dec.identifier = impl_ident(&fn_dec.identifier);
+                self.compile_fn(Some(tid), &impl_dec, env)?;
+            }
+        }
+
+        self.builder.position_at_end(prev_block);
+        Ok(())
+    }
+
+    /// Builds a function of a class that calls the entry of the vtable of the value it is called
+    /// on, which belongs to the class the value was created as.
+    fn build_dispatch(
+        &mut self,
+        fn_id: FunctionId,
+        tid: TypeId,
+        slot: u32,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let fn_val = env.get_func(fn_id).ink();
+        let entry = self.ctx.append_basic_block(fn_val, "entry");
+        self.builder.position_at_end(entry);
+
+        let value = fn_val.get_nth_param(0).unwrap().into_pointer_value();
+        let vtable_ptr = self.builder.build_struct_gep(
+            tid.get_from(env).ink(),
+            value,
+            VTABLE_FIELD,
+            "vtable_ptr",
+        )?;
+        let vtable = self
+            .builder
+            .build_load(self.ptr_type(), vtable_ptr, "vtable")?
+            .into_pointer_value();
+
+        let slot = self.ctx.i32_type().const_int(slot as u64, false);
+        let entry_ptr = unsafe {
+            self.builder
+                .build_gep(self.ptr_type(), vtable, &[slot], "entry_ptr")?
+        };
+        let implementation = self
+            .builder
+            .build_load(self.ptr_type(), entry_ptr, "implementation")?
+            .into_pointer_value();
+
+        let args: Vec<BasicMetadataValueEnum> =
+            fn_val.get_param_iter().map(|param| param.into()).collect();
+        let ret = self
+            .builder
+            .build_indirect_call(fn_val.get_type(), implementation, &args, "_")?
+            .try_as_basic_value()
+            .unwrap_left();
+        self.builder.build_return(Some(&ret))?;
+
+        Ok(())
+    }
+
+    /// Puts the vtable in front of the field values of a new value, if its type is a class.
+    pub(super) fn insert_vtable(
+        &self,
+        tid: TypeId,
+        values: &mut Vec<BasicValueEnum<'ctx>>,
+        env: &Environment<'ctx>,
+    ) {
+        if let Some(class_def) = env.class_def(tid) {
+            let vtable = class_def.vtable().as_pointer_value();
+            values.insert(VTABLE_FIELD as usize, vtable.into());
+        }
+    }
+
+    /// Resolves a method called on `super()`, which is the implementation of the base of the
+    /// class whose method is being compiled rather than an entry of the vtable. `self` is put in
+    /// front of the arguments.
+    pub(super) fn resolve_super_call(
+        &mut self,
+        ident: &str,
+        args: &mut Vec<PointerValue<'ctx>>,
+        arg_tids: &mut Vec<TypeId>,
+        kwarg_tids: &[(String, TypeId)],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(FunctionId, Vec<ArgSource>), GenError> {
+        let self_expr = Expression::Primary(Primary::Identifier("self".to_string()));
+        let (self_ptr, self_tid) = self.compile_expression(&self_expr, env)?;
+        let Some(base) = env.class_def(self_tid).and_then(ClassDef::base) else {
+            return Err(GenError::InvalidType);
+        };
+
+        args.insert(0, self_ptr);
+        arg_tids.insert(0, base);
+        let (fn_id, sources) = env.resolve_func_call(ident, Some(base), arg_tids, kwarg_tids)?;
+
+        let implementation = env.class_def(base).unwrap().implementation_of(fn_id);
+        Ok((implementation, sources))
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::BTreeSet;
+
+use inkwell::{
+    types::BasicTypeEnum,
+    values::{FunctionValue, PointerValue},
+    AddressSpace,
+};
+
+use crate::parser::{Expression, FuncDeclaration, LValue, Primary, Statement, StatementKind};
+
+use super::{
+    env::{
+        id::TypeId,
+        type_def::{Field, TypeDef},
+        Environment,
+    },
+    err::GenError,
+    util::FREE_PTR_IDENT,
+    CodeGen,
+};
+
+const LAMBDA_NAME: &str = "lambda";
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Compiles a lambda into a value of the expected function type, which gives the types of
+    /// its parameters and body.
+    pub(super) fn compile_lambda(
+        &mut self,
+        params: &[String],
+        body: &Expression,
+        hint: Option<TypeId>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let Some((param_tids, ret_tid)) = hint.and_then(|tid| env.fn_signature(tid)).cloned()
+        else {
+            return Err(GenError::CannotInferType);
+        };
+        if param_tids.len() != params.len() {
+            return Err(GenError::InvalidType);
+        }
+
+        let params: Vec<_> = params.iter().cloned().zip(param_tids).collect();
+        let mut idents = BTreeSet::new();
+        collect_idents(body, &mut idents);
+
+        self.build_closure(
+            LAMBDA_NAME,
+            &params,
+            ret_tid,
+            &idents,
+            false,
+            env,
+            |gen, env| gen.compile_return(body, env),
+        )
+    }
+
+    /// Compiles a `def` inside a function into a closure, which is stored in a variable with
+    /// the name of the function. The name is also bound inside the body, so the function can
+    /// call itself.
+    pub(super) fn compile_nested_fn(
+        &mut self,
+        fn_dec: &FuncDeclaration,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        // Closures are called through function values, which take no keyword arguments
+        let is_plain = fn_dec.type_params.is_empty()
+            && fn_dec
+                .params
+                .iter()
+                .all(|param| !param.var_args && param.default.is_none());
+        if !is_plain || fn_dec.takes_self {
+            return Err(GenError::InvalidFunctionDefinition);
+        }
+
+        let params = fn_dec
+            .params
+            .iter()
+            .map(|param| {
+                let tid = self.resolve_type(&param.type_identifier, env)?;
+                Ok((param.identifier.clone(), tid))
+            })
+            .collect::<Result<Vec<_>, GenError>>()?;
+        let ret_tid = self.resolve_type(&fn_dec.return_identifier, env)?;
+
+        let mut idents = BTreeSet::new();
+        collect_block_idents(&fn_dec.body, &mut idents);
+
+        let (closure, closure_tid) = self.build_closure(
+            &fn_dec.identifier,
+            &params,
+            ret_tid,
+            &idents,
+            true,
+            env,
+            |gen, env| gen.compile_block(&fn_dec.body, env),
+        )?;
+        self.declare_var(&fn_dec.identifier, closure, closure_tid, env)
+    }
+
+    /// Builds a function value whose function is compiled by `compile_body`, capturing the
+    /// variables named by `idents` that are visible where the closure is created.
+    ///
+    /// Captured variables are copied into a context, which is reference counted like any other
+    /// value and owned by the function value, so they keep the values they had when the closure
+    /// was created. A `recursive` closure refers to itself by `ident` inside its body.
+    #[allow(clippy::too_many_arguments)]
+    fn build_closure(
+        &mut self,
+        ident: &str,
+        params: &[(String, TypeId)],
+        ret_tid: TypeId,
+        idents: &BTreeSet<String>,
+        recursive: bool,
+        env: &mut Environment<'ctx>,
+        compile_body: impl FnOnce(&mut Self, &mut Environment<'ctx>) -> Result<(), GenError>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let captures: Vec<_> = idents
+            .iter()
+            .filter(|captured| !(recursive && *captured == ident))
+            .filter(|ident| !params.iter().any(|(param, _)| param == *ident))
+            .filter_map(|ident| Some((ident.as_str(), env.get_var(ident).ok()?)))
+            .collect();
+
+        let context_ident = env.gen_closure_ident(ident);
+        let context_tid = self.create_closure_context(&context_ident, &captures, env)?;
+
+        let param_tids: Vec<_> = params.iter().map(|(_, tid)| *tid).collect();
+        let fn_tid = self.fn_type_of(&param_tids, ret_tid, env)?;
+
+        let mut fn_params = vec![context_tid];
+        fn_params.extend(&param_tids);
+        let (fn_val, fn_id) = env.create_func(None, &context_ident, &fn_params, ret_tid, false)?;
+
+        let prev_block = self.builder.get_insert_block().unwrap();
+        let entry_block = self.ctx.append_basic_block(fn_val, "entry");
+        self.builder.position_at_end(entry_block);
+
+        let containing_fn_env = env.new_fn_env(fn_id, false);
+        let line = self.line;
+        env.push_scope();
+
+        let context_type = context_tid.get_from(env).ink();
+        let context = fn_val.get_nth_param(0).unwrap().into_pointer_value();
+        for (i, (ident, (_, tid))) in captures.iter().enumerate() {
+            let capture_ptr =
+                self.builder
+                    .build_struct_gep(context_type, context, i as u32, "capture_ptr")?;
+            env.insert_capture(ident.to_string(), capture_ptr, *tid);
+        }
+
+        // The function refers to itself through a new function value sharing its context, which
+        // parameters with the same name shadow
+        if recursive {
+            self.copy_pointer(context, context_tid, env)?;
+            let self_value = self.build_fn_value(fn_val, context, context_tid, fn_tid, env)?;
+            self.declare_var(ident, self_value, fn_tid, env)?;
+        }
+
+        let ptr_type = self.ctx.ptr_type(AddressSpace::default());
+        for (i, (ident, tid)) in params.iter().enumerate() {
+            let param_var = self
+                .builder
+                .build_alloca(ptr_type, &(ident.to_owned() + "_var"))?;
+            let param = fn_val.get_nth_param(i as u32 + 1).unwrap();
+            self.builder.build_store(param_var, param)?;
+            env.insert_var(ident.clone(), param_var, *tid);
+        }
+
+        compile_body(self, env)?;
+
+        env.pop_scope();
+        env.set_fn_env(containing_fn_env);
+        self.line = line;
+        self.builder.position_at_end(prev_block);
+
+        let mut captured_values = Vec::with_capacity(captures.len());
+        for (ident, (var_ptr, tid)) in &captures {
+            let value = self
+                .builder
+                .build_load(ptr_type, *var_ptr, &(ident.to_string() + "_val"))?
+                .into_pointer_value();
+            captured_values.push(self.copy_pointer(value, *tid, env)?.into());
+        }
+        let context = self.build_struct(context_type, captured_values)?;
+        let closure = self.build_fn_value(fn_val, context, context_tid, fn_tid, env)?;
+
+        Ok((closure, fn_tid))
+    }
+
+    /// Builds a function value calling `fn_val` with `context`, taking ownership of the context.
+    fn build_fn_value(
+        &mut self,
+        fn_val: FunctionValue<'ctx>,
+        context: PointerValue<'ctx>,
+        context_tid: TypeId,
+        fn_tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        let free_context = env.find_func(FREE_PTR_IDENT, Some(context_tid), &[context_tid])?;
+        let free_context = free_context.get_from(env).ink().as_global_value();
+        self.build_struct(
+            fn_tid.get_from(env).ink(),
+            vec![
+                fn_val.as_global_value().as_pointer_value().into(),
+                context.into(),
+                free_context.as_pointer_value().into(),
+            ],
+        )
+    }
+
+    /// Creates the type of the context of a closure, which has a field for every captured
+    /// variable.
+    fn create_closure_context(
+        &mut self,
+        ident: &str,
+        captures: &[(&str, (PointerValue<'ctx>, TypeId))],
+        env: &mut Environment<'ctx>,
+    ) -> Result<TypeId, GenError> {
+        let field_types: Vec<BasicTypeEnum> =
+            captures.iter().map(|_| self.ptr_type().into()).collect();
+        let struct_type = self.create_struct_type(ident, field_types);
+
+        let fields = captures
+            .iter()
+            .enumerate()
+            .map(|(i, (ident, (_, tid)))| Field::new(i as u32, ident, *tid))
+            .collect();
+        let tid = env.gen_type_id();
+        env.register_type(ident, tid, TypeDef::new(ident, struct_type, fields))?;
+
+        let prev_block = self.builder.get_insert_block().unwrap();
+
+        self.build_free_ptr_fn(tid, Self::struct_unalloc, env)?;
+        self.build_copy_ptr_fn(tid, env)?;
+        self.build_get_reference_count_fn(tid, env)?;
+
+        self.builder.position_at_end(prev_block);
+
+        Ok(tid)
+    }
+}
+
+/// Collects every identifier an expression refers to, including those inside nested lambdas.
+/// Only the ones that name variables where a closure is created are captured, so collecting
+/// too many is harmless.
+fn collect_idents(expr: &Expression, idents: &mut BTreeSet<String>) {
+    match expr {
+        Expression::Binary(left, _, right) | Expression::BinaryFn(left, _, right) => {
+            collect_idents(left, idents);
+            collect_idents(right, idents);
+        }
+        Expression::Compare(first, chain) => {
+            collect_idents(first, idents);
+            for (_, expr) in chain {
+                collect_idents(expr, idents);
+            }
+        }
+        Expression::Conditional(condition, then_expr, else_expr) => {
+            collect_idents(condition, idents);
+            collect_idents(then_expr, idents);
+            collect_idents(else_expr, idents);
+        }
+        Expression::Unary(_, expr) | Expression::UnaryFn(_, expr) => collect_idents(expr, idents),
+        Expression::Invoke(callee, args, kwargs) => {
+            collect_idents(callee, idents);
+            for arg in args.iter().chain(kwargs.iter().map(|(_, arg)| arg)) {
+                collect_idents(arg, idents);
+            }
+        }
+        Expression::Access(expr, _) => collect_idents(expr, idents),
+        Expression::Index(expr, index) => {
+            collect_idents(expr, idents);
+            collect_idents(index, idents);
+        }
+        Expression::Slice(start, stop, step) => {
+            for part in [start, stop, step].into_iter().flatten() {
+                collect_idents(part, idents);
+            }
+        }
+        Expression::List(items) | Expression::Set(items) | Expression::Tuple(items) => {
+            for item in items {
+                collect_idents(item, idents);
+            }
+        }
+        Expression::Dict(entries) => {
+            for (key, value) in entries {
+                collect_idents(key, idents);
+                collect_idents(value, idents);
+            }
+        }
+        Expression::Lambda(_, body) => collect_idents(body, idents),
+        Expression::Primary(Primary::Identifier(ident)) => {
+            idents.insert(ident.clone());
+        }
+        Expression::Primary(Primary::Grouping(expr)) => collect_idents(expr, idents),
+        Expression::Primary(_) => {}
+    }
+}
+
+fn collect_block_idents(statements: &[Statement], idents: &mut BTreeSet<String>) {
+    for statement in statements {
+        match &statement.kind {
+            StatementKind::Declaration { expression, .. }
+            | StatementKind::Destructure { expression, .. }
+            | StatementKind::Expression(expression)
+            | StatementKind::ReturnStatement { expression } => collect_idents(expression, idents),
+            StatementKind::Assignment {
+                lvalue, expression, ..
+            } => {
+                match lvalue {
+                    LValue::Ident(ident) => {
+                        idents.insert(ident.clone());
+                    }
+                    LValue::Access(expr, _) => collect_idents(expr, idents),
+                    LValue::Index(expr, index) => {
+                        collect_idents(expr, idents);
+                        collect_idents(index, idents);
+                    }
+                }
+                collect_idents(expression, idents);
+            }
+            StatementKind::Delete { expression, index } => {
+                collect_idents(expression, idents);
+                collect_idents(index, idents);
+            }
+            StatementKind::FunctionDeclaration(fn_dec) => {
+                collect_block_idents(&fn_dec.body, idents)
+            }
+            StatementKind::IfStatement {
+                condition,
+                then_branch,
+                else_if_branches,
+                else_branch,
+            } => {
+                collect_idents(condition, idents);
+                collect_block_idents(then_branch, idents);
+                for (condition, branch) in else_if_branches {
+                    collect_idents(condition, idents);
+                    collect_block_idents(branch, idents);
+                }
+                if let Some(branch) = else_branch {
+                    collect_block_idents(branch, idents);
+                }
+            }
+            StatementKind::WhileLoop { condition, block } => {
+                collect_idents(condition, idents);
+                collect_block_idents(block, idents);
+            }
+            StatementKind::ForLoop {
+                iterable, block, ..
+            } => {
+                collect_idents(iterable, idents);
+                collect_block_idents(block, idents);
+            }
+            StatementKind::Match { subject, cases } => {
+                collect_idents(subject, idents);
+                for case in cases {
+                    if let Some(guard) = &case.guard {
+                        collect_idents(guard, idents);
+                    }
+                    collect_block_idents(&case.body, idents);
+                }
+            }
+            StatementKind::ExternFunctionDeclaration { .. }
+            | StatementKind::StructDefinition { .. }
+            | StatementKind::ClassDefinition { .. }
+            | StatementKind::ProtocolDefinition { .. }
+            | StatementKind::EnumDefinition { .. } => {}
+        }
+    }
+}
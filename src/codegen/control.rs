@@ -1,15 +1,30 @@
-use crate::{
-    codegen::env::id::BOOL_ID,
-    parser::{Expression, Statement},
+use inkwell::IntPredicate;
+
+use crate::parser::{BinaryOp, Expression, Primary, Statement};
+
+use super::{
+    builtin::{GET_ITEM_FN, ITER_FN, LEN_FN},
+    env::{id::INT_ID, Environment},
+    err::GenError,
+    CodeGen,
 };
 
-use super::{env::Environment, err::GenError, CodeGen};
+/// The hidden variable that holds the value being iterated over by a for loop.
+const FOR_ITERABLE_VAR: &str = "$iterable";
+
+/// A variable whose type is narrowed by an if statement's condition.
+struct Narrowing {
+    ident: String,
+    /// Whether the variable is narrowed in the then branch (`x is not None`), rather than in every
+    /// branch after it (`x is None`).
+    in_then_branch: bool,
+}
 
 impl<'ctx> CodeGen<'ctx> {
     pub(super) fn compile_if_statement(
         &mut self,
         condition: &Expression,
-        then_branch: &Vec<Statement>,
+        then_branch: &[Statement],
         else_if_branches: &Vec<(Expression, Vec<Statement>)>,
         else_branch: &Option<Vec<Statement>>,
         env: &mut Environment<'ctx>,
@@ -17,12 +32,7 @@ impl<'ctx> CodeGen<'ctx> {
         // TODO: Decrement Condition Expressions
 
         let (cond_ptr, cond_tid) = self.compile_expression(condition, env)?;
-        let cond_type = env.get_type(cond_tid);
-
-        assert_eq!(cond_tid, BOOL_ID); // TODO: GenError
-
-        let mut cond_val = self.extract_primitive(cond_ptr, cond_type.ink())?;
-        self.free_pointer(cond_ptr, cond_tid, env)?;
+        let mut cond_val = self.build_truthy(cond_ptr, cond_tid, env)?;
 
         let mut source_block = self.builder.get_insert_block().unwrap();
         let func = source_block.get_parent().unwrap();
@@ -30,11 +40,29 @@ impl<'ctx> CodeGen<'ctx> {
         let mut then_block = self.ctx.append_basic_block(func, "then");
         let merge_block = self.ctx.append_basic_block(func, "merge");
 
+        let narrowing = Self::find_narrowing(condition);
+
         // Primary/Then branch
         self.builder.position_at_end(then_block);
-        self.compile_block(&then_branch, env)?;
+        match &narrowing {
+            Some(narrowing) if narrowing.in_then_branch => {
+                self.push_narrowed_scope(narrowing, env)?;
+                self.compile_block(then_branch, env)?;
+                env.pop_scope();
+            }
+            _ => self.compile_block(then_branch, env)?,
+        }
         self.builder.build_unconditional_branch(merge_block)?;
 
+        // Later branches are only reached if the variable was not None
+        let narrowed_later = match &narrowing {
+            Some(narrowing) if !narrowing.in_then_branch => {
+                self.push_narrowed_scope(narrowing, env)?;
+                true
+            }
+            _ => false,
+        };
+
         // Else If branches
         for (condition, branch) in else_if_branches {
             // Create new branches
@@ -47,20 +75,13 @@ impl<'ctx> CodeGen<'ctx> {
 
             // Create branch from previous block to this new block
             self.builder.position_at_end(source_block);
-            self.builder.build_conditional_branch(
-                cond_val.into_int_value(),
-                then_block,
-                next_source_block,
-            )?;
+            self.builder
+                .build_conditional_branch(cond_val, then_block, next_source_block)?;
 
             // Compile condition
             self.builder.position_at_end(next_source_block);
             let (cond_ptr, cond_tid) = self.compile_expression(condition, env)?;
-            let cond_type = env.get_type(cond_tid);
-            // TODO: Check boolean?
-
-            cond_val = self.extract_primitive(cond_ptr, cond_type.ink())?;
-            self.free_pointer(cond_ptr, cond_tid, env)?;
+            cond_val = self.build_truthy(cond_ptr, cond_tid, env)?;
 
             source_block = next_source_block;
             then_block = next_then_block;
@@ -78,11 +99,8 @@ impl<'ctx> CodeGen<'ctx> {
 
             // Create branch from previous block to this new block
             self.builder.position_at_end(source_block);
-            self.builder.build_conditional_branch(
-                cond_val.into_int_value(),
-                then_block,
-                else_block,
-            )?;
+            self.builder
+                .build_conditional_branch(cond_val, then_block, else_block)?;
 
             self.builder.position_at_end(else_block);
             self.compile_block(&else_branch, env)?;
@@ -90,11 +108,12 @@ impl<'ctx> CodeGen<'ctx> {
         } else {
             // Create branch from previous block to outside the if statement
             self.builder.position_at_end(source_block);
-            self.builder.build_conditional_branch(
-                cond_val.into_int_value(),
-                then_block,
-                merge_block,
-            )?;
+            self.builder
+                .build_conditional_branch(cond_val, then_block, merge_block)?;
+        }
+
+        if narrowed_later {
+            env.pop_scope();
         }
 
         self.builder.position_at_end(merge_block);
@@ -102,6 +121,47 @@ impl<'ctx> CodeGen<'ctx> {
         Ok(())
     }
 
+    /// Finds a variable whose type can be narrowed by a condition of the form `x is None` or
+    /// `x is not None`.
+    fn find_narrowing(condition: &Expression) -> Option<Narrowing> {
+        let Expression::Binary(left, op, right) = condition else {
+            return None;
+        };
+
+        let (Expression::Primary(Primary::Identifier(ident)), Expression::Primary(Primary::None)) =
+            (left.as_ref(), right.as_ref())
+        else {
+            return None;
+        };
+
+        let in_then_branch = match op {
+            BinaryOp::Is => false,
+            BinaryOp::IsNot => true,
+            BinaryOp::And | BinaryOp::Or => return None,
+        };
+
+        Some(Narrowing {
+            ident: ident.clone(),
+            in_then_branch,
+        })
+    }
+
+    /// Pushes a scope where the narrowed variable has the inner type of its optional type.
+    fn push_narrowed_scope(
+        &mut self,
+        narrowing: &Narrowing,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        env.push_scope();
+
+        let (var_ptr, var_tid) = env.get_var(&narrowing.ident)?;
+        if let Some(inner) = env.get_type(var_tid).optional_inner() {
+            env.narrow_var(narrowing.ident.clone(), var_ptr, inner);
+        }
+
+        Ok(())
+    }
+
     pub(super) fn compile_while_loop(
         &mut self,
         condition: &Expression,
@@ -119,13 +179,7 @@ impl<'ctx> CodeGen<'ctx> {
         self.builder.position_at_end(condition_block);
 
         let (expr_ptr, tid) = self.compile_expression(condition, env)?;
-        assert_eq!(tid, BOOL_ID); // TODO Error
-
-        let expr_type = env.get_type(tid);
-        let bool_val = self
-            .extract_primitive(expr_ptr, expr_type.ink())?
-            .into_int_value();
-        self.free_pointer(expr_ptr, tid, env)?;
+        let bool_val = self.build_truthy(expr_ptr, tid, env)?;
 
         self.builder
             .build_conditional_branch(bool_val, body_block, merge_block)?;
@@ -138,4 +192,87 @@ impl<'ctx> CodeGen<'ctx> {
 
         Ok(())
     }
+
+    /// Compiles a for loop over any value with `__len__` and `__getitem__` methods, or over the keys
+    /// of a dict.
+    ///
+    /// The length is checked before every iteration, so the loop stops early if the iterable
+    /// shrinks while it is being iterated over.
+    pub(super) fn compile_for_loop(
+        &mut self,
+        identifier: &str,
+        iterable: &Expression,
+        block: &[Statement],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let (mut iter_ptr, mut iter_tid) = self.compile_expression(iterable, env)?;
+
+        // Types that cannot be indexed, like dicts and sets, are iterated over as a list
+        if let Ok(iter_fn) = self.find_method(ITER_FN, iter_tid, &[iter_tid], env) {
+            (iter_ptr, iter_tid) = self.call_func(iter_fn, &[iter_ptr], env)?;
+        }
+        let len_fn = self.find_method(LEN_FN, iter_tid, &[iter_tid], env)?;
+        let get_item_fn = self.find_method(GET_ITEM_FN, iter_tid, &[iter_tid, INT_ID], env)?;
+        let int_type = INT_ID.get_from(env).ink();
+
+        // Keep the iterable in a variable of its own so it is freed with the loop's scope
+        env.push_scope();
+        self.declare_var(FOR_ITERABLE_VAR, iter_ptr, iter_tid, env)?;
+
+        let index_ptr = self.builder.build_alloca(self.prim_int_type(), "index")?;
+        self.builder
+            .build_store(index_ptr, self.prim_int_type().const_zero())?;
+
+        let condition_block = self
+            .ctx
+            .insert_basic_block_after(self.builder.get_insert_block().unwrap(), "condition");
+        let body_block = self.ctx.insert_basic_block_after(condition_block, "body");
+        let merge_block = self.ctx.insert_basic_block_after(body_block, "continue");
+
+        self.builder.build_unconditional_branch(condition_block)?;
+        self.builder.position_at_end(condition_block);
+
+        let index = self
+            .builder
+            .build_load(self.prim_int_type(), index_ptr, "index")?
+            .into_int_value();
+
+        self.copy_pointer(iter_ptr, iter_tid, env)?;
+        let (len_ptr, _) = self.call_func(len_fn, &[iter_ptr], env)?;
+        let len = self.extract_primitive(len_ptr, int_type)?.into_int_value();
+        self.free_pointer(len_ptr, INT_ID, env)?;
+
+        let in_bounds =
+            self.builder
+                .build_int_compare(IntPredicate::SLT, index, len, "in_bounds")?;
+        self.builder
+            .build_conditional_branch(in_bounds, body_block, merge_block)?;
+
+        self.builder.position_at_end(body_block);
+
+        self.copy_pointer(iter_ptr, iter_tid, env)?;
+        let index_struct = self.build_struct(int_type, vec![index.into()])?;
+        let (item_ptr, item_tid) = self.call_func(get_item_fn, &[iter_ptr, index_struct], env)?;
+
+        env.push_scope();
+        self.declare_var(identifier, item_ptr, item_tid, env)?;
+        self.compile_block(block, env)?;
+        let item_scope = env.pop_scope().unwrap();
+        self.free_vars_in_scope(&item_scope, env)?;
+
+        let next_index = self.builder.build_int_add(
+            index,
+            self.prim_int_type().const_int(1, false),
+            "next_index",
+        )?;
+        self.builder.build_store(index_ptr, next_index)?;
+        self.builder.build_unconditional_branch(condition_block)?;
+
+        self.builder.position_at_end(merge_block);
+
+        let iter_scope = env.pop_scope().unwrap();
+        self.free_vars_in_scope(&iter_scope, env)?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,223 @@
+use inkwell::{
+    types::{BasicType, BasicTypeEnum},
+    values::{BasicValue, BasicValueEnum, IntValue, PointerValue},
+};
+
+use crate::parser::EnumVariant;
+
+use super::{
+    env::{
+        enum_def::{EnumDef, Variant},
+        id::{TypeId, INT_ID, NONE_ID},
+        type_def::{Field, TypeDef},
+        Environment,
+    },
+    err::GenError,
+    util::{COPY_PTR_IDENT, FREE_PTR_IDENT, RCOUNT_IDENT},
+    CodeGen,
+};
+
+/// Enums keep the tag of their variant in front of the fields.
+const TAG_FIELD: u32 = 0;
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Declares an enum, whose values hold the tag of their variant followed by a field for every
+    /// field of every variant.
+    pub(super) fn preprocess_enum_definition(
+        &mut self,
+        ident: &str,
+        variant_defs: &[EnumVariant],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        // Register the type before resolving its fields so that fields can refer to it
+        let struct_type = self.ctx.opaque_struct_type(ident);
+        let tid = env.gen_type_id();
+        env.register_type(ident, tid, TypeDef::new(ident, struct_type, Vec::new()))?;
+
+        // Declare the pointer functions, which are needed by types containing this one
+        env.create_func(Some(tid), FREE_PTR_IDENT, &[tid], NONE_ID, false)?;
+        env.create_func(Some(tid), COPY_PTR_IDENT, &[tid], tid, false)?;
+        env.create_func(Some(tid), RCOUNT_IDENT, &[tid], INT_ID, false)?;
+
+        let mut variants: Vec<Variant> = Vec::new();
+        let mut field_count = 0;
+        for (tag, variant_def) in variant_defs.iter().enumerate() {
+            if variants
+                .iter()
+                .any(|variant| variant.ident == variant_def.identifier)
+            {
+                return Err(GenError::IdentConflict);
+            }
+
+            let mut fields: Vec<Field> = Vec::new();
+            for field_def in &variant_def.fields {
+                if fields
+                    .iter()
+                    .any(|field| field.ident() == field_def.identifier)
+                {
+                    return Err(GenError::IdentConflict);
+                }
+
+                let field_type = self.resolve_type(&field_def.type_identifier, env)?;
+                let index = TAG_FIELD + 1 + field_count;
+                fields.push(Field::new(index, &field_def.identifier, field_type));
+                field_count += 1;
+            }
+
+            variants.push(Variant {
+                ident: variant_def.identifier.clone(),
+                tag: tag as u64,
+                fields,
+            });
+        }
+
+        let mut field_types: Vec<BasicTypeEnum> = vec![self.ctx.i64_type().into()];
+        field_types.extend((0..field_count).map(|_| self.ptr_type().as_basic_type_enum()));
+        field_types.push(self.ctx.i64_type().into());
+
+        struct_type.set_body(&field_types, false);
+        env.register_enum(tid, EnumDef::new(variants));
+
+        Ok(())
+    }
+
+    pub(super) fn compile_enum_definition(
+        &mut self,
+        ident: &str,
+        variant_defs: &[EnumVariant],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let tid = env.find_type(ident)?;
+        let prev_block = self.builder.get_insert_block().unwrap();
+
+        // Pointer Functions
+        self.build_free_ptr_fn(tid, Self::enum_unalloc, env)?;
+        self.build_copy_ptr_fn(tid, env)?;
+        self.build_get_reference_count_fn(tid, env)?;
+
+        for variant_def in variant_defs {
+            self.build_variant_constructor(tid, variant_def, env)?;
+        }
+
+        self.builder.position_at_end(prev_block);
+        Ok(())
+    }
+
+    /// Frees the fields of the variant an enum holds, which are the only ones that are set.
+    fn enum_unalloc(
+        ptr: PointerValue<'ctx>,
+        tid: TypeId,
+        gen: &mut CodeGen<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let fields: Vec<Field> = env
+            .enum_def(tid)
+            .unwrap()
+            .variants()
+            .iter()
+            .flat_map(|variant| variant.fields.clone())
+            .collect();
+
+        for field in fields {
+            let field_ptr = gen.build_load_variant_field(ptr, tid, &field, env)?;
+            gen.free_pointer_if_set(field_ptr, field.tid(), env)?;
+        }
+
+        Ok(())
+    }
+
+    /// Builds the function that creates a value of a variant, which is called on the enum as in
+    /// `Shape.Circle(radius=1.0)`.
+    fn build_variant_constructor(
+        &mut self,
+        tid: TypeId,
+        variant_def: &EnumVariant,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let variant = env
+            .enum_def(tid)
+            .unwrap()
+            .find_variant(&variant_def.identifier)
+            .unwrap()
+            .clone();
+        let struct_type = tid.get_from(env).ink();
+
+        let params: Vec<TypeId> = variant.fields.iter().map(Field::tid).collect();
+        let param_idents = variant
+            .fields
+            .iter()
+            .map(|field| field.ident().to_string())
+            .collect();
+        let defaults = variant_def
+            .fields
+            .iter()
+            .map(|field| field.default.clone())
+            .collect();
+        let (fn_val, fn_id) = env.create_func(Some(tid), &variant.ident, &params, tid, false)?;
+        env.set_fn_params(fn_id, param_idents, defaults, false);
+        let entry_block = self.ctx.append_basic_block(fn_val, "entry");
+        self.builder.position_at_end(entry_block);
+
+        // The fields of the other variants stay null
+        let field_count = struct_type.count_fields() - 2;
+        let mut values: Vec<BasicValueEnum> =
+            vec![self.ctx.i64_type().const_int(variant.tag, false).into()];
+        let null = self.ptr_type().const_null().as_basic_value_enum();
+        values.extend((0..field_count).map(|_| null));
+        for (i, field) in variant.fields.iter().enumerate() {
+            values[field.index() as usize] = fn_val.get_nth_param(i as u32).unwrap();
+        }
+
+        let enum_ptr = self.build_struct(struct_type, values)?;
+        self.builder.build_return(Some(&enum_ptr))?;
+
+        Ok(())
+    }
+
+    /// Creates the value of a variant without fields, which is written like `Shape.Empty`.
+    pub(super) fn build_unit_variant(
+        &mut self,
+        tid: TypeId,
+        ident: &str,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let fn_id = env.find_func(ident, Some(tid), &[])?;
+        self.call_func(fn_id, &[], env)
+    }
+
+    pub(super) fn build_load_tag(
+        &mut self,
+        ptr: PointerValue<'ctx>,
+        tid: TypeId,
+        env: &Environment<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        let struct_type = tid.get_from(env).ink();
+        let tag_ptr = self
+            .builder
+            .build_struct_gep(struct_type, ptr, TAG_FIELD, "tag_ptr")?;
+        let tag = self
+            .builder
+            .build_load(self.ctx.i64_type(), tag_ptr, "tag")?
+            .into_int_value();
+        Ok(tag)
+    }
+
+    /// Loads a field of a variant without taking a reference to it.
+    pub(super) fn build_load_variant_field(
+        &mut self,
+        ptr: PointerValue<'ctx>,
+        tid: TypeId,
+        field: &Field,
+        env: &Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        let struct_type = tid.get_from(env).ink();
+        let field_ptr_ptr =
+            self.builder
+                .build_struct_gep(struct_type, ptr, field.index(), "variant_field_ptr")?;
+        let field_ptr = self
+            .builder
+            .build_load(self.ptr_type(), field_ptr_ptr, "variant_field")?
+            .into_pointer_value();
+        Ok(field_ptr)
+    }
+}
@@ -0,0 +1,69 @@
+use inkwell::values::GlobalValue;
+
+use crate::parser::StructField;
+
+use super::id::{FunctionId, TypeId};
+
+/// A method of a class that is called through the vtable.
+#[derive(Debug, Clone)]
+pub struct VirtualMethod {
+    pub ident: String,
+    /// The parameter types after `self`, which an override has to match.
+    pub params: Vec<TypeId>,
+    pub ret_type: TypeId,
+    /// The function registered for the class, which calls the entry of the vtable.
+    pub dispatch: FunctionId,
+    /// The function the entry of the vtable points to, which may be inherited.
+    pub implementation: FunctionId,
+}
+
+#[derive(Debug)]
+pub struct ClassDef<'ctx> {
+    base: Option<TypeId>,
+    /// Every field of the class, starting with the inherited ones.
+    fields: Vec<StructField>,
+    vtable: GlobalValue<'ctx>,
+    /// The entries of the vtable after the pointer functions, starting with the inherited ones.
+    methods: Vec<VirtualMethod>,
+}
+
+impl<'ctx> ClassDef<'ctx> {
+    pub fn new(
+        base: Option<TypeId>,
+        fields: Vec<StructField>,
+        vtable: GlobalValue<'ctx>,
+        methods: Vec<VirtualMethod>,
+    ) -> Self {
+        Self {
+            base,
+            fields,
+            vtable,
+            methods,
+        }
+    }
+
+    pub fn base(&self) -> Option<TypeId> {
+        self.base
+    }
+
+    pub fn fields(&self) -> &[StructField] {
+        &self.fields
+    }
+
+    pub fn vtable(&self) -> GlobalValue<'ctx> {
+        self.vtable
+    }
+
+    pub fn methods(&self) -> &[VirtualMethod] {
+        &self.methods
+    }
+
+    /// Returns the implementation called by the given function of the class, which is the
+    /// function itself when it is not called through the vtable.
+    pub fn implementation_of(&self, fn_id: FunctionId) -> FunctionId {
+        self.methods
+            .iter()
+            .find(|method| method.dispatch == fn_id)
+            .map_or(fn_id, |method| method.implementation)
+    }
+}
@@ -0,0 +1,30 @@
+use super::type_def::Field;
+
+/// A variant of an enum. Its fields are stored in fields of the enum that no other variant
+/// uses, which are null while the enum holds another variant.
+#[derive(Debug, Clone)]
+pub struct Variant {
+    pub ident: String,
+    /// The value stored in front of the fields of the enum to tell its variants apart.
+    pub tag: u64,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug)]
+pub struct EnumDef {
+    variants: Vec<Variant>,
+}
+
+impl EnumDef {
+    pub fn new(variants: Vec<Variant>) -> Self {
+        Self { variants }
+    }
+
+    pub fn variants(&self) -> &[Variant] {
+        &self.variants
+    }
+
+    pub fn find_variant(&self, ident: &str) -> Option<&Variant> {
+        self.variants.iter().find(|variant| variant.ident == ident)
+    }
+}
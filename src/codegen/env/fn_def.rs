@@ -1,5 +1,7 @@
 use inkwell::values::FunctionValue;
 
+use crate::parser::Expression;
+
 use super::id::TypeId;
 
 pub fn create_fn_name(
@@ -25,12 +27,32 @@ pub fn create_fn_name(
     name
 }
 
+/// Where a call gets the value of a parameter from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgSource {
+    /// The positional argument at the index.
+    Positional(usize),
+    /// The keyword argument at the index.
+    Keyword(usize),
+    /// The default value of the parameter.
+    Default,
+    /// The positional arguments from the index on, which are collected into a list.
+    VarArgs(usize),
+}
+
 #[derive(Debug)]
 pub struct FuncDef<'ctx> {
     pub ident: String,
     pub value: FunctionValue<'ctx>,
     pub params: Vec<TypeId>,
     pub ret_type: TypeId,
+    /// The names of the parameters, which are only known for functions declared in Reticulated
+    /// code and struct constructors. Builtins can only be called with positional arguments.
+    pub param_idents: Vec<String>,
+    pub defaults: Vec<Option<Expression>>,
+    /// Whether the last parameter is declared as `*args`, so that its list holds the positional
+    /// arguments left over after the other parameters.
+    pub var_args: bool,
 }
 
 impl<'ctx> FuncDef<'ctx> {
@@ -45,9 +67,16 @@ impl<'ctx> FuncDef<'ctx> {
             value,
             params,
             ret_type,
+            param_idents: Vec::new(),
+            defaults: Vec::new(),
+            var_args: false,
         }
     }
 
+    pub fn has_default(&self, param: usize) -> bool {
+        self.defaults.get(param).is_some_and(Option::is_some)
+    }
+
     pub fn ink(&self) -> FunctionValue<'ctx> {
         self.value
     }
@@ -9,6 +9,9 @@ pub struct FuncEnvironment<'ctx> {
     pub fn_id: FunctionId,
     pub is_script: bool,
     pub scopes: Vec<Scope<'ctx>>,
+    /// The variables of enclosing functions captured by a closure, which point into its context.
+    /// The context owns them, so they are looked up after the locals and never freed here.
+    pub(super) captures: HashMap<String, (PointerValue<'ctx>, TypeId)>,
 }
 
 impl<'ctx> FuncEnvironment<'ctx> {
@@ -17,6 +20,7 @@ impl<'ctx> FuncEnvironment<'ctx> {
             fn_id,
             is_script,
             scopes: vec![],
+            captures: HashMap::new(),
         }
     }
 }
@@ -24,6 +28,9 @@ impl<'ctx> FuncEnvironment<'ctx> {
 #[derive(Default, Debug)]
 pub struct Scope<'ctx> {
     pub(super) variables: HashMap<String, (PointerValue<'ctx>, TypeId)>,
+    /// Variables from outer scopes whose type is known to be narrower in this scope (e.g. after
+    /// an `is not None` check). These are owned by their original scope, so they are never freed here.
+    pub(super) narrowed: HashMap<String, (PointerValue<'ctx>, TypeId)>,
     pub has_returned: bool,
 }
 
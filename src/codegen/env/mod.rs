@@ -1,21 +1,29 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc};
 
-use fn_def::{create_fn_name, FuncDef};
+use class_def::ClassDef;
+use enum_def::EnumDef;
+use fn_def::{create_fn_name, ArgSource, FuncDef};
 use func::{FuncEnvironment, Scope};
-use id::{FunctionId, TypeId, INVALID_FN_ID};
+use id::{FunctionId, TypeId, INVALID_FN_ID, NONE_ID};
 use inkwell::{
     module::Module,
     types::BasicMetadataTypeEnum,
     values::{FunctionValue, PointerValue},
     AddressSpace,
 };
-use type_def::TypeDef;
+use template::Template;
+use type_def::{TypeDef, TypeInfo};
+
+use crate::parser::{Derive, Expression, ProtocolMethod};
 
 use super::err::GenError;
 
+pub mod class_def;
+pub mod enum_def;
 pub mod fn_def;
 pub mod func;
 pub mod id;
+pub mod template;
 pub mod type_def;
 
 #[derive(Debug)]
@@ -28,8 +36,27 @@ pub struct Environment<'ctx> {
     types: HashMap<TypeId, TypeDef<'ctx>>,
 
     next_fn_id: u64,
+    next_closure_id: u64,
     fn_ids: HashMap<String, FunctionId>,
     fns: HashMap<FunctionId, FuncDef<'ctx>>,
+    /// Every function with a given owner and identifier, regardless of parameter types.
+    fn_overloads: HashMap<(Option<TypeId>, String), Vec<FunctionId>>,
+
+    templates: HashMap<String, Rc<Template>>,
+    /// The types bound to the type parameters of the template being instantiated.
+    type_args: HashMap<String, TypeId>,
+    /// The template and type arguments that each instance of a generic struct was created from.
+    instances: HashMap<TypeId, (String, Vec<TypeId>)>,
+    /// The parameter and return types of each function type.
+    fn_signatures: HashMap<TypeId, (Vec<TypeId>, TypeId)>,
+    /// The methods each struct generates from its fields, which are created once they are used.
+    derives: HashMap<TypeId, Vec<Derive>>,
+    classes: HashMap<TypeId, ClassDef<'ctx>>,
+    /// The methods required by each protocol.
+    protocols: HashMap<String, Rc<Vec<ProtocolMethod>>>,
+    enums: HashMap<TypeId, EnumDef>,
+    /// The type and value of the constants declared in the body of each struct.
+    consts: HashMap<(TypeId, String), (TypeId, Expression)>,
 }
 
 impl<'ctx> Environment<'ctx> {
@@ -43,8 +70,20 @@ impl<'ctx> Environment<'ctx> {
             types: HashMap::new(),
 
             next_fn_id: 1,
+            next_closure_id: 0,
             fn_ids: HashMap::new(),
             fns: HashMap::new(),
+            fn_overloads: HashMap::new(),
+
+            templates: HashMap::new(),
+            type_args: HashMap::new(),
+            instances: HashMap::new(),
+            fn_signatures: HashMap::new(),
+            derives: HashMap::new(),
+            classes: HashMap::new(),
+            protocols: HashMap::new(),
+            enums: HashMap::new(),
+            consts: HashMap::new(),
         }
     }
 
@@ -54,12 +93,36 @@ impl<'ctx> Environment<'ctx> {
 
     pub fn get_var(&self, ident: &str) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
         for scope in self.func.scopes.iter().rev() {
+            if let Some(var) = scope.narrowed.get(ident) {
+                return Ok(*var);
+            }
+
             if scope.variables.contains_key(ident) {
                 return Ok(scope.variables.get(ident).cloned().unwrap());
             }
         }
 
-        Err(GenError::VariableNotFound)
+        self.func
+            .captures
+            .get(ident)
+            .copied()
+            .ok_or(GenError::VariableNotFound)
+    }
+
+    /// Makes a variable captured by the current closure visible, where `capture_ptr` points to
+    /// its field in the context of the closure.
+    pub fn insert_capture(&mut self, ident: String, capture_ptr: PointerValue<'ctx>, tid: TypeId) {
+        self.func.captures.insert(ident, (capture_ptr, tid));
+    }
+
+    /// Treats an existing variable as having a narrower type until the current scope ends.
+    pub fn narrow_var(&mut self, ident: String, var_ptr: PointerValue<'ctx>, tid: TypeId) {
+        self.func
+            .scopes
+            .last_mut()
+            .unwrap()
+            .narrowed
+            .insert(ident, (var_ptr, tid));
     }
 
     pub fn insert_var(&mut self, ident: String, var_ptr: PointerValue<'ctx>, ptr_type: TypeId) {
@@ -115,12 +178,146 @@ impl<'ctx> Environment<'ctx> {
     }
 
     pub fn find_type(&self, ident: &str) -> Result<TypeId, GenError> {
-        self.type_ids
+        self.type_args
             .get(ident)
+            .or_else(|| self.type_ids.get(ident))
             .copied()
             .ok_or(GenError::TypeNotFound)
     }
 
+    pub fn register_template(&mut self, ident: &str, template: Template) -> Result<(), GenError> {
+        if self.templates.contains_key(ident)
+            || self.type_ids.contains_key(ident)
+            || self.protocols.contains_key(ident)
+        {
+            return Err(GenError::IdentConflict);
+        }
+
+        self.templates.insert(ident.to_string(), Rc::new(template));
+        Ok(())
+    }
+
+    pub fn find_template(&self, ident: &str) -> Option<Rc<Template>> {
+        self.templates.get(ident).cloned()
+    }
+
+    /// Binds type parameters to types while a template is instantiated and returns the old bindings.
+    pub fn set_type_args(&mut self, type_args: HashMap<String, TypeId>) -> HashMap<String, TypeId> {
+        std::mem::replace(&mut self.type_args, type_args)
+    }
+
+    pub fn register_instance(&mut self, tid: TypeId, template_ident: &str, type_args: &[TypeId]) {
+        self.instances
+            .insert(tid, (template_ident.to_string(), type_args.to_vec()));
+    }
+
+    /// Returns the template and type arguments of a generic struct instance.
+    pub fn instance_of(&self, tid: TypeId) -> Option<&(String, Vec<TypeId>)> {
+        self.instances.get(&tid)
+    }
+
+    pub fn register_fn_type(&mut self, tid: TypeId, params: &[TypeId], ret_type: TypeId) {
+        self.fn_signatures.insert(tid, (params.to_vec(), ret_type));
+    }
+
+    /// Returns the parameter and return types of a function type.
+    pub fn fn_signature(&self, tid: TypeId) -> Option<&(Vec<TypeId>, TypeId)> {
+        self.fn_signatures.get(&tid)
+    }
+
+    pub fn register_derives(&mut self, tid: TypeId, derives: &[Derive]) {
+        self.derives.insert(tid, derives.to_vec());
+    }
+
+    /// Returns the methods a struct generates from its fields.
+    pub fn derives(&self, tid: TypeId) -> &[Derive] {
+        self.derives.get(&tid).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn register_class(&mut self, tid: TypeId, class_def: ClassDef<'ctx>) {
+        self.classes.insert(tid, class_def);
+    }
+
+    pub fn class_def(&self, tid: TypeId) -> Option<&ClassDef<'ctx>> {
+        self.classes.get(&tid)
+    }
+
+    /// Returns the type itself followed by the classes it inherits from, nearest first.
+    pub fn type_and_bases(&self, tid: TypeId) -> Vec<TypeId> {
+        let mut tids = vec![tid];
+        while let Some(base) = self
+            .class_def(*tids.last().unwrap())
+            .and_then(ClassDef::base)
+        {
+            tids.push(base);
+        }
+        tids
+    }
+
+    /// Whether a class inherits from another, directly or through its bases.
+    pub fn is_subclass(&self, tid: TypeId, base: TypeId) -> bool {
+        let mut cur = self.class_def(tid).and_then(ClassDef::base);
+        while let Some(cur_tid) = cur {
+            if cur_tid == base {
+                return true;
+            }
+            cur = self.class_def(cur_tid).and_then(ClassDef::base);
+        }
+        false
+    }
+
+    pub fn register_protocol(
+        &mut self,
+        ident: &str,
+        methods: Vec<ProtocolMethod>,
+    ) -> Result<(), GenError> {
+        if self.protocols.contains_key(ident)
+            || self.templates.contains_key(ident)
+            || self.type_ids.contains_key(ident)
+        {
+            return Err(GenError::IdentConflict);
+        }
+
+        self.protocols.insert(ident.to_string(), Rc::new(methods));
+        Ok(())
+    }
+
+    pub fn find_protocol(&self, ident: &str) -> Option<Rc<Vec<ProtocolMethod>>> {
+        self.protocols.get(ident).cloned()
+    }
+
+    pub fn register_enum(&mut self, tid: TypeId, enum_def: EnumDef) {
+        self.enums.insert(tid, enum_def);
+    }
+
+    pub fn enum_def(&self, tid: TypeId) -> Option<&EnumDef> {
+        self.enums.get(&tid)
+    }
+
+    pub fn register_const(
+        &mut self,
+        owner: TypeId,
+        ident: &str,
+        tid: TypeId,
+        value: Expression,
+    ) -> Result<(), GenError> {
+        let key = (owner, ident.to_string());
+        if self.consts.contains_key(&key) {
+            return Err(GenError::IdentConflict);
+        }
+
+        self.consts.insert(key, (tid, value));
+        Ok(())
+    }
+
+    /// Finds a constant of a type, including those declared by the classes it inherits from.
+    pub fn find_const(&self, owner: TypeId, ident: &str) -> Option<(TypeId, Expression)> {
+        self.type_and_bases(owner)
+            .into_iter()
+            .find_map(|tid| self.consts.get(&(tid, ident.to_string())))
+            .cloned()
+    }
+
     // pub fn create_type(
     //     &mut self,
     //     ident: &str,
@@ -146,6 +343,265 @@ impl<'ctx> Environment<'ctx> {
             .ok_or(GenError::FunctionNotFound)
     }
 
+    /// Returns the function with the given owner and identifier if it is not overloaded.
+    pub fn unique_func(&self, owner: Option<TypeId>, ident: &str) -> Option<FunctionId> {
+        match self
+            .fn_overloads
+            .get(&(owner, ident.to_string()))?
+            .as_slice()
+        {
+            [id] => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Whether any function with the given owner and identifier has been declared.
+    pub fn has_func(&self, owner: Option<TypeId>, ident: &str) -> bool {
+        self.fn_overloads.contains_key(&(owner, ident.to_string()))
+    }
+
+    /// Finds the function to call with the given argument types, allowing arguments to be
+    /// coerced to the parameter types when there is no exact match.
+    pub fn resolve_func(
+        &self,
+        ident: &str,
+        owner: Option<TypeId>,
+        arg_tids: &[TypeId],
+    ) -> Result<FunctionId, GenError> {
+        if let Some(id) = self.find_exact_func(ident, owner, arg_tids) {
+            return Ok(id);
+        }
+
+        // Every argument has to be passed as it is, since the caller fills in no defaults and
+        // does not collect `*args`
+        let candidates = self
+            .fn_overloads
+            .get(&(owner, ident.to_string()))
+            .ok_or(GenError::FunctionNotFound)?
+            .iter()
+            .filter(|id| {
+                self.bind_args(**id, arg_tids, &[]).is_some_and(|sources| {
+                    sources
+                        .iter()
+                        .all(|source| matches!(source, ArgSource::Positional(_)))
+                })
+            })
+            .copied()
+            .collect();
+
+        self.pick_overload(ident, candidates)
+    }
+
+    /// Finds the function to call with the given positional and keyword arguments, along with
+    /// where each of its parameters gets its value from.
+    pub fn resolve_func_call(
+        &self,
+        ident: &str,
+        owner: Option<TypeId>,
+        arg_tids: &[TypeId],
+        kwarg_tids: &[(String, TypeId)],
+    ) -> Result<(FunctionId, Vec<ArgSource>), GenError> {
+        if kwarg_tids.is_empty() {
+            if let Some(id) = self.find_exact_func(ident, owner, arg_tids) {
+                return Ok((id, (0..arg_tids.len()).map(ArgSource::Positional).collect()));
+            }
+        }
+
+        let candidates = self
+            .fn_overloads
+            .get(&(owner, ident.to_string()))
+            .ok_or(GenError::FunctionNotFound)?
+            .iter()
+            .filter(|id| self.bind_args(**id, arg_tids, kwarg_tids).is_some())
+            .copied()
+            .collect();
+
+        let id = self.pick_overload(ident, candidates)?;
+        Ok((id, self.bind_args(id, arg_tids, kwarg_tids).unwrap()))
+    }
+
+    /// Finds the function referred to by a function value, which takes a bound `self` when it
+    /// has an owner. Overloads are told apart by the parameter types of the expected function
+    /// type, if there is one.
+    pub fn resolve_fn_ref(
+        &self,
+        ident: &str,
+        owner: Option<TypeId>,
+        expected: Option<TypeId>,
+    ) -> Result<FunctionId, GenError> {
+        // Only methods can be bound to a value, not the static methods and variant constructors
+        // that the type also owns
+        let overloads: Vec<FunctionId> = self
+            .fn_overloads
+            .get(&(owner, ident.to_string()))
+            .ok_or(GenError::FunctionNotFound)?
+            .iter()
+            .filter(|id| owner.is_none() || self.get_func(**id).params.first() == owner.as_ref())
+            .copied()
+            .collect();
+
+        let candidates = match expected.and_then(|tid| self.fn_signature(tid)) {
+            Some((params, ret_type)) => overloads
+                .into_iter()
+                .filter(|id| {
+                    let func = self.get_func(*id);
+                    func.params[owner.is_some() as usize..] == params[..]
+                        && self.can_coerce(func.ret_type, *ret_type)
+                })
+                .collect(),
+            None => overloads,
+        };
+
+        self.pick_overload(ident, candidates)
+    }
+
+    /// Finds the function whose parameter types are exactly the argument types, leaving out
+    /// functions with `*args`, whose list can only be built from the arguments of a call.
+    fn find_exact_func(
+        &self,
+        ident: &str,
+        owner: Option<TypeId>,
+        arg_tids: &[TypeId],
+    ) -> Option<FunctionId> {
+        self.find_func(ident, owner, arg_tids)
+            .ok()
+            .filter(|id| !self.get_func(*id).var_args)
+    }
+
+    /// Matches the arguments of a call to the parameters of a function, or returns `None` if
+    /// they do not fit. Positional arguments fill the first parameters, with any left over
+    /// going to `*args`, keyword arguments fill the parameters with their names, and the rest
+    /// have to have defaults.
+    fn bind_args(
+        &self,
+        id: FunctionId,
+        arg_tids: &[TypeId],
+        kwarg_tids: &[(String, TypeId)],
+    ) -> Option<Vec<ArgSource>> {
+        let func = self.get_func(id);
+        let fixed_params = func.params.len() - func.var_args as usize;
+        if !func.var_args && arg_tids.len() > fixed_params {
+            return None;
+        }
+
+        let mut sources: Vec<_> = (0..fixed_params)
+            .map(|i| (i < arg_tids.len()).then_some(ArgSource::Positional(i)))
+            .collect();
+        if func.var_args {
+            sources.push(Some(ArgSource::VarArgs(fixed_params.min(arg_tids.len()))));
+        }
+        for (i, (kw, _)) in kwarg_tids.iter().enumerate() {
+            let param = func.param_idents.iter().position(|ident| ident == kw)?;
+            if sources[param].is_some() {
+                return None;
+            }
+            sources[param] = Some(ArgSource::Keyword(i));
+        }
+
+        sources
+            .into_iter()
+            .enumerate()
+            .map(|(i, source)| {
+                let source = source.or(func.has_default(i).then_some(ArgSource::Default))?;
+                let fits = match source {
+                    ArgSource::Positional(j) => self.can_coerce(arg_tids[j], func.params[i]),
+                    ArgSource::Keyword(j) => self.can_coerce(kwarg_tids[j].1, func.params[i]),
+                    ArgSource::Default => true,
+                    ArgSource::VarArgs(start) => {
+                        let TypeInfo::List(elem_tid) = self.get_type(func.params[i]).info() else {
+                            return None;
+                        };
+                        arg_tids[start..]
+                            .iter()
+                            .all(|arg_tid| self.can_coerce(*arg_tid, elem_tid))
+                    }
+                };
+                fits.then_some(source)
+            })
+            .collect()
+    }
+
+    fn pick_overload(
+        &self,
+        ident: &str,
+        candidates: Vec<FunctionId>,
+    ) -> Result<FunctionId, GenError> {
+        match candidates[..] {
+            [] => Err(GenError::FunctionNotFound),
+            [id] => Ok(id),
+            _ => Err(GenError::AmbiguousCall {
+                ident: ident.to_string(),
+                candidates: candidates
+                    .iter()
+                    .map(|id| self.describe_func(ident, *id))
+                    .collect(),
+            }),
+        }
+    }
+
+    /// Describes the signature of a function as it was declared, such as
+    /// `f(x: int, scale: float = ...)`, which is used to tell overloads apart in errors.
+    fn describe_func(&self, ident: &str, id: FunctionId) -> String {
+        let func = self.get_func(id);
+        let params: Vec<_> = func
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, tid)| {
+                let is_var_args = func.var_args && i == func.params.len() - 1;
+                let tid = match self.get_type(*tid).info() {
+                    TypeInfo::List(elem_tid) if is_var_args => elem_tid,
+                    _ => *tid,
+                };
+
+                let mut param = match func.param_idents.get(i) {
+                    Some(param_ident) => format!("{param_ident}: {}", self.type_id_ident(tid)),
+                    None => self.type_id_ident(tid).to_string(),
+                };
+                if is_var_args {
+                    param.insert(0, '*');
+                }
+                if func.has_default(i) {
+                    param.push_str(" = ...");
+                }
+                param
+            })
+            .collect();
+
+        format!("{ident}({})", params.join(", "))
+    }
+
+    /// Records the names and default values of the parameters of a function declared in
+    /// Reticulated code, so that calls can pass them by name or leave them out, along with
+    /// whether its last parameter is `*args`.
+    pub fn set_fn_params(
+        &mut self,
+        id: FunctionId,
+        param_idents: Vec<String>,
+        defaults: Vec<Option<Expression>>,
+        var_args: bool,
+    ) {
+        let func = self.fns.get_mut(&id).unwrap();
+        func.param_idents = param_idents;
+        func.defaults = defaults;
+        func.var_args = var_args;
+    }
+
+    /// Whether a value of type `from` can be used where a value of type `to` is expected.
+    ///
+    /// Coercions never change the representation of a value, so no code needs to be generated.
+    pub fn can_coerce(&self, from: TypeId, to: TypeId) -> bool {
+        if from == to {
+            return true;
+        }
+
+        // A class is used in place of its bases without any conversion
+        match self.get_type(to).optional_inner() {
+            Some(inner) => from == NONE_ID || from == inner || self.is_subclass(from, inner),
+            None => self.is_subclass(from, to),
+        }
+    }
+
     pub fn get_func(&self, id: FunctionId) -> &FuncDef<'ctx> {
         self.fns.get(&id).unwrap()
     }
@@ -162,15 +618,34 @@ impl<'ctx> Environment<'ctx> {
         ret_type: TypeId,
         is_var_args: bool,
     ) -> Result<(FunctionValue<'ctx>, FunctionId), GenError> {
-        // Ensure the owner type is the first parameter
-        if let Some(owner) = owner {
-            if param_types.len() == 0 || param_types[0] != owner {
-                return Err(GenError::InvalidFunctionDefinition);
-            }
-        }
+        self.add_func(owner, ident, None, param_types, ret_type, is_var_args)
+    }
 
+    /// Declares a function of the runtime library, which is linked into the program. It is looked
+    /// up by its Reticulated identifier like any other function, but keeps its C symbol in LLVM.
+    pub fn declare_runtime_func(
+        &mut self,
+        owner: Option<TypeId>,
+        ident: &str,
+        symbol: &str,
+        param_types: &[TypeId],
+        ret_type: TypeId,
+    ) -> Result<FunctionId, GenError> {
+        let (_, id) = self.add_func(owner, ident, Some(symbol), param_types, ret_type, false)?;
+        Ok(id)
+    }
+
+    fn add_func(
+        &mut self,
+        owner: Option<TypeId>,
+        ident: &str,
+        symbol: Option<&str>,
+        param_types: &[TypeId],
+        ret_type: TypeId,
+        is_var_args: bool,
+    ) -> Result<(FunctionValue<'ctx>, FunctionId), GenError> {
         let id = self.gen_fn_id(owner);
-        let fn_name = self.create_fn_name(ident, owner, &param_types);
+        let fn_name = self.create_fn_name(ident, owner, param_types);
 
         let ink_param_types = param_types
             .iter()
@@ -188,17 +663,37 @@ impl<'ctx> Environment<'ctx> {
             .ptr_type(AddressSpace::default())
             .fn_type(&ink_param_types, is_var_args);
 
-        let fn_value = self.module.add_function(&fn_name, ink_fn_type, None);
+        let symbol = symbol.unwrap_or(&fn_name);
+        let fn_value = self.module.add_function(symbol, ink_fn_type, None);
 
         self.register_fn(
             &fn_name,
             id,
             FuncDef::new(&fn_name, fn_value, param_types.to_vec(), ret_type),
         );
+        self.fn_overloads
+            .entry((owner, ident.to_string()))
+            .or_default()
+            .push(id);
 
         Ok((fn_value, id))
     }
 
+    /// Returns the function with the given signature, creating it if it has not been declared yet.
+    pub fn get_or_create_func(
+        &mut self,
+        owner: Option<TypeId>,
+        ident: &str,
+        param_types: &[TypeId],
+        ret_type: TypeId,
+        is_var_args: bool,
+    ) -> Result<(FunctionValue<'ctx>, FunctionId), GenError> {
+        match self.find_func(ident, owner, param_types) {
+            Ok(id) => Ok((self.get_func(id).ink(), id)),
+            Err(_) => self.create_func(owner, ident, param_types, ret_type, is_var_args),
+        }
+    }
+
     pub fn type_id_ident(&self, id: TypeId) -> &str {
         self.types.get(&id).unwrap().ident()
     }
@@ -236,7 +731,7 @@ impl<'ctx> Environment<'ctx> {
         id: TypeId,
         type_def: TypeDef<'ctx>,
     ) -> Result<(), GenError> {
-        if self.type_ids.contains_key(ident) {
+        if self.type_ids.contains_key(ident) || self.protocols.contains_key(ident) {
             return Err(GenError::IdentConflict);
         }
 
@@ -245,6 +740,14 @@ impl<'ctx> Environment<'ctx> {
         Ok(())
     }
 
+    /// Returns a name for the context type and function of a new closure, which cannot clash
+    /// with names written in source code.
+    pub fn gen_closure_ident(&mut self, ident: &str) -> String {
+        let closure_ident = format!("{}$closure{}", ident, self.next_closure_id);
+        self.next_closure_id += 1;
+        closure_ident
+    }
+
     pub fn gen_fn_id(&mut self, owner: Option<TypeId>) -> FunctionId {
         let id = FunctionId(self.next_fn_id, owner.unwrap_or(TypeId(0)).0);
         self.next_fn_id += 1;
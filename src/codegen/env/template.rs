@@ -0,0 +1,82 @@
+use crate::{
+    codegen::structs::INIT_FN,
+    parser::{Derive, FuncDeclaration, StructConst, StructField, TypeExpr},
+};
+
+/// A generic struct or function, which is compiled once for every distinct list of type
+/// arguments it is used with.
+#[derive(Debug)]
+pub enum Template {
+    Struct {
+        type_params: Vec<String>,
+        fields: Vec<StructField>,
+        consts: Vec<StructConst>,
+        fns: Vec<FuncDeclaration>,
+        protocols: Vec<String>,
+        derives: Vec<Derive>,
+    },
+    Func {
+        fn_dec: FuncDeclaration,
+        /// The protocols that the types bound to some of the type parameters have to conform to,
+        /// as pairs of type parameter and protocol.
+        bounds: Vec<(String, String)>,
+    },
+}
+
+impl Template {
+    pub fn type_params(&self) -> &[String] {
+        match self {
+            Template::Struct { type_params, .. } => type_params,
+            Template::Func { fn_dec, .. } => &fn_dec.type_params,
+        }
+    }
+
+    /// Whether the function collects the positional arguments left over into `*args`.
+    pub fn has_var_args(&self) -> bool {
+        match self {
+            Template::Struct { fns, .. } => Self::unique_init(fns).is_some_and(Self::fn_var_args),
+            Template::Func { fn_dec, .. } => Self::fn_var_args(fn_dec),
+        }
+    }
+
+    /// The names and types of the arguments used to call the function or construct the struct.
+    /// A struct with several `__init__` overloads has no single list, so its type arguments
+    /// cannot be inferred.
+    pub fn params(&self) -> Vec<(&str, &TypeExpr)> {
+        match self {
+            Template::Struct { fields, fns, .. } => {
+                let init_count = fns
+                    .iter()
+                    .filter(|fn_dec| fn_dec.identifier == INIT_FN)
+                    .count();
+                match Self::unique_init(fns) {
+                    Some(init) => Self::fn_params(init),
+                    None if init_count > 0 => Vec::new(),
+                    None => fields
+                        .iter()
+                        .map(|field| (field.identifier.as_str(), &field.type_identifier))
+                        .collect(),
+                }
+            }
+            Template::Func { fn_dec, .. } => Self::fn_params(fn_dec),
+        }
+    }
+
+    fn unique_init(fns: &[FuncDeclaration]) -> Option<&FuncDeclaration> {
+        let mut inits = fns.iter().filter(|fn_dec| fn_dec.identifier == INIT_FN);
+        let init = inits.next()?;
+        inits.next().is_none().then_some(init)
+    }
+
+    fn fn_var_args(fn_dec: &FuncDeclaration) -> bool {
+        fn_dec.params.last().is_some_and(|param| param.var_args)
+    }
+
+    fn fn_params(fn_dec: &FuncDeclaration) -> Vec<(&str, &TypeExpr)> {
+        fn_dec
+            .params
+            .iter()
+            .map(|param| (param.identifier.as_str(), &param.type_identifier))
+            .collect()
+    }
+}
@@ -4,11 +4,24 @@ use crate::codegen::err::GenError;
 
 use super::id::TypeId;
 
-// #[derive(Debug)]
-// pub enum TypeInfo {
-//     Primitive,
-//     UserDefined { fields: Vec<TypeId> },
-// }
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeInfo {
+    Primitive,
+    Struct,
+    /// A value of the inner type or `None`, represented as a possibly null pointer to the inner type.
+    Optional(TypeId),
+    /// A growable array of the element type.
+    List(TypeId),
+    /// A hash map from the key type to the value type.
+    Dict(TypeId, TypeId),
+    /// A hash set of the element type.
+    Set(TypeId),
+    /// A fixed-size group of values, stored as fields named by their position.
+    Tuple,
+    /// A function that can be called through a value, whose signature is kept by the
+    /// environment.
+    Func,
+}
 
 #[derive(Debug, Clone)]
 pub struct Field(u32, String, TypeId); // index, ident, type
@@ -36,6 +49,7 @@ pub struct TypeDef<'ctx> {
     ident: String,
     value: StructType<'ctx>,
     fields: Vec<Field>,
+    info: TypeInfo,
 }
 
 impl<'ctx> TypeDef<'ctx> {
@@ -44,6 +58,7 @@ impl<'ctx> TypeDef<'ctx> {
             ident: ident.to_string(),
             value,
             fields,
+            info: TypeInfo::Struct,
         }
     }
 
@@ -52,6 +67,29 @@ impl<'ctx> TypeDef<'ctx> {
             ident: ident.to_string(),
             value,
             fields: Vec::new(),
+            info: TypeInfo::Primitive,
+        }
+    }
+
+    /// Creates an instance of a builtin generic type, such as `Optional[int]` or `list[str]`.
+    pub fn new_generic(ident: impl ToString, value: StructType<'ctx>, info: TypeInfo) -> Self {
+        Self {
+            ident: ident.to_string(),
+            value,
+            fields: Vec::new(),
+            info,
+        }
+    }
+
+    pub fn info(&self) -> TypeInfo {
+        self.info
+    }
+
+    /// The type wrapped by this type, if it is an optional type.
+    pub fn optional_inner(&self) -> Option<TypeId> {
+        match self.info {
+            TypeInfo::Optional(inner) => Some(inner),
+            _ => None,
         }
     }
 
@@ -75,6 +113,10 @@ impl<'ctx> TypeDef<'ctx> {
     pub fn fields(&self) -> &[Field] {
         &self.fields
     }
+
+    pub fn set_fields(&mut self, fields: Vec<Field>) {
+        self.fields = fields;
+    }
 }
 
 impl<'ctx> Into<StructType<'ctx>> for &TypeDef<'ctx> {
@@ -5,11 +5,39 @@ pub enum GenError {
     Call,
     InvalidFunctionDefinition,
     FunctionNotFound,
+    /// A call matched several overloads of a function, which are described by their signatures.
+    AmbiguousCall {
+        ident: String,
+        candidates: Vec<String>,
+    },
     TypeNotFound,
     InvalidType,
+    CannotInferType,
     IdentConflict,
     VariableNotFound,
     FieldNotFound,
+    /// A field without a default value is not set by every `__init__` of its struct, or may be
+    /// read before it is set.
+    UninitializedField {
+        struct_ident: String,
+        field: String,
+    },
+    /// A type lacks a method required by a protocol it is used as or declares to conform to.
+    DoesNotConform {
+        type_ident: String,
+        protocol: String,
+    },
+    /// A match statement has no case for values like the ones described by `missing`.
+    NonExhaustiveMatch {
+        missing: String,
+    },
+    IndexOutOfRange,
+    /// A generic struct or function failed to compile for the type arguments used on `line`.
+    Instantiation {
+        instance: String,
+        line: usize,
+        source: Box<GenError>,
+    },
     Build(BuilderError),
 }
 
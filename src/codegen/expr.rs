@@ -1,13 +1,22 @@
 use std::ops::Deref;
 
-use inkwell::{values::PointerValue, AddressSpace};
+use inkwell::{
+    values::{IntValue, PointerValue},
+    AddressSpace, IntPredicate,
+};
 
 use crate::parser::{BinaryFnOp, BinaryOp, Expression, Primary, UnaryFnOp, UnaryOp};
 
 use super::{
-    builtin::{TO_BOOL_FN, TO_FLOAT_FN, TO_INT_FN, TO_STR_FN},
+    builtin::{
+        io::{PrintArgs, FILE_NAME},
+        GET_ITEM_FN, LEN_FN, TO_BOOL_FN, TO_FLOAT_FN, TO_INT_FN, TO_STR_FN,
+    },
+    class::is_super,
     env::{
-        id::{TypeId, BOOL_ID, FLOAT_ID, INT_ID, STR_ID},
+        fn_def::ArgSource,
+        id::{FunctionId, TypeId, BOOL_ID, FLOAT_ID, INT_ID, NONE_ID, STR_ID},
+        type_def::TypeInfo,
         Environment,
     },
     err::GenError,
@@ -24,21 +33,317 @@ impl<'ctx> CodeGen<'ctx> {
         match expression {
             Expression::Binary(left, op, right) => self.compile_binary(left, op, right, env),
             Expression::BinaryFn(left, op, right) => self.compile_binary_fn(left, op, right, env),
+            Expression::Compare(first, chain) => self.compile_comparison_chain(first, chain, env),
+            Expression::Conditional(condition, then_expr, else_expr) => {
+                self.compile_conditional(condition, then_expr, else_expr, env)
+            }
             Expression::Unary(op, expr) => self.compile_unary(op, expr, env),
             Expression::UnaryFn(op, expr) => self.compile_unary_fn(op, expr, env),
-            Expression::Invoke(expr, params) => self.compile_invoke(expr, params, env),
-            Expression::Access(expr, id) => self.compile_access(expr, id, env),
+            Expression::Invoke(expr, args, kwargs) => self.compile_invoke(expr, args, kwargs, env),
+            Expression::Access(expr, id) => self.compile_access(expr, id, None, env),
+            Expression::Lambda(params, body) => self.compile_lambda(params, body, None, env),
+            Expression::Index(expr, index) => self.compile_index(expr, index, env),
+            Expression::Slice(start, stop, step) => self.compile_slice(start, stop, step, env),
+            Expression::List(items) => self.compile_list(items, None, env),
+            Expression::Dict(entries) => self.compile_dict(entries, None, env),
+            Expression::Set(items) => self.compile_set(items, None, env),
+            Expression::Tuple(items) => self.compile_tuple(items, None, env),
             Expression::Primary(primary) => self.compile_primary(primary, env),
         }
     }
 
+    /// Compiles an expression that is expected to have the given type, which is used to infer the
+    /// types of literals that could not be inferred on their own, like `[]` or `{}`.
+    pub(super) fn compile_hinted_expression(
+        &mut self,
+        expression: &Expression,
+        hint: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        match expression {
+            Expression::List(items) => self.compile_list(items, Some(hint), env),
+            // Like Python, `{}` is an empty dict, unless a set is expected
+            Expression::Dict(entries)
+                if entries.is_empty() && matches!(env.get_type(hint).info(), TypeInfo::Set(_)) =>
+            {
+                self.compile_set(&[], Some(hint), env)
+            }
+            Expression::Dict(entries) => self.compile_dict(entries, Some(hint), env),
+            Expression::Set(items) => self.compile_set(items, Some(hint), env),
+            Expression::Tuple(items) => self.compile_tuple(items, Some(hint), env),
+            // Overloaded functions are told apart by the expected function type
+            Expression::Primary(Primary::Identifier(ident))
+                if env.get_var(ident).is_err() && env.has_func(None, ident) =>
+            {
+                self.build_fn_ref(ident, None, Some(hint), env)
+            }
+            Expression::Access(expr, ident) => self.compile_access(expr, ident, Some(hint), env),
+            Expression::Lambda(params, body) => self.compile_lambda(params, body, Some(hint), env),
+            _ => self.compile_expression(expression, env),
+        }
+    }
+
+    fn compile_list(
+        &mut self,
+        items: &[Expression],
+        hint: Option<TypeId>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let elem_hint = hint.and_then(|tid| match env.get_type(tid).info() {
+            TypeInfo::List(elem) => Some(elem),
+            _ => None,
+        });
+
+        let mut elem_tid = elem_hint;
+        let mut elems = Vec::new();
+        for item in items {
+            elems.push(self.compile_element(item, elem_hint, &mut elem_tid, env)?);
+        }
+
+        let list_tid = self.list_of(Self::inferred_elem_type(elem_tid)?, env)?;
+        let list_ptr = self.build_list(list_tid, &elems, env)?;
+
+        Ok((list_ptr, list_tid))
+    }
+
+    fn compile_dict(
+        &mut self,
+        entries: &[(Expression, Expression)],
+        hint: Option<TypeId>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let (key_hint, value_hint) = match hint.map(|tid| env.get_type(tid).info()) {
+            Some(TypeInfo::Dict(key, value)) => (Some(key), Some(value)),
+            _ => (None, None),
+        };
+
+        let mut key_tid = key_hint;
+        let mut value_tid = value_hint;
+        let mut entry_ptrs = Vec::new();
+        for (key, value) in entries {
+            let key_ptr = self.compile_element(key, key_hint, &mut key_tid, env)?;
+            let value_ptr = self.compile_element(value, value_hint, &mut value_tid, env)?;
+            entry_ptrs.push((key_ptr, value_ptr));
+        }
+
+        let dict_tid = self.dict_of(
+            Self::inferred_elem_type(key_tid)?,
+            Self::inferred_elem_type(value_tid)?,
+            env,
+        )?;
+        let dict_ptr = self.build_dict(dict_tid, &entry_ptrs, env)?;
+
+        Ok((dict_ptr, dict_tid))
+    }
+
+    fn compile_set(
+        &mut self,
+        items: &[Expression],
+        hint: Option<TypeId>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let elem_hint = hint.and_then(|tid| match env.get_type(tid).info() {
+            TypeInfo::Set(elem) => Some(elem),
+            _ => None,
+        });
+
+        let mut elem_tid = elem_hint;
+        let mut elems = Vec::new();
+        for item in items {
+            elems.push(self.compile_element(item, elem_hint, &mut elem_tid, env)?);
+        }
+
+        let set_tid = self.set_of(Self::inferred_elem_type(elem_tid)?, env)?;
+        let set_ptr = self.build_set(set_tid, &elems, env)?;
+
+        Ok((set_ptr, set_tid))
+    }
+
+    fn compile_tuple(
+        &mut self,
+        items: &[Expression],
+        hint: Option<TypeId>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        // Each element is hinted with the type at its position, if the hint has the same length
+        let elem_hints = match hint.map(|tid| (tid, env.get_type(tid).info())) {
+            Some((tid, TypeInfo::Tuple)) => Some(self.tuple_elems(tid, env)),
+            _ => None,
+        }
+        .filter(|elem_hints| elem_hints.len() == items.len());
+
+        let mut elems = Vec::new();
+        let mut elem_tids = Vec::new();
+        for (i, item) in items.iter().enumerate() {
+            let elem_hint = elem_hints.as_ref().map(|elem_hints| elem_hints[i]);
+            let mut elem_tid = elem_hint;
+            elems.push(self.compile_element(item, elem_hint, &mut elem_tid, env)?);
+            elem_tids.push(elem_tid.unwrap());
+        }
+
+        let tuple_tid = self.tuple_of(&elem_tids, env)?;
+        let tuple_ptr = self.build_tuple(tuple_tid, &elems, env)?;
+
+        Ok((tuple_ptr, tuple_tid))
+    }
+
+    /// Compiles an element of a collection literal, updating the element type inferred so far.
+    ///
+    /// Elements must be coercible to the hinted element type if there is one, otherwise the
+    /// element type is unified with the type of every element.
+    fn compile_element(
+        &mut self,
+        item: &Expression,
+        elem_hint: Option<TypeId>,
+        elem_tid: &mut Option<TypeId>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        let (item_ptr, item_tid) = match elem_hint {
+            Some(elem_hint) => self.compile_hinted_expression(item, elem_hint, env)?,
+            None => self.compile_expression(item, env)?,
+        };
+
+        *elem_tid = Some(match (*elem_tid, elem_hint) {
+            (Some(elem_hint), Some(_)) if env.can_coerce(item_tid, elem_hint) => elem_hint,
+            (Some(_), Some(_)) => return Err(GenError::InvalidType),
+            (Some(prev_tid), None) => self.unify_types(prev_tid, item_tid, env)?,
+            (None, _) => item_tid,
+        });
+
+        Ok(item_ptr)
+    }
+
+    /// The element type of a collection literal, which cannot be inferred when it is empty or
+    /// only contains `None`.
+    fn inferred_elem_type(elem_tid: Option<TypeId>) -> Result<TypeId, GenError> {
+        match elem_tid {
+            Some(elem_tid) if elem_tid != NONE_ID => Ok(elem_tid),
+            _ => Err(GenError::CannotInferType),
+        }
+    }
+
+    fn compile_index(
+        &mut self,
+        expr: &Expression,
+        index: &Expression,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let (expr_ptr, expr_tid) = self.compile_expression(expr, env)?;
+
+        if let TypeInfo::Tuple = env.get_type(expr_tid).info() {
+            return self.compile_tuple_index(expr_ptr, expr_tid, index, env);
+        }
+
+        let (index_ptr, index_tid) = self.compile_expression(index, env)?;
+
+        let get_item_fn = self.find_method(GET_ITEM_FN, expr_tid, &[expr_tid, index_tid], env)?;
+
+        // Operands may have called functions that moved the current line
+        self.build_set_line_for(get_item_fn, env)?;
+
+        self.call_func(get_item_fn, &[expr_ptr, index_ptr], env)
+    }
+
+    fn compile_slice(
+        &mut self,
+        start: &Option<Box<Expression>>,
+        stop: &Option<Box<Expression>>,
+        step: &Option<Box<Expression>>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let bound_tid = self.optional_of(INT_ID, env)?;
+
+        let mut bounds = Vec::new();
+        for bound in [start, stop, step] {
+            let bound_ptr = match bound {
+                Some(expr) => {
+                    let (expr_ptr, expr_tid) =
+                        self.compile_hinted_expression(expr, bound_tid, env)?;
+                    if !env.can_coerce(expr_tid, bound_tid) {
+                        return Err(GenError::InvalidType);
+                    }
+                    expr_ptr
+                }
+                None => self.build_none(env)?,
+            };
+            bounds.push(bound_ptr);
+        }
+
+        // The step is checked where the slice is created
+        self.build_set_line()?;
+        self.build_slice([bounds[0], bounds[1], bounds[2]], env)
+    }
+
+    /// Indexes a tuple, which is only possible with a constant index since the type of the
+    /// element depends on its position.
+    fn compile_tuple_index(
+        &mut self,
+        tuple_ptr: PointerValue<'ctx>,
+        tuple_tid: TypeId,
+        index: &Expression,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let index = match index {
+            Expression::Primary(Primary::Integer(index)) => *index,
+            Expression::UnaryFn(UnaryFnOp::Negate, expr) => match expr.deref() {
+                Expression::Primary(Primary::Integer(index)) => -*index,
+                _ => return Err(GenError::InvalidType),
+            },
+            _ => return Err(GenError::InvalidType),
+        };
+
+        // Like Python, negative indices count from the end
+        let elems = self.tuple_elems(tuple_tid, env);
+        let len = elems.len() as i64;
+        let index = if index < 0 { index + len } else { index };
+        if !(0..len).contains(&index) {
+            return Err(GenError::IndexOutOfRange);
+        }
+
+        let elem_tid = elems[index as usize];
+        let elem_ptr = self.build_load_tuple_elem(tuple_ptr, tuple_tid, index as u32, env)?;
+        self.copy_pointer(elem_ptr, elem_tid, env)?;
+        self.free_pointer(tuple_ptr, tuple_tid, env)?;
+
+        Ok((elem_ptr, elem_tid))
+    }
+
+    /// Compiles a field access, or a method bound to the value it is accessed on when the type
+    /// has no field with the name. Constants can be accessed on the type or on its values.
     pub(super) fn compile_access(
         &mut self,
         expr: &Box<Expression>,
         ident: &String,
+        hint: Option<TypeId>,
         env: &mut Environment<'ctx>,
     ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        if let Some(tid) = self.type_named(expr, env)? {
+            return self.compile_type_access(tid, ident, env);
+        }
+
         let (expr_ptr, expr_tid) = self.compile_expression(expr, env)?;
+
+        if env.get_type(expr_tid).find_field(ident).is_err() {
+            if env.has_func(Some(expr_tid), ident) {
+                return self.build_fn_ref(ident, Some((expr_ptr, expr_tid)), hint, env);
+            }
+            if env.find_const(expr_tid, ident).is_some() {
+                self.free_pointer(expr_ptr, expr_tid, env)?;
+                return self.compile_const(expr_tid, ident, env);
+            }
+        }
+
+        self.build_take_field(expr_ptr, expr_tid, ident, env)
+    }
+
+    /// Loads a field of a value, taking ownership of the value.
+    fn build_take_field(
+        &mut self,
+        expr_ptr: PointerValue<'ctx>,
+        expr_tid: TypeId,
+        ident: &String,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
         let (field_ptr_ptr, field_tid) = self.build_gep_field(expr_ptr, expr_tid, ident, env)?;
 
         let field_ptr = self
@@ -60,39 +365,307 @@ impl<'ctx> CodeGen<'ctx> {
         &mut self,
         callee: &Box<Expression>,
         args: &Vec<Expression>,
+        kwargs: &[(String, Expression)],
         env: &mut Environment<'ctx>,
     ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        // `print` takes any arguments, so it is compiled separately unless a variable or a
+        // function of the program shadows it
+        if let Expression::Primary(Primary::Identifier(ident)) = callee.deref() {
+            if ident == "print" && env.get_var(ident).is_err() && !env.has_func(None, ident) {
+                return self.compile_print(args, kwargs, env);
+            }
+        }
+
+        let param_hints = self.param_hints(callee, env);
         let params = args
             .into_iter()
-            .map(|val| self.compile_expression(val, env))
+            .enumerate()
+            .map(|(i, val)| match param_hints.get(i) {
+                Some(hint) => self.compile_hinted_expression(val, *hint, env),
+                None => self.compile_expression(val, env),
+            })
             .collect::<Result<Vec<_>, GenError>>()?;
         let (mut param_vals, mut param_tids): (Vec<_>, Vec<_>) = params.into_iter().unzip();
 
-        let fn_id = match callee.deref() {
+        let mut kwarg_vals = Vec::with_capacity(kwargs.len());
+        let mut kwarg_tids = Vec::with_capacity(kwargs.len());
+        for (ident, expr) in kwargs {
+            let (ptr, tid) = self.compile_expression(expr, env)?;
+            kwarg_vals.push(ptr);
+            kwarg_tids.push((ident.clone(), tid));
+        }
+
+        // Builtins have no parameter names to match keyword arguments with
+        let arg_count = param_vals.len();
+        let builtin = |fn_id| match kwargs {
+            [] => Ok((fn_id, (0..arg_count).map(ArgSource::Positional).collect())),
+            _ => Err(GenError::Call),
+        };
+
+        // Variables shadow functions with the same name
+        let callee_is_value = match callee.deref() {
+            Expression::Primary(Primary::Identifier(ident)) => env.get_var(ident).is_ok(),
+            Expression::Index(generic, _) => match generic.deref() {
+                Expression::Primary(Primary::Identifier(ident)) => env.get_var(ident).is_ok(),
+                _ => true,
+            },
+            Expression::Access(..) => false,
+            _ => true,
+        };
+        if callee_is_value {
+            let (fn_value, fn_tid) = self.compile_expression(callee, env)?;
+            return self.compile_fn_value_call(
+                fn_value,
+                fn_tid,
+                &param_vals,
+                &param_tids,
+                kwargs,
+                env,
+            );
+        }
+
+        let namespace = match callee.deref() {
+            Expression::Access(expr, _) => self.type_named(expr, env)?,
+            _ => None,
+        };
+
+        let (fn_id, sources) = match callee.deref() {
             Expression::Primary(Primary::Identifier(ident)) => match ident.as_str() {
-                "str" => env.find_func(TO_STR_FN, param_tids.get(0).copied(), &param_tids)?,
-                "int" => env.find_func(TO_INT_FN, param_tids.get(0).copied(), &param_tids)?,
-                "float" => env.find_func(TO_FLOAT_FN, param_tids.get(0).copied(), &param_tids)?,
-                "bool" => env.find_func(TO_BOOL_FN, param_tids.get(0).copied(), &param_tids)?,
-                "ref_count" => {
-                    env.find_func(RCOUNT_IDENT, param_tids.get(0).copied(), &param_tids)?
+                "str" => builtin(self.find_builtin_fn(TO_STR_FN, &param_tids, env)?)?,
+                "len" => builtin(self.find_builtin_fn(LEN_FN, &param_tids, env)?)?,
+                "int" => {
+                    builtin(env.find_func(TO_INT_FN, param_tids.get(0).copied(), &param_tids)?)?
+                }
+                "float" => {
+                    builtin(env.find_func(TO_FLOAT_FN, param_tids.get(0).copied(), &param_tids)?)?
+                }
+                "bool" => {
+                    builtin(env.find_func(TO_BOOL_FN, param_tids.get(0).copied(), &param_tids)?)?
+                }
+                "ref_count" => builtin(env.find_func(
+                    RCOUNT_IDENT,
+                    param_tids.get(0).copied(),
+                    &param_tids,
+                )?)?,
+                _ => self.resolve_call(ident, &param_tids, &kwarg_tids, env)?,
+            },
+            Expression::Index(generic, type_args) => match generic.deref() {
+                Expression::Primary(Primary::Identifier(ident)) => {
+                    self.resolve_explicit_call(ident, type_args, &param_tids, &kwarg_tids, env)?
                 }
-                _ => env.find_func(ident, None, &param_tids)?,
+                _ => unreachable!(),
             },
+            Expression::Access(expr, ident) if is_super(expr) => {
+                self.resolve_super_call(ident, &mut param_vals, &mut param_tids, &kwarg_tids, env)?
+            }
+            // Static methods and variants are called on their type, as in `Vec.zero()` and
+            // `Shape.Circle(1.0)`
+            Expression::Access(_, ident) if namespace.is_some() => {
+                self.resolve_static_call(namespace.unwrap(), ident, &param_tids, &kwarg_tids, env)?
+            }
             Expression::Access(expr, ident) => {
                 let (expr_ptr, expr_tid) = self.compile_expression(expr, env)?;
 
+                // Fields holding functions are called through their value, unless a method has
+                // the same name
+                let field = env
+                    .get_type(expr_tid)
+                    .find_field(ident)
+                    .map(|field| field.tid());
+                if let Ok(field_tid) = field {
+                    if !env.has_func(Some(expr_tid), ident) && env.fn_signature(field_tid).is_some()
+                    {
+                        let (fn_value, fn_tid) =
+                            self.build_take_field(expr_ptr, expr_tid, ident, env)?;
+                        return self.compile_fn_value_call(
+                            fn_value,
+                            fn_tid,
+                            &param_vals,
+                            &param_tids,
+                            kwargs,
+                            env,
+                        );
+                    }
+                }
+
                 param_tids.insert(0, expr_tid);
                 param_vals.insert(0, expr_ptr);
-                env.find_func(ident, Some(expr_tid), &param_tids)?
+                match env.resolve_func_call(ident, Some(expr_tid), &param_tids, &kwarg_tids) {
+                    // Methods of builtin types are created the first time they are used
+                    Err(GenError::FunctionNotFound) if kwargs.is_empty() => {
+                        let fn_id = self.find_method(ident, expr_tid, &param_tids, env)?;
+                        (
+                            fn_id,
+                            (0..param_vals.len()).map(ArgSource::Positional).collect(),
+                        )
+                    }
+                    result => result?,
+                }
             }
-            _ => todo!("Add first-class function support."),
+            _ => unreachable!(),
+        };
+
+        let mut call_args = Vec::with_capacity(sources.len());
+        for (i, source) in sources.into_iter().enumerate() {
+            call_args.push(match source {
+                ArgSource::Positional(j) => param_vals[j],
+                ArgSource::Keyword(j) => kwarg_vals[j],
+                ArgSource::Default => self.compile_default_arg(fn_id, i, env)?,
+                ArgSource::VarArgs(start) => {
+                    let list_tid = env.get_func(fn_id).params[i];
+                    self.build_list(list_tid, &param_vals[start..], env)?
+                }
+            });
+        }
+
+        // Arguments may have called functions that moved the current line
+        self.build_set_line_for(fn_id, env)?;
+
+        self.call_func(fn_id, &call_args, env)
+    }
+
+    /// Returns the types expected for the positional arguments of a call, when they are known
+    /// before the arguments are compiled, so that arguments like lambdas can be typed by them.
+    fn param_hints(&self, callee: &Expression, env: &Environment<'ctx>) -> Vec<TypeId> {
+        let Expression::Primary(Primary::Identifier(ident)) = callee else {
+            return Vec::new();
+        };
+
+        match env.get_var(ident) {
+            Ok((_, tid)) => env
+                .fn_signature(tid)
+                .map(|(params, _)| params.clone())
+                .unwrap_or_default(),
+            // Only a function without overloads or `*args` has one type for every argument
+            Err(_) => env
+                .unique_func(None, ident)
+                .map(|id| env.get_func(id))
+                .filter(|func| !func.var_args)
+                .map(|func| func.params.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Compiles a call through a function value, which only takes positional arguments.
+    fn compile_fn_value_call(
+        &mut self,
+        fn_value: PointerValue<'ctx>,
+        fn_tid: TypeId,
+        args: &[PointerValue<'ctx>],
+        arg_tids: &[TypeId],
+        kwargs: &[(String, Expression)],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let Some((params, _)) = env.fn_signature(fn_tid) else {
+            return Err(GenError::Call);
+        };
+        if !kwargs.is_empty() {
+            return Err(GenError::Call);
+        }
+
+        let fits = params.len() == arg_tids.len()
+            && params
+                .iter()
+                .zip(arg_tids)
+                .all(|(param, arg)| env.can_coerce(*arg, *param));
+        if !fits {
+            return Err(GenError::InvalidType);
+        }
+
+        // The function behind the value is only known at runtime, so it may abort
+        self.build_set_line()?;
+        self.build_call_fn_value(fn_value, fn_tid, args, env)
+    }
+
+    /// Compiles the default value of a parameter at the call that leaves it out. Like the body of
+    /// the function, the default cannot see the variables of the caller.
+    fn compile_default_arg(
+        &mut self,
+        fn_id: FunctionId,
+        param: usize,
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        let func = env.get_func(fn_id);
+        let param_tid = func.params[param];
+        let default = func.defaults[param].clone().unwrap();
+
+        self.compile_detached_expression(&default, param_tid, env)
+    }
+
+    /// Compiles an expression written outside of any function body, such as a default value,
+    /// where none of the variables of the code it is compiled into are visible.
+    pub(super) fn compile_detached_expression(
+        &mut self,
+        expr: &Expression,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<PointerValue<'ctx>, GenError> {
+        let outer_fn_env = env.new_fn_env(env.func.fn_id, false);
+        env.push_scope();
+        let value = self.compile_hinted_expression(expr, tid, env);
+        env.pop_scope();
+        env.set_fn_env(outer_fn_env);
+
+        let (ptr, value_tid) = value?;
+        if !env.can_coerce(value_tid, tid) {
+            return Err(GenError::InvalidType);
+        }
+
+        Ok(ptr)
+    }
+
+    /// Compiles a call to `print`, which takes any number of values of any type along with the
+    /// keyword arguments `sep`, `end` and `file`.
+    fn compile_print(
+        &mut self,
+        args: &[Expression],
+        kwargs: &[(String, Expression)],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let values = args
+            .iter()
+            .map(|arg| self.compile_expression(arg, env))
+            .collect::<Result<Vec<_>, GenError>>()?;
+        let mut print_args = PrintArgs {
+            values,
+            sep: None,
+            end: None,
+            file: None,
         };
 
-        self.call_func(fn_id, &param_vals, env)
+        let file_tid = env.find_type(FILE_NAME)?;
+        for (ident, expr) in kwargs {
+            let (ptr, tid) = self.compile_expression(expr, env)?;
+            let (arg, expected_tid) = match ident.as_str() {
+                "sep" => (&mut print_args.sep, STR_ID),
+                "end" => (&mut print_args.end, STR_ID),
+                "file" => (&mut print_args.file, file_tid),
+                _ => return Err(GenError::Call),
+            };
+
+            if tid != expected_tid {
+                return Err(GenError::InvalidType);
+            }
+            *arg = Some(ptr);
+        }
+
+        self.build_print(print_args, env)
+    }
+
+    /// Finds the method that implements a builtin function like `str` or `len` for its argument.
+    fn find_builtin_fn(
+        &mut self,
+        ident: &str,
+        arg_tids: &[TypeId],
+        env: &mut Environment<'ctx>,
+    ) -> Result<FunctionId, GenError> {
+        match arg_tids {
+            [tid] => self.find_method(ident, *tid, arg_tids, env),
+            _ => Err(GenError::FunctionNotFound),
+        }
     }
 
-    // Currently, this only has short circuiting ops, everything else is implemented as a fn
+    // Currently, this only has ops that can't be overloaded, everything else is implemented as a fn
     fn compile_binary(
         &mut self,
         left: &Box<Expression>,
@@ -100,50 +673,112 @@ impl<'ctx> CodeGen<'ctx> {
         right: &Box<Expression>,
         env: &mut Environment<'ctx>,
     ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
-        let (left_ptr, left_tid) = self.compile_expression(left, env)?;
-
-        if left_tid != BOOL_ID {
-            return Err(GenError::InvalidType);
+        match op {
+            BinaryOp::And | BinaryOp::Or => self.compile_logical(left, op, right, env),
+            BinaryOp::Is | BinaryOp::IsNot => self.compile_identity(left, op, right, env),
         }
+    }
 
-        let left_type = left_tid.get_from(env).ink();
-        let left_bool = self
-            .extract_primitive(left_ptr, left_type)?
-            .into_int_value();
+    fn compile_logical(
+        &mut self,
+        left: &Expression,
+        op: &BinaryOp,
+        right: &Expression,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let bool_type = BOOL_ID.get_from(env).ink();
+
+        // Like Python, the result is whichever operand decided the outcome
+        let (left_ptr, left_tid) = self.compile_expression(left, env)?;
+        self.copy_pointer(left_ptr, left_tid, env)?;
+        let left_bool = self.build_truthy(left_ptr, left_tid, env)?;
 
         let cur_block = self.builder.get_insert_block().unwrap();
         let cur_fn = cur_block.get_parent().unwrap();
         let right_block = self
             .ctx
             .append_basic_block(cur_fn, &format!("right_{}_condition", op.to_string()));
+        let left_block = self
+            .ctx
+            .append_basic_block(cur_fn, &format!("left_{}_result", op.to_string()));
         let continue_block = self.ctx.append_basic_block(cur_fn, "continue");
 
         match op {
             BinaryOp::And => {
                 self.builder
-                    .build_conditional_branch(left_bool, right_block, continue_block)?;
+                    .build_conditional_branch(left_bool, right_block, left_block)?;
             }
             BinaryOp::Or => {
                 self.builder
-                    .build_conditional_branch(left_bool, continue_block, right_block)?;
+                    .build_conditional_branch(left_bool, left_block, right_block)?;
             }
+            BinaryOp::Is | BinaryOp::IsNot => unreachable!(),
         };
 
         self.builder.position_at_end(right_block);
         self.free_pointer(left_ptr, left_tid, env)?;
-        let (right_ptr, right_tid) = self.compile_expression(right, env)?;
-        if right_tid != BOOL_ID {
-            return Err(GenError::InvalidType);
+        let (mut right_ptr, right_tid) = self.compile_expression(right, env)?;
+
+        // Operands can only be returned directly if they share a type, otherwise return a bool
+        let ret_tid = if left_tid == right_tid {
+            left_tid
+        } else {
+            BOOL_ID
+        };
+
+        if right_tid != ret_tid {
+            let right_bool = self.build_truthy(right_ptr, right_tid, env)?;
+            right_ptr = self.build_struct(bool_type, vec![right_bool.into()])?;
         }
+        let right_end_block = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(continue_block)?;
+
+        self.builder.position_at_end(left_block);
+        let left_result = if left_tid != ret_tid {
+            self.free_pointer(left_ptr, left_tid, env)?;
+            self.build_struct(bool_type, vec![left_bool.into()])?
+        } else {
+            left_ptr
+        };
         self.builder.build_unconditional_branch(continue_block)?;
 
         self.builder.position_at_end(continue_block);
         let ret = self
             .builder
             .build_phi(self.ptr_type(), &format!("{}_result", op.to_string()))?;
-        ret.add_incoming(&[(&left_ptr, cur_block), (&right_ptr, right_block)]);
+        ret.add_incoming(&[(&left_result, left_block), (&right_ptr, right_end_block)]);
 
-        Ok((ret.as_basic_value().into_pointer_value(), BOOL_ID))
+        Ok((ret.as_basic_value().into_pointer_value(), ret_tid))
+    }
+
+    fn compile_identity(
+        &mut self,
+        left: &Expression,
+        op: &BinaryOp,
+        right: &Expression,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let (left_ptr, left_tid) = self.compile_expression(left, env)?;
+        let (right_ptr, right_tid) = self.compile_expression(right, env)?;
+
+        let predicate = match op {
+            BinaryOp::Is => IntPredicate::EQ,
+            BinaryOp::IsNot => IntPredicate::NE,
+            BinaryOp::And | BinaryOp::Or => unreachable!(),
+        };
+        let result = self.builder.build_int_compare(
+            predicate,
+            left_ptr,
+            right_ptr,
+            &format!("{}_result", op.to_string()),
+        )?;
+
+        self.free_pointer(left_ptr, left_tid, env)?;
+        self.free_pointer(right_ptr, right_tid, env)?;
+
+        let bool_type = BOOL_ID.get_from(env).ink();
+        let ptr = self.build_struct(bool_type, vec![result.into()])?;
+        Ok((ptr, BOOL_ID))
     }
 
     fn compile_binary_fn(
@@ -159,6 +794,118 @@ impl<'ctx> CodeGen<'ctx> {
         self.build_binary_fn(left_ptr, left_tid, op, right_ptr, right_tid, env)
     }
 
+    fn compile_comparison_chain(
+        &mut self,
+        first: &Expression,
+        chain: &[(BinaryFnOp, Expression)],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let cur_fn = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let continue_block = self.ctx.append_basic_block(cur_fn, "chain_continue");
+        let bool_type = BOOL_ID.get_from(env).ink();
+
+        let mut incoming = Vec::new();
+        let (mut left_ptr, mut left_tid) = self.compile_expression(first, env)?;
+
+        for (i, (op, right)) in chain.iter().enumerate() {
+            let is_last = i == chain.len() - 1;
+
+            // Middle operands are compared twice, so keep a reference for the next comparison
+            let (right_ptr, right_tid) = self.compile_expression(right, env)?;
+            if !is_last {
+                self.copy_pointer(right_ptr, right_tid, env)?;
+            }
+
+            let (result_ptr, result_tid) =
+                self.build_binary_fn(left_ptr, left_tid, op, right_ptr, right_tid, env)?;
+            if result_tid != BOOL_ID {
+                return Err(GenError::InvalidType);
+            }
+
+            if is_last {
+                incoming.push((result_ptr, self.builder.get_insert_block().unwrap()));
+                self.builder.build_unconditional_branch(continue_block)?;
+                break;
+            }
+
+            // Short circuit as soon as a comparison is false
+            let result_bool = self
+                .extract_primitive(result_ptr, bool_type)?
+                .into_int_value();
+            let next_block = self.ctx.append_basic_block(cur_fn, "chain_next");
+            let short_circuit_block = self.ctx.append_basic_block(cur_fn, "chain_short_circuit");
+            self.builder
+                .build_conditional_branch(result_bool, next_block, short_circuit_block)?;
+
+            self.builder.position_at_end(short_circuit_block);
+            self.free_pointer(right_ptr, right_tid, env)?;
+            self.builder.build_unconditional_branch(continue_block)?;
+            incoming.push((result_ptr, short_circuit_block));
+
+            self.builder.position_at_end(next_block);
+            self.free_pointer(result_ptr, result_tid, env)?;
+
+            (left_ptr, left_tid) = (right_ptr, right_tid);
+        }
+
+        self.builder.position_at_end(continue_block);
+        let ret = self.builder.build_phi(self.ptr_type(), "chain_result")?;
+        for (ptr, block) in &incoming {
+            ret.add_incoming(&[(ptr, *block)]);
+        }
+
+        Ok((ret.as_basic_value().into_pointer_value(), BOOL_ID))
+    }
+
+    fn compile_conditional(
+        &mut self,
+        condition: &Expression,
+        then_expr: &Expression,
+        else_expr: &Expression,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let (cond_ptr, cond_tid) = self.compile_expression(condition, env)?;
+        let cond_bool = self.build_truthy(cond_ptr, cond_tid, env)?;
+
+        let cur_fn = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let then_block = self.ctx.append_basic_block(cur_fn, "conditional_then");
+        let else_block = self.ctx.append_basic_block(cur_fn, "conditional_else");
+        let continue_block = self.ctx.append_basic_block(cur_fn, "conditional_continue");
+
+        self.builder
+            .build_conditional_branch(cond_bool, then_block, else_block)?;
+
+        self.builder.position_at_end(then_block);
+        let (then_ptr, then_tid) = self.compile_expression(then_expr, env)?;
+        let then_end_block = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(continue_block)?;
+
+        self.builder.position_at_end(else_block);
+        let (else_ptr, else_tid) = self.compile_expression(else_expr, env)?;
+        let else_end_block = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(continue_block)?;
+
+        let ret_tid = self.unify_types(then_tid, else_tid, env)?;
+
+        self.builder.position_at_end(continue_block);
+        let ret = self
+            .builder
+            .build_phi(self.ptr_type(), "conditional_result")?;
+        ret.add_incoming(&[(&then_ptr, then_end_block), (&else_ptr, else_end_block)]);
+
+        Ok((ret.as_basic_value().into_pointer_value(), ret_tid))
+    }
+
     pub fn build_binary_fn(
         &mut self,
         left_ptr: PointerValue<'ctx>,
@@ -168,10 +915,42 @@ impl<'ctx> CodeGen<'ctx> {
         right_tid: TypeId,
         env: &mut Environment<'ctx>,
     ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
-        let op_func_name = op.fn_name();
-        let op_func_id = env.find_func(op_func_name, Some(left_tid), &[left_tid, right_tid])?;
+        // Reflected operators are implemented by the right operand, such as `__contains__` for `in`
+        let (owner_ptr, owner_tid, arg_ptr, arg_tid) = if op.is_reflected() {
+            (right_ptr, right_tid, left_ptr, left_tid)
+        } else {
+            (left_ptr, left_tid, right_ptr, right_tid)
+        };
+
+        let arg_tids = [owner_tid, arg_tid];
+        let (op_func_id, negate) = match (
+            self.find_method(op.fn_name(), owner_tid, &arg_tids, env),
+            op,
+        ) {
+            (Ok(fn_id), BinaryFnOp::NotIn) => (fn_id, true),
+            (Ok(fn_id), _) => (fn_id, false),
+            // Like Python, `!=` is the negation of `==` for types without `__ne__`
+            (Err(_), BinaryFnOp::NotEqual) => {
+                let eq_fn = BinaryFnOp::Equal.fn_name();
+                (self.find_method(eq_fn, owner_tid, &arg_tids, env)?, true)
+            }
+            (Err(err), _) => return Err(err),
+        };
+
+        // Operands may have called functions that moved the current line
+        self.build_set_line_for(op_func_id, env)?;
 
-        self.call_func(op_func_id, &[left_ptr, right_ptr], env)
+        let (result_ptr, result_tid) = self.call_func(op_func_id, &[owner_ptr, arg_ptr], env)?;
+
+        if negate {
+            let result = self.build_truthy(result_ptr, result_tid, env)?;
+            let negated = self.builder.build_not(result, "negated")?;
+            let bool_type = BOOL_ID.get_from(env).ink();
+            let ptr = self.build_struct(bool_type, vec![negated.into()])?;
+            return Ok((ptr, BOOL_ID));
+        }
+
+        Ok((result_ptr, result_tid))
     }
 
     fn compile_unary(
@@ -182,15 +961,8 @@ impl<'ctx> CodeGen<'ctx> {
     ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
         let (expr_ptr, expr_tid) = self.compile_expression(expr, env)?;
 
-        if expr_tid != BOOL_ID {
-            return Err(GenError::InvalidType);
-        }
-
         let bool_type = BOOL_ID.get_from(env).ink();
-        let expr_bool = self
-            .extract_primitive(expr_ptr, bool_type)?
-            .into_int_value();
-        self.free_pointer(expr_ptr, expr_tid, env)?;
+        let expr_bool = self.build_truthy(expr_ptr, expr_tid, env)?;
 
         match op {
             UnaryOp::Not => {
@@ -211,13 +983,81 @@ impl<'ctx> CodeGen<'ctx> {
     ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
         let (expr_ptr, expr_tid) = self.compile_expression(expr, env)?;
 
-        let op_func_id = env.find_func(op.fn_name(), Some(expr_tid), &[expr_tid])?;
+        let op_func_id = self.find_method(op.fn_name(), expr_tid, &[expr_tid], env)?;
+
+        self.build_set_line_for(op_func_id, env)?;
 
         let ret = self.call_func(op_func_id, &[expr_ptr], env)?;
 
         Ok(ret)
     }
 
+    /// Converts a value to an LLVM bool using its `__bool__` function, consuming the value.
+    ///
+    /// `None` is falsy, so optional values are only truthy if they hold a truthy value.
+    pub(super) fn build_truthy(
+        &mut self,
+        ptr: PointerValue<'ctx>,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        if let Some(inner) = env.get_type(tid).optional_inner() {
+            return self.build_optional_truthy(ptr, inner, env);
+        }
+
+        let (bool_ptr, bool_tid) = if tid == BOOL_ID {
+            (ptr, tid)
+        } else {
+            let to_bool_fn = self
+                .find_method(TO_BOOL_FN, tid, &[tid], env)
+                .map_err(|_| GenError::InvalidType)?;
+            self.call_func(to_bool_fn, &[ptr], env)?
+        };
+
+        if bool_tid != BOOL_ID {
+            return Err(GenError::InvalidType);
+        }
+
+        let bool_val = self
+            .extract_primitive(bool_ptr, BOOL_ID.get_from(env).ink())?
+            .into_int_value();
+        self.free_pointer(bool_ptr, bool_tid, env)?;
+
+        Ok(bool_val)
+    }
+
+    fn build_optional_truthy(
+        &mut self,
+        ptr: PointerValue<'ctx>,
+        inner: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<IntValue<'ctx>, GenError> {
+        let cur_block = self.builder.get_insert_block().unwrap();
+        let cur_fn = cur_block.get_parent().unwrap();
+        let some_block = self.ctx.append_basic_block(cur_fn, "truthy_some");
+        let continue_block = self.ctx.append_basic_block(cur_fn, "truthy_continue");
+
+        let is_none = self.builder.build_is_null(ptr, "is_none")?;
+        self.builder
+            .build_conditional_branch(is_none, continue_block, some_block)?;
+
+        self.builder.position_at_end(some_block);
+        let some_bool = self.build_truthy(ptr, inner, env)?;
+        let some_end_block = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(continue_block)?;
+
+        self.builder.position_at_end(continue_block);
+        let ret = self
+            .builder
+            .build_phi(self.ctx.bool_type(), "optional_truthy")?;
+        ret.add_incoming(&[
+            (&self.ctx.bool_type().const_zero(), cur_block),
+            (&some_bool, some_end_block),
+        ]);
+
+        Ok(ret.as_basic_value().into_int_value())
+    }
+
     pub(super) fn compile_primary(
         &mut self,
         primary: &Primary,
@@ -225,7 +1065,20 @@ impl<'ctx> CodeGen<'ctx> {
     ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
         match primary {
             Primary::Identifier(ident) => {
-                let (var_ptr, tid) = env.get_var(ident)?;
+                let (var_ptr, tid) = match env.get_var(ident) {
+                    Ok(var) => var,
+                    // Variables can shadow builtin constants like `stderr` and functions
+                    Err(GenError::VariableNotFound) => {
+                        if let Some(file) = self.build_std_file(ident, env)? {
+                            return Ok(file);
+                        }
+                        if env.has_func(None, ident) {
+                            return self.build_fn_ref(ident, None, None, env);
+                        }
+                        return Err(GenError::VariableNotFound);
+                    }
+                    Err(err) => return Err(err),
+                };
                 let expr_ptr = self
                     .builder
                     .build_load(
@@ -260,7 +1113,10 @@ impl<'ctx> CodeGen<'ctx> {
                 let ptr = self.build_struct(bool_type.ink(), vec![inner_bool.into()])?;
                 Ok((ptr, BOOL_ID))
             }
-            Primary::None => todo!(),
+            Primary::None => {
+                let ptr = self.build_none(env)?;
+                Ok((ptr, NONE_ID))
+            }
             Primary::Grouping(expr) => self.compile_expression(expr, env),
         }
     }
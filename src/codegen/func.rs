@@ -1,6 +1,6 @@
 use inkwell::{values::FunctionValue, AddressSpace};
 
-use crate::parser::{Expression, FuncDeclaration};
+use crate::parser::{Expression, FuncDeclaration, FuncParameter};
 
 use super::{
     env::{id::TypeId, Environment},
@@ -8,7 +8,7 @@ use super::{
     CodeGen,
 };
 
-struct ParamInfo<'a>(&'a str, TypeId, bool);
+struct ParamInfo<'a>(&'a str, TypeId);
 
 impl<'ctx> CodeGen<'ctx> {
     pub(super) fn preprocess_fn(
@@ -18,9 +18,13 @@ impl<'ctx> CodeGen<'ctx> {
         env: &mut Environment<'ctx>,
     ) -> Result<(), GenError> {
         let mut param_types = Vec::new();
+        let mut param_idents = Vec::new();
+        let mut defaults = Vec::new();
         if fn_dec.takes_self {
             if let Some(owner) = owner {
                 param_types.push(owner);
+                param_idents.push("self".to_string());
+                defaults.push(None);
             } else {
                 return Err(GenError::InvalidFunctionDefinition);
             }
@@ -28,26 +32,21 @@ impl<'ctx> CodeGen<'ctx> {
 
         let mut is_var_args = false;
         for param in &fn_dec.params {
-            if is_var_args {
+            // `*args` has to be the last parameter and cannot have a default
+            if is_var_args || (param.var_args && param.default.is_some()) {
                 return Err(GenError::InvalidFunctionDefinition);
             }
 
-            if param.var_args {
-                is_var_args = true;
-            } else {
-                let param_type = env.find_type(&param.type_identifier)?;
-                param_types.push(param_type);
-            }
+            is_var_args = param.var_args;
+            param_types.push(self.resolve_param_type(param, env)?);
+            param_idents.push(param.identifier.clone());
+            defaults.push(param.default.clone());
         }
 
-        let ret_type = env.find_type(&fn_dec.return_identifier)?;
-        env.create_func(
-            owner,
-            &fn_dec.identifier,
-            &param_types,
-            ret_type,
-            is_var_args,
-        )?;
+        let ret_type = self.resolve_type(&fn_dec.return_identifier, env)?;
+        let (_, fn_id) =
+            env.create_func(owner, &fn_dec.identifier, &param_types, ret_type, false)?;
+        env.set_fn_params(fn_id, param_idents, defaults, is_var_args);
 
         Ok(()) // TODO: Make a preprocessed statement enum?
     }
@@ -61,21 +60,12 @@ impl<'ctx> CodeGen<'ctx> {
         let mut param_info = fn_dec
             .params
             .iter()
-            .map(|p| {
-                Ok(ParamInfo(
-                    &p.identifier,
-                    env.find_type(&p.type_identifier)?,
-                    p.var_args,
-                ))
-            })
+            .map(|p| Ok(ParamInfo(&p.identifier, self.resolve_param_type(p, env)?)))
             .collect::<Result<Vec<_>, GenError>>()?;
 
-        if let Some(owner) = owner {
-            if fn_dec.takes_self {
-                param_info.insert(0, ParamInfo("self", owner, false));
-            } else {
-                return Err(GenError::InvalidFunctionDefinition);
-            }
+        // Static methods are owned by their struct but take no `self`
+        if let Some(owner) = owner.filter(|_| fn_dec.takes_self) {
+            param_info.insert(0, ParamInfo("self", owner));
         }
 
         let fn_id = env.find_func(
@@ -108,10 +98,10 @@ impl<'ctx> CodeGen<'ctx> {
         expr: &Expression,
         env: &mut Environment<'ctx>,
     ) -> Result<(), GenError> {
-        let (expr_ptr, expr_tid) = self.compile_expression(expr, env)?;
-        let fn_def = env.get_cur_fn();
+        let ret_tid = env.get_cur_fn().ret_type;
+        let (expr_ptr, expr_tid) = self.compile_hinted_expression(expr, ret_tid, env)?;
 
-        if fn_def.ret_type != expr_tid {
+        if !env.can_coerce(expr_tid, ret_tid) {
             return Err(GenError::InvalidType);
         }
 
@@ -134,20 +124,30 @@ impl<'ctx> CodeGen<'ctx> {
         let ptr_type = self.ctx.ptr_type(AddressSpace::default());
         Ok(for i in 0..params.len() {
             let param = &params[i];
-            if !param.2 {
-                let param_var = self
-                    .builder
-                    .build_alloca(ptr_type, &(param.0.to_owned() + "_var"))?;
+            let param_var = self
+                .builder
+                .build_alloca(ptr_type, &(param.0.to_owned() + "_var"))?;
 
-                self.builder.build_store(
-                    param_var,
-                    fn_val.get_nth_param(i as u32).unwrap().into_pointer_value(),
-                )?;
+            self.builder.build_store(
+                param_var,
+                fn_val.get_nth_param(i as u32).unwrap().into_pointer_value(),
+            )?;
 
-                env.insert_var(param.0.to_string(), param_var, param.1);
-            } else {
-                todo!("Figure out how to implement variable length parameters")
-            }
+            env.insert_var(param.0.to_string(), param_var, param.1);
         })
     }
+
+    /// Resolves the type of a parameter, where `*args: T` is a `list[T]` holding the arguments
+    /// it collects.
+    pub(super) fn resolve_param_type(
+        &mut self,
+        param: &FuncParameter,
+        env: &mut Environment<'ctx>,
+    ) -> Result<TypeId, GenError> {
+        let tid = self.resolve_type(&param.type_identifier, env)?;
+        match param.var_args {
+            true => self.list_of(tid, env),
+            false => Ok(tid),
+        }
+    }
 }
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+
+use crate::parser::{Expression, Primary, TypeExpr};
+
+use super::{
+    builtin::{dict::DICT_NAME, list::LIST_NAME, set::SET_NAME, tuple::TUPLE_NAME},
+    env::{
+        fn_def::ArgSource,
+        id::{FunctionId, TypeId, NONE_ID},
+        template::Template,
+        type_def::TypeInfo,
+        Environment,
+    },
+    err::GenError,
+    CodeGen,
+};
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Returns the instance of a generic struct for the given type arguments, compiling it the
+    /// first time it is used.
+    pub(super) fn instantiate_struct(
+        &mut self,
+        ident: &str,
+        type_args: &[TypeId],
+        env: &mut Environment<'ctx>,
+    ) -> Result<TypeId, GenError> {
+        let template = env.find_template(ident).ok_or(GenError::TypeNotFound)?;
+        let Template::Struct {
+            type_params,
+            fields,
+            consts,
+            fns,
+            protocols,
+            derives,
+        } = template.as_ref()
+        else {
+            return Err(GenError::TypeNotFound);
+        };
+
+        let instance_ident = Self::instance_ident(ident, type_args, env);
+        if let Ok(tid) = env.find_type(&instance_ident) {
+            return Ok(tid);
+        }
+
+        self.instantiate(&instance_ident, type_params, type_args, env, |gen, env| {
+            gen.preprocess_struct_definition(&instance_ident, fields, consts, fns, derives, env)?;
+            let tid = env.find_type(&instance_ident)?;
+            env.register_instance(tid, ident, type_args);
+            gen.compile_struct_definition(&instance_ident, fields, fns, protocols, env)
+        })?;
+
+        env.find_type(&instance_ident)
+    }
+
+    /// Returns the name of the instance of a generic function for the given type arguments,
+    /// compiling it the first time it is used.
+    fn instantiate_fn(
+        &mut self,
+        ident: &str,
+        type_args: &[TypeId],
+        env: &mut Environment<'ctx>,
+    ) -> Result<String, GenError> {
+        let template = env.find_template(ident).ok_or(GenError::FunctionNotFound)?;
+        let Template::Func { fn_dec, bounds } = template.as_ref() else {
+            return Err(GenError::FunctionNotFound);
+        };
+
+        let instance_ident = Self::instance_ident(ident, type_args, env);
+        if env.has_func(None, &instance_ident) {
+            return Ok(instance_ident);
+        }
+
+        for (type_param, protocol) in bounds {
+            let position = fn_dec
+                .type_params
+                .iter()
+                .position(|param| param == type_param);
+            if let Some(&type_arg) = position.and_then(|i| type_args.get(i)) {
+                self.check_conformance(type_arg, protocol, env)?;
+            }
+        }
+
+        self.instantiate(
+            &instance_ident,
+            &fn_dec.type_params,
+            type_args,
+            env,
+            |gen, env| {
+                let mut instance = fn_dec.clone();
+                instance.identifier = instance_ident.clone();
+                instance.type_params = Vec::new();
+
+                gen.preprocess_fn(None, &instance, env)?;
+                gen.compile_fn(None, &instance, env)
+            },
+        )?;
+
+        Ok(instance_ident)
+    }
+
+    /// Compiles a template with its type parameters bound to the type arguments, reporting any
+    /// error at the line that used the instance.
+    fn instantiate(
+        &mut self,
+        instance_ident: &str,
+        type_params: &[String],
+        type_args: &[TypeId],
+        env: &mut Environment<'ctx>,
+        compile: impl FnOnce(&mut Self, &mut Environment<'ctx>) -> Result<(), GenError>,
+    ) -> Result<(), GenError> {
+        if type_params.len() != type_args.len() {
+            return Err(GenError::InvalidType);
+        }
+
+        let bindings = type_params.iter().cloned().zip(type_args.iter().copied());
+        let prev_type_args = env.set_type_args(bindings.collect());
+        let line = self.line;
+
+        let result = compile(self, env);
+
+        env.set_type_args(prev_type_args);
+        self.line = line;
+
+        result.map_err(|err| GenError::Instantiation {
+            instance: instance_ident.to_string(),
+            line,
+            source: Box::new(err),
+        })
+    }
+
+    fn instance_ident(ident: &str, type_args: &[TypeId], env: &Environment<'ctx>) -> String {
+        let arg_idents: Vec<_> = type_args
+            .iter()
+            .map(|tid| env.type_id_ident(*tid))
+            .collect();
+        format!("{}[{}]", ident, arg_idents.join(", "))
+    }
+
+    /// Finds the function called by name, instantiating a generic function or struct constructor
+    /// with type arguments inferred from the argument types. Parameters left out of the call
+    /// take no part in the inference.
+    pub(super) fn resolve_call(
+        &mut self,
+        ident: &str,
+        arg_tids: &[TypeId],
+        kwarg_tids: &[(String, TypeId)],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(FunctionId, Vec<ArgSource>), GenError> {
+        let Some(template) = env.find_template(ident) else {
+            return env.resolve_func_call(ident, None, arg_tids, kwarg_tids);
+        };
+
+        // Positional arguments left over are matched against the element type of `*args`
+        let params = template.params();
+        let var_args = template.has_var_args().then(|| params.last().unwrap().1);
+        let mut matched = Vec::new();
+        for (i, arg_tid) in arg_tids.iter().enumerate() {
+            let param_type = params
+                .get(i)
+                .map(|(_, param_type)| *param_type)
+                .or(var_args)
+                .ok_or(GenError::FunctionNotFound)?;
+            matched.push((param_type, *arg_tid));
+        }
+        for (kw, arg_tid) in kwarg_tids {
+            if let Some((_, param_type)) = params.iter().find(|(ident, _)| ident == kw) {
+                matched.push((param_type, *arg_tid));
+            }
+        }
+
+        let mut bindings = HashMap::new();
+        for (param_type, arg_tid) in matched {
+            self.infer_type_args(
+                param_type,
+                arg_tid,
+                template.type_params(),
+                &mut bindings,
+                env,
+            )?;
+        }
+
+        let type_args = template
+            .type_params()
+            .iter()
+            .map(|param| bindings.get(param).copied())
+            .collect::<Option<Vec<_>>>()
+            .ok_or(GenError::CannotInferType)?;
+
+        self.resolve_instance_call(ident, &type_args, arg_tids, kwarg_tids, env)
+    }
+
+    /// Finds the function called by a generic function or struct with explicit type arguments,
+    /// such as `Stack[int]()`.
+    pub(super) fn resolve_explicit_call(
+        &mut self,
+        ident: &str,
+        type_args: &Expression,
+        arg_tids: &[TypeId],
+        kwarg_tids: &[(String, TypeId)],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(FunctionId, Vec<ArgSource>), GenError> {
+        let type_args = match type_args {
+            Expression::Tuple(items) => items.iter().collect(),
+            type_arg => vec![type_arg],
+        };
+
+        let type_args = type_args
+            .into_iter()
+            .map(|type_arg| {
+                let type_expr = Self::type_expr_of(type_arg)?;
+                self.resolve_type(&type_expr, env)
+            })
+            .collect::<Result<Vec<_>, GenError>>()?;
+
+        self.resolve_instance_call(ident, &type_args, arg_tids, kwarg_tids, env)
+    }
+
+    fn resolve_instance_call(
+        &mut self,
+        ident: &str,
+        type_args: &[TypeId],
+        arg_tids: &[TypeId],
+        kwarg_tids: &[(String, TypeId)],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(FunctionId, Vec<ArgSource>), GenError> {
+        let template = env.find_template(ident).ok_or(GenError::FunctionNotFound)?;
+
+        let instance_ident = match template.as_ref() {
+            Template::Struct { .. } => {
+                let tid = self.instantiate_struct(ident, type_args, env)?;
+                env.type_id_ident(tid).to_string()
+            }
+            Template::Func { .. } => self.instantiate_fn(ident, type_args, env)?,
+        };
+
+        env.resolve_func_call(&instance_ident, None, arg_tids, kwarg_tids)
+    }
+
+    /// Binds the type parameters that appear in a parameter type by matching it against the type
+    /// of an argument. Mismatches other than conflicting bindings are left for the call to report.
+    fn infer_type_args(
+        &mut self,
+        param_type: &TypeExpr,
+        arg_tid: TypeId,
+        type_params: &[String],
+        bindings: &mut HashMap<String, TypeId>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        match param_type {
+            TypeExpr::Named(ident) if type_params.contains(ident) => {
+                let tid = match bindings.get(ident) {
+                    Some(bound) => self.unify_types(*bound, arg_tid, env)?,
+                    None => arg_tid,
+                };
+                bindings.insert(ident.clone(), tid);
+                Ok(())
+            }
+            TypeExpr::Named(_) => Ok(()),
+            TypeExpr::Optional(inner) => match env.get_type(arg_tid).optional_inner() {
+                Some(arg_inner) => {
+                    self.infer_type_args(inner, arg_inner, type_params, bindings, env)
+                }
+                None if arg_tid == NONE_ID => Ok(()),
+                None => self.infer_type_args(inner, arg_tid, type_params, bindings, env),
+            },
+            TypeExpr::Generic(ident, params) => {
+                let arg_elems = match (ident.as_str(), env.get_type(arg_tid).info()) {
+                    (LIST_NAME, TypeInfo::List(elem)) | (SET_NAME, TypeInfo::Set(elem)) => {
+                        vec![elem]
+                    }
+                    (DICT_NAME, TypeInfo::Dict(key, value)) => vec![key, value],
+                    (TUPLE_NAME, TypeInfo::Tuple) => self.tuple_elems(arg_tid, env),
+                    _ => match env.instance_of(arg_tid) {
+                        Some((template, type_args)) if template == ident => type_args.clone(),
+                        _ => return Ok(()),
+                    },
+                };
+
+                if arg_elems.len() != params.len() {
+                    return Ok(());
+                }
+
+                for (param, arg_elem) in params.iter().zip(arg_elems) {
+                    self.infer_type_args(param, arg_elem, type_params, bindings, env)?;
+                }
+                Ok(())
+            }
+            TypeExpr::Func(params, ret) => {
+                let Some((arg_params, arg_ret)) = env.fn_signature(arg_tid).cloned() else {
+                    return Ok(());
+                };
+                if arg_params.len() != params.len() {
+                    return Ok(());
+                }
+
+                for (param, arg_param) in params.iter().zip(arg_params) {
+                    self.infer_type_args(param, arg_param, type_params, bindings, env)?;
+                }
+                self.infer_type_args(ret, arg_ret, type_params, bindings, env)
+            }
+        }
+    }
+
+    /// Reads a type argument written in an expression, such as the `int` in `Stack[int]()`.
+    pub(super) fn type_expr_of(expr: &Expression) -> Result<TypeExpr, GenError> {
+        match expr {
+            Expression::Primary(Primary::Identifier(ident)) => Ok(TypeExpr::Named(ident.clone())),
+            Expression::Index(generic, args) => {
+                let Expression::Primary(Primary::Identifier(ident)) = generic.as_ref() else {
+                    return Err(GenError::InvalidType);
+                };
+
+                let mut args = match args.as_ref() {
+                    Expression::Tuple(items) => items
+                        .iter()
+                        .map(Self::type_expr_of)
+                        .collect::<Result<Vec<_>, GenError>>()?,
+                    arg => vec![Self::type_expr_of(arg)?],
+                };
+
+                if ident == "Optional" {
+                    if args.len() != 1 {
+                        return Err(GenError::InvalidType);
+                    }
+                    Ok(TypeExpr::Optional(Box::new(args.pop().unwrap())))
+                } else {
+                    Ok(TypeExpr::Generic(ident.clone(), args))
+                }
+            }
+            _ => Err(GenError::InvalidType),
+        }
+    }
+}
@@ -1,33 +1,81 @@
-use env::{id::INT_ID, Environment};
+use std::collections::{HashMap, HashSet};
+
+use builtin::{llvm_resources::LLVMResources, DEL_ITEM_FN, GET_ITEM_FN, SET_ITEM_FN};
+use env::{
+    id::{TypeId, INT_ID, NONE_ID},
+    template::Template,
+    type_def::TypeInfo,
+    Environment,
+};
 use err::GenError;
-use inkwell::{builder::Builder, context::Context, module::Module, AddressSpace};
+use inkwell::{
+    builder::Builder,
+    context::Context,
+    module::Module,
+    values::{FunctionValue, PointerValue},
+    AddressSpace,
+};
+use protocol::{fn_template, is_generic_fn};
 
 pub mod builtin;
+pub mod class;
+pub mod closure;
 pub mod control;
+pub mod enums;
 pub mod env;
 pub mod err;
 pub mod expr;
 pub mod func;
+pub mod generics;
 pub mod ink_extension;
+pub mod namespace;
+pub mod pattern;
+pub mod protocol;
 pub mod structs;
+pub mod types;
 pub mod util;
 
-use crate::parser::{LValue, Primary, Statement};
+use crate::parser::{Expression, LValue, Primary, Statement, StatementKind};
+
+/// Options that change how code is generated.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CodeGenOptions {
+    /// Lowers int arithmetic to LLVM's overflow intrinsics and aborts on overflow
+    /// instead of silently wrapping.
+    pub checked_overflow: bool,
+}
 
 // TODO: Not pub
 pub struct CodeGen<'ctx> {
     pub ctx: &'ctx Context,
     pub builder: Builder<'ctx>,
+    pub options: CodeGenOptions,
+    res: Option<LLVMResources<'ctx>>,
+    /// The source line of the statement being compiled.
+    line: usize,
+    /// The functions that can abort with a runtime error, which need the current line to be
+    /// recorded before they are called.
+    fallible_fns: HashSet<FunctionValue<'ctx>>,
 }
 
 impl<'ctx> CodeGen<'ctx> {
-    pub fn new(ctx: &'ctx Context) -> Self {
+    pub fn new(ctx: &'ctx Context, options: CodeGenOptions) -> Self {
         Self {
             ctx,
             builder: ctx.create_builder(),
+            options,
+            res: None,
+            line: 0,
+            fallible_fns: HashSet::new(),
         }
     }
 
+    /// Returns the LLVM resources created while setting up the builtins.
+    pub(super) fn res(&self) -> LLVMResources<'ctx> {
+        self.res
+            .expect("LLVM resources are set up before any code is compiled")
+    }
+
     pub fn gen_code_for(&mut self, program: Vec<Statement>) -> Module<'ctx> {
         let module = self.ctx.create_module("main");
 
@@ -77,12 +125,12 @@ impl<'ctx> CodeGen<'ctx> {
         env: &mut Environment<'ctx>,
     ) -> Result<(), GenError> {
         env.push_scope();
-        for statement in statements {
-            self.preprocess_statement(&statement, env).unwrap();
+        for statement in preprocess_order(statements)? {
+            self.preprocess_statement(statement, env)?;
         }
 
         for statement in statements {
-            self.compile_statement(&statement, env).unwrap();
+            self.compile_statement(&statement, env)?;
         }
 
         let prev_scope = env.pop_scope().unwrap();
@@ -105,13 +153,56 @@ impl<'ctx> CodeGen<'ctx> {
         statement: &Statement,
         env: &mut env::Environment<'ctx>,
     ) -> Result<(), GenError> {
-        match statement {
-            Statement::FunctionDeclaration(fn_dec) => self.preprocess_fn(None, fn_dec, env),
-            Statement::StructDefinition {
+        match &statement.kind {
+            // Generic functions and structs are only compiled once they are used with type arguments
+            StatementKind::FunctionDeclaration(fn_dec) if is_generic_fn(fn_dec, env) => {
+                env.register_template(&fn_dec.identifier, fn_template(fn_dec, env))
+            }
+            // Functions declared inside other functions are closures, created where they appear
+            StatementKind::FunctionDeclaration(_) if !env.func.is_script => Ok(()),
+            StatementKind::FunctionDeclaration(fn_dec) => self.preprocess_fn(None, fn_dec, env),
+            StatementKind::StructDefinition {
+                identifier,
+                type_params,
+                fields,
+                consts,
+                fns,
+                protocols,
+                derives,
+            } if !type_params.is_empty() => env.register_template(
+                identifier,
+                Template::Struct {
+                    type_params: type_params.clone(),
+                    fields: fields.clone(),
+                    consts: consts.clone(),
+                    fns: fns.clone(),
+                    protocols: protocols.clone(),
+                    derives: derives.clone(),
+                },
+            ),
+            StatementKind::StructDefinition {
                 identifier,
                 fields,
+                consts,
                 fns,
-            } => self.preprocess_struct_definition(identifier, fields, fns, env),
+                derives,
+                ..
+            } => self.preprocess_struct_definition(identifier, fields, consts, fns, derives, env),
+            StatementKind::ClassDefinition {
+                identifier,
+                bases,
+                fields,
+                consts,
+                fns,
+            } => self.preprocess_class_definition(identifier, bases, fields, consts, fns, env),
+            StatementKind::ProtocolDefinition {
+                identifier,
+                methods,
+            } => env.register_protocol(identifier, methods.clone()),
+            StatementKind::EnumDefinition {
+                identifier,
+                variants,
+            } => self.preprocess_enum_definition(identifier, variants, env),
             _ => Ok(()),
         }
     }
@@ -121,24 +212,80 @@ impl<'ctx> CodeGen<'ctx> {
         statement: &Statement,
         env: &mut env::Environment<'ctx>,
     ) -> Result<(), GenError> {
-        match statement {
-            Statement::Declaration {
+        self.line = statement.pos.line() + 1;
+
+        match &statement.kind {
+            StatementKind::Declaration {
                 identifier,
                 type_identifier,
                 expression,
             } => {
-                let var_tid = env.find_type(type_identifier)?;
+                let var_tid = self.resolve_type(type_identifier, env)?;
+                let (expr_ptr, expr_tid) =
+                    self.compile_hinted_expression(expression, var_tid, env)?;
+
+                if !env.can_coerce(expr_tid, var_tid) {
+                    return Err(GenError::InvalidType);
+                }
+
+                self.declare_var(identifier, expr_ptr, var_tid, env)?;
+            }
+            StatementKind::Assignment {
+                lvalue: LValue::Ident(identifier),
+                op,
+                expression,
+            } if op.to_binary_op().is_none() && env.get_var(identifier).is_err() => {
+                // The first assignment to a variable declares it, using the type of the expression
                 let (expr_ptr, expr_tid) = self.compile_expression(expression, env)?;
 
-                assert_eq!(var_tid, expr_tid); // TODO: Gen Error
+                if expr_tid == NONE_ID {
+                    return Err(GenError::CannotInferType);
+                }
 
-                let ptr_type = self.ctx.ptr_type(AddressSpace::default());
-                let var_ptr = self.builder.build_alloca(ptr_type, identifier)?;
-                self.builder.build_store(var_ptr, expr_ptr)?; // Store the expression pointer in the variable.
+                self.declare_var(identifier, expr_ptr, expr_tid, env)?;
+            }
+            StatementKind::Assignment {
+                lvalue: LValue::Index(expr, index),
+                op,
+                expression,
+            } => {
+                let (expr_ptr, expr_tid) = self.compile_expression(expr, env)?;
+                let (index_ptr, index_tid) = self.compile_expression(index, env)?;
+
+                let (value_ptr, value_tid) = match op.to_binary_op() {
+                    // `xs[i] += v` reads the item first, so the container and index are used twice
+                    Some(op) => {
+                        self.copy_pointer(expr_ptr, expr_tid, env)?;
+                        self.copy_pointer(index_ptr, index_tid, env)?;
+
+                        let get_item_fn =
+                            self.find_method(GET_ITEM_FN, expr_tid, &[expr_tid, index_tid], env)?;
+                        self.build_set_line_for(get_item_fn, env)?;
+                        let (item_ptr, item_tid) =
+                            self.call_func(get_item_fn, &[expr_ptr, index_ptr], env)?;
+
+                        let (rhs_ptr, rhs_tid) =
+                            self.compile_hinted_expression(expression, item_tid, env)?;
+                        self.build_binary_fn(item_ptr, item_tid, &op, rhs_ptr, rhs_tid, env)?
+                    }
+                    None => self.compile_expression(expression, env)?,
+                };
+
+                let set_item_fn = self.find_method(
+                    SET_ITEM_FN,
+                    expr_tid,
+                    &[expr_tid, index_tid, value_tid],
+                    env,
+                )?;
 
-                env.insert_var(identifier.clone(), var_ptr, expr_tid);
+                // Operands may have called functions that moved the current line
+                self.build_set_line_for(set_item_fn, env)?;
+
+                let (ptr, ptr_tid) =
+                    self.call_func(set_item_fn, &[expr_ptr, index_ptr, value_ptr], env)?;
+                self.free_pointer(ptr, ptr_tid, env)?;
             }
-            Statement::Assignment {
+            StatementKind::Assignment {
                 lvalue,
                 op,
                 expression,
@@ -149,6 +296,7 @@ impl<'ctx> CodeGen<'ctx> {
                         let (expr_ptr, expr_tid) = self.compile_expression(expr, env)?;
                         self.build_gep_field(expr_ptr, expr_tid, ident, env)?
                     }
+                    LValue::Index(..) => unreachable!(),
                 };
                 let var_val_ptr = self
                     .builder
@@ -159,7 +307,8 @@ impl<'ctx> CodeGen<'ctx> {
                     )?
                     .into_pointer_value();
 
-                let (expr_ptr, expr_tid) = self.compile_expression(expression, env)?;
+                let (expr_ptr, expr_tid) =
+                    self.compile_hinted_expression(expression, var_tid, env)?;
                 let assign_op = op.to_binary_op();
 
                 let (expr_ptr, expr_tid) = match assign_op {
@@ -178,28 +327,55 @@ impl<'ctx> CodeGen<'ctx> {
                     None => (expr_ptr, expr_tid),
                 };
 
-                self.free_pointer(var_val_ptr, var_tid, env)?;
+                // Fields are null until `__init__` sets them
+                match lvalue {
+                    LValue::Access(..) => self.free_pointer_if_set(var_val_ptr, var_tid, env)?,
+                    _ => self.free_pointer(var_val_ptr, var_tid, env)?,
+                }
 
-                assert_eq!(var_tid, expr_tid); // TODO: Gen Error
+                if !env.can_coerce(expr_tid, var_tid) {
+                    return Err(GenError::InvalidType);
+                }
 
                 self.builder.build_store(var_ptr, expr_ptr)?;
             }
-            Statement::FunctionDeclaration(fn_dec) => {
+            StatementKind::Destructure {
+                identifiers,
+                expression,
+            } => self.compile_destructure(identifiers, expression, env)?,
+            StatementKind::FunctionDeclaration(fn_dec) if !env.func.is_script => {
+                self.compile_nested_fn(fn_dec, env)?;
+            }
+            StatementKind::FunctionDeclaration(fn_dec) if is_generic_fn(fn_dec, env) => {}
+            StatementKind::FunctionDeclaration(fn_dec) => {
                 self.compile_fn(None, fn_dec, env)?;
             }
-            Statement::ExternFunctionDeclaration {
+            StatementKind::ExternFunctionDeclaration {
                 identifier: _,
                 parameters: _,
                 return_identifier: _,
             } => todo!(),
-            Statement::ReturnStatement { expression: expr } => {
+            StatementKind::ReturnStatement { expression: expr } => {
                 self.compile_return(expr, env)?;
             }
-            Statement::Expression(expression) => {
+            StatementKind::Delete { expression, index } => {
+                let (expr_ptr, expr_tid) = self.compile_expression(expression, env)?;
+                let (index_ptr, index_tid) = self.compile_expression(index, env)?;
+
+                let del_item_fn =
+                    self.find_method(DEL_ITEM_FN, expr_tid, &[expr_tid, index_tid], env)?;
+
+                // Operands may have called functions that moved the current line
+                self.build_set_line_for(del_item_fn, env)?;
+
+                let (ptr, ptr_tid) = self.call_func(del_item_fn, &[expr_ptr, index_ptr], env)?;
+                self.free_pointer(ptr, ptr_tid, env)?;
+            }
+            StatementKind::Expression(expression) => {
                 let (ptr, ptr_tid) = self.compile_expression(expression, env)?;
                 self.free_pointer(ptr, ptr_tid, env)?;
             }
-            Statement::IfStatement {
+            StatementKind::IfStatement {
                 condition,
                 then_branch,
                 else_if_branches,
@@ -211,18 +387,159 @@ impl<'ctx> CodeGen<'ctx> {
                 else_branch,
                 env,
             )?,
-            Statement::StructDefinition {
+            StatementKind::StructDefinition { type_params, .. } if !type_params.is_empty() => {}
+            StatementKind::StructDefinition {
                 identifier,
                 fields,
                 fns,
+                protocols,
+                ..
             } => {
-                self.compile_struct_definition(identifier, fields, fns, env)?;
+                self.compile_struct_definition(identifier, fields, fns, protocols, env)?;
             }
-            Statement::WhileLoop { condition, block } => {
+            StatementKind::ClassDefinition {
+                identifier,
+                bases,
+                fns,
+                ..
+            } => self.compile_class_definition(identifier, bases, fns, env)?,
+            StatementKind::ProtocolDefinition { .. } => {}
+            StatementKind::EnumDefinition {
+                identifier,
+                variants,
+            } => self.compile_enum_definition(identifier, variants, env)?,
+            StatementKind::Match { subject, cases } => self.compile_match(subject, cases, env)?,
+            StatementKind::WhileLoop { condition, block } => {
                 self.compile_while_loop(condition, block, env)?
             }
+            StatementKind::ForLoop {
+                identifier,
+                iterable,
+                block,
+            } => self.compile_for_loop(identifier, iterable, block, env)?,
         };
 
         Ok(())
     }
+
+    /// Unpacks a tuple into variables, declaring the ones that do not exist yet.
+    fn compile_destructure(
+        &mut self,
+        identifiers: &[String],
+        expression: &Expression,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let (tuple_ptr, tuple_tid) = self.compile_expression(expression, env)?;
+
+        if env.get_type(tuple_tid).info() != TypeInfo::Tuple {
+            return Err(GenError::InvalidType);
+        }
+        let elems = self.tuple_elems(tuple_tid, env);
+        if elems.len() != identifiers.len() {
+            return Err(GenError::InvalidType);
+        }
+
+        for (i, (identifier, elem_tid)) in identifiers.iter().zip(elems).enumerate() {
+            let elem_ptr = self.build_load_tuple_elem(tuple_ptr, tuple_tid, i as u32, env)?;
+            self.copy_pointer(elem_ptr, elem_tid, env)?;
+
+            let Ok((var_ptr, var_tid)) = env.get_var(identifier) else {
+                if elem_tid == NONE_ID {
+                    return Err(GenError::CannotInferType);
+                }
+                self.declare_var(identifier, elem_ptr, elem_tid, env)?;
+                continue;
+            };
+
+            if !env.can_coerce(elem_tid, var_tid) {
+                return Err(GenError::InvalidType);
+            }
+
+            let old_ptr = self
+                .builder
+                .build_load(self.ptr_type(), var_ptr, "old_val_ptr")?
+                .into_pointer_value();
+            self.free_pointer(old_ptr, var_tid, env)?;
+            self.builder.build_store(var_ptr, elem_ptr)?;
+        }
+
+        self.free_pointer(tuple_ptr, tuple_tid, env)
+    }
+
+    fn declare_var(
+        &mut self,
+        identifier: &str,
+        expr_ptr: PointerValue<'ctx>,
+        var_tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let ptr_type = self.ctx.ptr_type(AddressSpace::default());
+        let var_ptr = self.builder.build_alloca(ptr_type, identifier)?;
+        self.builder.build_store(var_ptr, expr_ptr)?; // Store the expression pointer in the variable.
+
+        env.insert_var(identifier.to_string(), var_ptr, var_tid);
+
+        Ok(())
+    }
+}
+
+/// Orders the statements of a block for preprocessing, so that declarations can refer to
+/// protocols and inherit from classes written after them. Protocols come first, and every class
+/// comes after the classes it inherits from.
+fn preprocess_order(statements: &[Statement]) -> Result<Vec<&Statement>, GenError> {
+    let mut classes = HashMap::new();
+    for statement in statements {
+        if let StatementKind::ClassDefinition { identifier, .. } = &statement.kind {
+            classes.entry(identifier.as_str()).or_insert(statement);
+        }
+    }
+
+    let mut order: Vec<_> = statements
+        .iter()
+        .filter(|statement| matches!(statement.kind, StatementKind::ProtocolDefinition { .. }))
+        .collect();
+    let mut ordered_classes = HashMap::new();
+    for statement in statements {
+        match &statement.kind {
+            StatementKind::ProtocolDefinition { .. } => {}
+            // A class defined twice is left where it is, so that registering it fails
+            StatementKind::ClassDefinition { identifier, .. }
+                if std::ptr::eq(classes[identifier.as_str()], statement) =>
+            {
+                order_class(identifier, &classes, &mut ordered_classes, &mut order)?
+            }
+            _ => order.push(statement),
+        }
+    }
+
+    Ok(order)
+}
+
+/// Adds a class to the order after the classes of the block it inherits from. A class is marked
+/// false while its bases are added, so inheriting from itself is caught.
+fn order_class<'a>(
+    ident: &'a str,
+    classes: &HashMap<&'a str, &'a Statement>,
+    ordered: &mut HashMap<&'a str, bool>,
+    order: &mut Vec<&'a Statement>,
+) -> Result<(), GenError> {
+    match ordered.get(ident) {
+        Some(true) => return Ok(()),
+        Some(false) => return Err(GenError::InvalidType),
+        None => {}
+    }
+    ordered.insert(ident, false);
+
+    let statement = classes[ident];
+    if let StatementKind::ClassDefinition { bases, .. } = &statement.kind {
+        for base in bases {
+            if let Some((base, _)) = classes.get_key_value(base.as_str()) {
+                order_class(base, classes, ordered, order)?;
+            }
+        }
+    }
+
+    ordered.insert(ident, true);
+    order.push(statement);
+    Ok(())
 }
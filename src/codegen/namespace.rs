@@ -0,0 +1,109 @@
+use inkwell::values::PointerValue;
+
+use crate::parser::{Expression, Primary};
+
+use super::{
+    env::{
+        fn_def::ArgSource,
+        id::{FunctionId, TypeId},
+        template::Template,
+        Environment,
+    },
+    err::GenError,
+    CodeGen,
+};
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Returns the type named on the left of `.`, as in `Vec.zero()` or `Stack[int].empty()`.
+    /// Variables shadow types with the same name.
+    pub(super) fn type_named(
+        &mut self,
+        expr: &Expression,
+        env: &mut Environment<'ctx>,
+    ) -> Result<Option<TypeId>, GenError> {
+        match expr {
+            Expression::Primary(Primary::Identifier(ident)) if env.get_var(ident).is_err() => {
+                Ok(env.find_type(ident).ok())
+            }
+            Expression::Index(generic, _) => {
+                let Expression::Primary(Primary::Identifier(ident)) = generic.as_ref() else {
+                    return Ok(None);
+                };
+                let is_struct_template = env
+                    .find_template(ident)
+                    .is_some_and(|template| matches!(template.as_ref(), Template::Struct { .. }));
+                if env.get_var(ident).is_ok() || !is_struct_template {
+                    return Ok(None);
+                }
+
+                let type_expr = Self::type_expr_of(expr)?;
+                self.resolve_type(&type_expr, env).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Compiles a name accessed on a type, which is either a variant without fields of an enum
+    /// or a constant.
+    pub(super) fn compile_type_access(
+        &mut self,
+        tid: TypeId,
+        ident: &str,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let is_variant = env
+            .enum_def(tid)
+            .is_some_and(|enum_def| enum_def.find_variant(ident).is_some());
+        if is_variant {
+            return self.build_unit_variant(tid, ident, env);
+        }
+
+        self.compile_const(tid, ident, env)
+    }
+
+    /// Compiles the value of a constant, which is evaluated wherever the constant is used but
+    /// cannot see the variables there.
+    pub(super) fn compile_const(
+        &mut self,
+        owner: TypeId,
+        ident: &str,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, TypeId), GenError> {
+        let (const_tid, value) = env
+            .find_const(owner, ident)
+            .ok_or(GenError::FieldNotFound)?;
+
+        let ptr = self.compile_detached_expression(&value, const_tid, env)?;
+        Ok((ptr, const_tid))
+    }
+
+    /// Finds the function called on a type, such as a static method or the constructor of a
+    /// variant. Static methods are inherited, so a class also looks for them in its bases.
+    pub(super) fn resolve_static_call(
+        &mut self,
+        tid: TypeId,
+        ident: &str,
+        param_tids: &[TypeId],
+        kwarg_tids: &[(String, TypeId)],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(FunctionId, Vec<ArgSource>), GenError> {
+        let owner = env
+            .type_and_bases(tid)
+            .into_iter()
+            .find(|owner| env.has_func(Some(*owner), ident))
+            .unwrap_or(tid);
+
+        match env.resolve_func_call(ident, Some(owner), param_tids, kwarg_tids) {
+            // Methods of builtin types are created the first time they are used, as in
+            // `str.upper(s)`
+            Err(GenError::FunctionNotFound) if kwarg_tids.is_empty() => {
+                let fn_id = self.find_method(ident, owner, param_tids, env)?;
+                Ok((
+                    fn_id,
+                    (0..param_tids.len()).map(ArgSource::Positional).collect(),
+                ))
+            }
+            result => result,
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::HashSet;
+
+use inkwell::{
+    basic_block::BasicBlock,
+    values::{IntValue, PointerValue},
+    IntPredicate,
+};
+
+use crate::parser::{BinaryFnOp, Expression, MatchCase, Pattern, Primary};
+
+use super::{
+    env::{
+        enum_def::Variant,
+        id::{TypeId, BOOL_ID},
+        type_def::Field,
+        Environment,
+    },
+    err::GenError,
+    CodeGen,
+};
+
+/// The hidden variable that holds the value being matched, so that it is freed with the scope of
+/// the match statement.
+const MATCH_SUBJECT_VAR: &str = "$subject";
+
+/// Stands in for the fields of a variant matched by a pattern that does not look inside it.
+const WILDCARD: &Pattern = &Pattern::Wildcard;
+
+/// Finds the variant named by a pattern, looking inside optional values.
+fn find_variant(ident: &str, tid: TypeId, env: &Environment) -> Option<Variant> {
+    let tid = env.get_type(tid).optional_inner().unwrap_or(tid);
+    env.enum_def(tid)?.find_variant(ident).cloned()
+}
+
+/// Whether a pattern matches every value of the type.
+fn is_irrefutable(pattern: &Pattern, tid: TypeId, env: &Environment) -> bool {
+    match pattern {
+        Pattern::Wildcard => true,
+        Pattern::Identifier(ident) => find_variant(ident, tid, env).is_none(),
+        Pattern::Literal(_) | Pattern::Variant(..) => false,
+    }
+}
+
+/// Looks for values that no row of patterns matches, where a row matches values of the given
+/// types, one for each of its patterns. Returns a pattern for each type describing such values,
+/// or None if every value is matched.
+///
+/// Enums, bools and optionals are split into their variants, values and None, while any other
+/// type has too many values to list, so only rows starting with an irrefutable pattern can
+/// match all of them.
+fn find_missing(rows: &[Vec<&Pattern>], tids: &[TypeId], env: &Environment) -> Option<Vec<String>> {
+    let Some((&tid, rest_tids)) = tids.split_first() else {
+        return rows.is_empty().then(Vec::new);
+    };
+
+    if let Some(inner) = env.get_type(tid).optional_inner() {
+        let none_rows: Vec<_> = rows
+            .iter()
+            .filter(|row| {
+                matches!(row[0], Pattern::Literal(Primary::None))
+                    || is_irrefutable(row[0], tid, env)
+            })
+            .map(|row| row[1..].to_vec())
+            .collect();
+        if let Some(mut missing) = find_missing(&none_rows, rest_tids, env) {
+            missing.insert(0, "None".to_string());
+            return Some(missing);
+        }
+
+        // Patterns other than `None` match the values inside the optional value
+        let some_rows: Vec<_> = rows
+            .iter()
+            .filter(|row| !matches!(row[0], Pattern::Literal(Primary::None)))
+            .cloned()
+            .collect();
+        let mut some_tids = vec![inner];
+        some_tids.extend(rest_tids);
+        return find_missing(&some_rows, &some_tids, env);
+    }
+
+    if let Some(enum_def) = env.enum_def(tid) {
+        for variant in enum_def.variants() {
+            let field_count = variant.fields.len();
+            let variant_rows: Vec<_> = rows
+                .iter()
+                .filter_map(|row| {
+                    let fields = match row[0] {
+                        Pattern::Variant(ident, fields) if *ident == variant.ident => {
+                            fields.iter().collect()
+                        }
+                        Pattern::Identifier(ident) if *ident == variant.ident => Vec::new(),
+                        pattern if is_irrefutable(pattern, tid, env) => vec![WILDCARD; field_count],
+                        _ => return None,
+                    };
+                    Some([fields, row[1..].to_vec()].concat())
+                })
+                .collect();
+
+            let mut variant_tids: Vec<_> = variant.fields.iter().map(Field::tid).collect();
+            variant_tids.extend(rest_tids);
+
+            if let Some(mut missing) = find_missing(&variant_rows, &variant_tids, env) {
+                let rest = missing.split_off(field_count);
+                let pattern = match field_count {
+                    0 => variant.ident.clone(),
+                    _ => format!("{}({})", variant.ident, missing.join(", ")),
+                };
+                return Some([vec![pattern], rest].concat());
+            }
+        }
+        return None;
+    }
+
+    if tid == BOOL_ID {
+        for value in [true, false] {
+            let value_rows: Vec<_> = rows
+                .iter()
+                .filter(|row| match row[0] {
+                    Pattern::Literal(Primary::Bool(literal)) => *literal == value,
+                    pattern => is_irrefutable(pattern, tid, env),
+                })
+                .map(|row| row[1..].to_vec())
+                .collect();
+            if let Some(mut missing) = find_missing(&value_rows, rest_tids, env) {
+                let literal = if value { "True" } else { "False" };
+                missing.insert(0, literal.to_string());
+                return Some(missing);
+            }
+        }
+        return None;
+    }
+
+    let default_rows: Vec<_> = rows
+        .iter()
+        .filter(|row| is_irrefutable(row[0], tid, env))
+        .map(|row| row[1..].to_vec())
+        .collect();
+    let mut missing = find_missing(&default_rows, rest_tids, env)?;
+    missing.insert(0, "_".to_string());
+    Some(missing)
+}
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Compiles a match statement, which runs the body of the first case that matches. The
+    /// cases without a guard have to match every value of the subject.
+    pub(super) fn compile_match(
+        &mut self,
+        subject: &Expression,
+        cases: &[MatchCase],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let (subject_ptr, subject_tid) = self.compile_expression(subject, env)?;
+
+        env.push_scope();
+        self.declare_var(MATCH_SUBJECT_VAR, subject_ptr, subject_tid, env)?;
+
+        let cur_fn = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let merge_block = self.ctx.append_basic_block(cur_fn, "match_end");
+
+        for case in cases {
+            let next_case_block = self.ctx.append_basic_block(cur_fn, "next_case");
+
+            self.build_pattern_test(
+                &case.pattern,
+                subject_ptr,
+                subject_tid,
+                next_case_block,
+                env,
+            )?;
+
+            env.push_scope();
+            self.bind_pattern(
+                &case.pattern,
+                subject_ptr,
+                subject_tid,
+                &mut HashSet::new(),
+                env,
+            )?;
+
+            if let Some(guard) = &case.guard {
+                let (guard_ptr, guard_tid) = self.compile_expression(guard, env)?;
+                let holds = self.build_truthy(guard_ptr, guard_tid, env)?;
+
+                let body_block = self.ctx.append_basic_block(cur_fn, "case");
+                let guard_failed_block = self.ctx.append_basic_block(cur_fn, "guard_failed");
+                self.builder
+                    .build_conditional_branch(holds, body_block, guard_failed_block)?;
+
+                // The names bound by the pattern are released before the next case is tried
+                self.builder.position_at_end(guard_failed_block);
+                self.free_vars_in_scope(env.func.scopes.last().unwrap(), env)?;
+                self.builder.build_unconditional_branch(next_case_block)?;
+
+                self.builder.position_at_end(body_block);
+            }
+
+            self.compile_block(&case.body, env)?;
+            let case_scope = env.pop_scope().unwrap();
+            self.free_vars_in_scope(&case_scope, env)?;
+            self.builder.build_unconditional_branch(merge_block)?;
+
+            self.builder.position_at_end(next_case_block);
+        }
+
+        let rows: Vec<_> = cases
+            .iter()
+            .filter(|case| case.guard.is_none())
+            .map(|case| vec![&case.pattern])
+            .collect();
+        if let Some(mut missing) = find_missing(&rows, &[subject_tid], env) {
+            return Err(GenError::NonExhaustiveMatch {
+                missing: missing.remove(0),
+            });
+        }
+
+        // Every value is matched by one of the cases
+        self.builder.build_unreachable()?;
+
+        self.builder.position_at_end(merge_block);
+        let subject_scope = env.pop_scope().unwrap();
+        self.free_vars_in_scope(&subject_scope, env)?;
+
+        Ok(())
+    }
+
+    /// Branches to `no_match` unless the value matches the pattern, and otherwise continues in a
+    /// new block.
+    fn build_pattern_test(
+        &mut self,
+        pattern: &Pattern,
+        value: PointerValue<'ctx>,
+        tid: TypeId,
+        no_match: BasicBlock<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        match pattern {
+            Pattern::Wildcard => Ok(()),
+            Pattern::Literal(Primary::None) => {
+                if env.get_type(tid).optional_inner().is_none() {
+                    return Err(GenError::InvalidType);
+                }
+                let is_none = self.builder.build_is_null(value, "is_none")?;
+                self.build_match_branch(is_none, no_match)
+            }
+            Pattern::Literal(literal) => {
+                let tid = self.build_some_test(value, tid, no_match, env)?;
+
+                let literal = Expression::Primary(literal.clone());
+                let (literal_ptr, literal_tid) =
+                    self.compile_hinted_expression(&literal, tid, env)?;
+                let eq_fn = self
+                    .find_method(BinaryFnOp::Equal.fn_name(), tid, &[tid, literal_tid], env)
+                    .map_err(|_| GenError::InvalidType)?;
+
+                self.copy_pointer(value, tid, env)?;
+                let (eq_ptr, eq_tid) = self.call_func(eq_fn, &[value, literal_ptr], env)?;
+                let is_equal = self.build_truthy(eq_ptr, eq_tid, env)?;
+                self.build_match_branch(is_equal, no_match)
+            }
+            Pattern::Identifier(ident) => match find_variant(ident, tid, env) {
+                Some(variant) => self.build_variant_test(&variant, &[], value, tid, no_match, env),
+                None => Ok(()),
+            },
+            Pattern::Variant(ident, fields) => {
+                let variant = find_variant(ident, tid, env).ok_or(GenError::InvalidType)?;
+                self.build_variant_test(&variant, fields, value, tid, no_match, env)
+            }
+        }
+    }
+
+    fn build_variant_test(
+        &mut self,
+        variant: &Variant,
+        fields: &[Pattern],
+        value: PointerValue<'ctx>,
+        tid: TypeId,
+        no_match: BasicBlock<'ctx>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        if fields.len() != variant.fields.len() {
+            return Err(GenError::InvalidType);
+        }
+
+        let tid = self.build_some_test(value, tid, no_match, env)?;
+        let tag = self.build_load_tag(value, tid, env)?;
+        let variant_tag = self.ctx.i64_type().const_int(variant.tag, false);
+        let is_variant =
+            self.builder
+                .build_int_compare(IntPredicate::EQ, tag, variant_tag, "is_variant")?;
+        self.build_match_branch(is_variant, no_match)?;
+
+        for (pattern, field) in fields.iter().zip(&variant.fields) {
+            let field_ptr = self.build_load_variant_field(value, tid, field, env)?;
+            self.build_pattern_test(pattern, field_ptr, field.tid(), no_match, env)?;
+        }
+
+        Ok(())
+    }
+
+    /// Branches to `no_match` if an optional value is None, returning the type of the value
+    /// inside it.
+    fn build_some_test(
+        &mut self,
+        value: PointerValue<'ctx>,
+        tid: TypeId,
+        no_match: BasicBlock<'ctx>,
+        env: &Environment<'ctx>,
+    ) -> Result<TypeId, GenError> {
+        let Some(inner) = env.get_type(tid).optional_inner() else {
+            return Ok(tid);
+        };
+
+        let is_some = self.builder.build_is_not_null(value, "is_some")?;
+        self.build_match_branch(is_some, no_match)?;
+        Ok(inner)
+    }
+
+    fn build_match_branch(
+        &mut self,
+        matches: IntValue<'ctx>,
+        no_match: BasicBlock<'ctx>,
+    ) -> Result<(), GenError> {
+        let cur_fn = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let matched_block = self.ctx.append_basic_block(cur_fn, "matched");
+        self.builder
+            .build_conditional_branch(matches, matched_block, no_match)?;
+        self.builder.position_at_end(matched_block);
+        Ok(())
+    }
+
+    /// Declares the names bound by a pattern that matched the value, each holding a reference to
+    /// the part of the value it matched.
+    fn bind_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: PointerValue<'ctx>,
+        tid: TypeId,
+        bound: &mut HashSet<String>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        match pattern {
+            Pattern::Identifier(ident) if find_variant(ident, tid, env).is_none() => {
+                if !bound.insert(ident.clone()) {
+                    return Err(GenError::IdentConflict);
+                }
+                self.copy_pointer(value, tid, env)?;
+                self.declare_var(ident, value, tid, env)
+            }
+            Pattern::Variant(ident, fields) => {
+                let variant = find_variant(ident, tid, env).ok_or(GenError::InvalidType)?;
+                let tid = env.get_type(tid).optional_inner().unwrap_or(tid);
+                for (pattern, field) in fields.iter().zip(&variant.fields) {
+                    let field_ptr = self.build_load_variant_field(value, tid, field, env)?;
+                    self.bind_pattern(pattern, field_ptr, field.tid(), bound, env)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use crate::parser::{FuncDeclaration, ProtocolMethod, TypeExpr};
+
+use super::{
+    env::{id::TypeId, template::Template, Environment},
+    err::GenError,
+    CodeGen,
+};
+
+/// The name protocol methods use for the type of the value they are called on.
+const SELF_TYPE: &str = "Self";
+
+/// Whether a function is compiled once for every list of types it is used with, which is the
+/// case for functions with type parameters and for functions taking protocols.
+pub(super) fn is_generic_fn(fn_dec: &FuncDeclaration, env: &Environment) -> bool {
+    !fn_dec.type_params.is_empty()
+        || fn_dec
+            .params
+            .iter()
+            .any(|param| mentions_protocol(&param.type_identifier, env))
+}
+
+/// Turns a generic function into a template. Every protocol in the parameter types becomes a
+/// type parameter of its own, bound by that protocol, so a function taking a protocol is
+/// compiled for every type it is called with.
+pub(super) fn fn_template(fn_dec: &FuncDeclaration, env: &Environment) -> Template {
+    let mut fn_dec = fn_dec.clone();
+    let mut bounds = Vec::new();
+    for param in &mut fn_dec.params {
+        bind_protocols(&mut param.type_identifier, &mut bounds, env);
+    }
+    fn_dec
+        .type_params
+        .extend(bounds.iter().map(|(type_param, _)| type_param.clone()));
+
+    Template::Func { fn_dec, bounds }
+}
+
+fn mentions_protocol(type_expr: &TypeExpr, env: &Environment) -> bool {
+    match type_expr {
+        TypeExpr::Named(ident) => env.find_protocol(ident).is_some(),
+        TypeExpr::Generic(_, params) => params.iter().any(|param| mentions_protocol(param, env)),
+        TypeExpr::Optional(inner) => mentions_protocol(inner, env),
+        TypeExpr::Func(params, ret) => {
+            params.iter().any(|param| mentions_protocol(param, env)) || mentions_protocol(ret, env)
+        }
+    }
+}
+
+/// Replaces every protocol in a type with a new type parameter, such as `Printable$0`.
+fn bind_protocols(type_expr: &mut TypeExpr, bounds: &mut Vec<(String, String)>, env: &Environment) {
+    match type_expr {
+        TypeExpr::Named(ident) if env.find_protocol(ident).is_some() => {
+            let type_param = format!("{}${}", ident, bounds.len());
+            bounds.push((type_param.clone(), ident.clone()));
+            *ident = type_param;
+        }
+        TypeExpr::Named(_) => {}
+        TypeExpr::Generic(_, params) => {
+            for param in params {
+                bind_protocols(param, bounds, env);
+            }
+        }
+        TypeExpr::Optional(inner) => bind_protocols(inner, bounds, env),
+        TypeExpr::Func(params, ret) => {
+            for param in params {
+                bind_protocols(param, bounds, env);
+            }
+            bind_protocols(ret, bounds, env);
+        }
+    }
+}
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Checks that a type has every method required by the protocols it declares to conform to.
+    pub(super) fn check_protocols(
+        &mut self,
+        tid: TypeId,
+        protocols: &[String],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        for protocol in protocols {
+            self.check_conformance(tid, protocol, env)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that a type has every method required by a protocol, with parameters accepting the
+    /// ones of the protocol and a return type usable as the one of the protocol.
+    pub(super) fn check_conformance(
+        &mut self,
+        tid: TypeId,
+        protocol: &str,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let methods = env.find_protocol(protocol).ok_or(GenError::TypeNotFound)?;
+
+        // Signatures are resolved outside of any template being instantiated
+        let prev_type_args = env.set_type_args(HashMap::from([(SELF_TYPE.to_string(), tid)]));
+        let conforms = self.has_methods(tid, &methods, env);
+        env.set_type_args(prev_type_args);
+
+        if !conforms? {
+            return Err(GenError::DoesNotConform {
+                type_ident: env.type_id_ident(tid).to_string(),
+                protocol: protocol.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn has_methods(
+        &mut self,
+        tid: TypeId,
+        methods: &[ProtocolMethod],
+        env: &mut Environment<'ctx>,
+    ) -> Result<bool, GenError> {
+        for method in methods {
+            let mut arg_tids = vec![tid];
+            for param in &method.params {
+                arg_tids.push(self.resolve_param_type(param, env)?);
+            }
+            let ret_tid = self.resolve_type(&method.return_identifier, env)?;
+
+            let Ok(fn_id) = self.find_method(&method.identifier, tid, &arg_tids, env) else {
+                return Ok(false);
+            };
+            if !env.can_coerce(env.get_func(fn_id).ret_type, ret_tid) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
@@ -5,44 +5,75 @@ use inkwell::{
 
 use crate::{
     codegen::ink_extension::{InkTypeExt, InkValueExt},
-    parser::FuncDeclaration,
+    parser::{
+        AssignOp, Derive, Expression, FuncDeclaration, LValue, Primary, Statement, StatementKind,
+        StructConst, StructField,
+    },
 };
 
 use super::{
+    class::is_super,
     env::{
-        id::TypeId,
+        class_def::ClassDef,
+        id::{FunctionId, TypeId, INT_ID, NONE_ID},
         type_def::{Field, TypeDef},
         Environment,
     },
     err::GenError,
-    util::FREE_PTR_IDENT,
+    util::{COPY_PTR_IDENT, FREE_PTR_IDENT, RCOUNT_IDENT},
     CodeGen,
 };
 
+/// The method that replaces the generated constructor of a struct when it is defined.
+pub const INIT_FN: &str = "__init__";
+/// The method called on a struct right before its fields are released.
+pub const DEL_FN: &str = "__del__";
+
 impl<'ctx> CodeGen<'ctx> {
     pub(super) fn preprocess_struct_definition(
         &mut self,
         ident: &str,
-        field_defs: &[(String, String)],
+        field_defs: &[StructField],
+        consts: &[StructConst],
         fns: &[FuncDeclaration],
+        derives: &[Derive],
         env: &mut Environment<'ctx>,
     ) -> Result<(), GenError> {
+        // A derived method would clash with one written by hand
+        if derives.iter().any(|derive| {
+            fns.iter()
+                .any(|fn_dec| fn_dec.identifier == derive.fn_name())
+        }) {
+            return Err(GenError::IdentConflict);
+        }
+
+        // Register the type before resolving its fields so that fields can refer to it
+        let struct_type = self.ctx.opaque_struct_type(ident);
+        let tid = env.gen_type_id();
+        env.register_type(ident, tid, TypeDef::new(ident, struct_type, Vec::new()))?;
+
+        // Declare the pointer functions, which are needed by types containing this one
+        env.create_func(Some(tid), FREE_PTR_IDENT, &[tid], NONE_ID, false)?;
+        env.create_func(Some(tid), COPY_PTR_IDENT, &[tid], tid, false)?;
+        env.create_func(Some(tid), RCOUNT_IDENT, &[tid], INT_ID, false)?;
+
         let mut fields = Vec::new();
         for i in 0..field_defs.len() {
-            let (field_ident, field_type_ident) = &field_defs[i];
-            let field_type = env.find_type(field_type_ident)?;
-            fields.push(Field::new(i as u32, field_ident, field_type));
+            let field_def = &field_defs[i];
+            let field_type = self.resolve_type(&field_def.type_identifier, env)?;
+            fields.push(Field::new(i as u32, &field_def.identifier, field_type));
         }
 
-        let field_ptr_types = fields
+        let mut field_types: Vec<BasicTypeEnum> = fields
             .iter()
             .map(|_| self.ptr_type().as_basic_type_enum())
             .collect();
+        field_types.push(self.ctx.i64_type().into());
 
-        let struct_type = self.create_struct_type(ident, field_ptr_types);
-
-        let tid = env.gen_type_id();
-        env.register_type(ident, tid, TypeDef::new(ident, struct_type, fields))?;
+        struct_type.set_body(&field_types, false);
+        env.get_type_mut(tid).set_fields(fields);
+        env.register_derives(tid, derives);
+        self.preprocess_consts(tid, consts, fns, env)?;
 
         for fn_dec in fns {
             self.preprocess_fn(Some(tid), fn_dec, env)?;
@@ -54,20 +85,28 @@ impl<'ctx> CodeGen<'ctx> {
     pub(super) fn compile_struct_definition(
         &mut self,
         ident: &str,
-        _fields: &[(String, String)],
+        fields: &[StructField],
         fns: &[FuncDeclaration],
+        protocols: &[String],
         env: &mut Environment<'ctx>,
     ) -> Result<(), GenError> {
         let tid = env.find_type(ident)?;
         let prev_block = self.builder.get_insert_block().unwrap();
 
+        self.check_special_fns(fns, env)?;
+        self.check_protocols(tid, protocols, env)?;
+
         // Pointer Functions
         self.build_free_ptr_fn(tid, Self::struct_unalloc, env)?;
         self.build_copy_ptr_fn(tid, env)?;
         self.build_get_reference_count_fn(tid, env)?;
 
         // Constructor
-        self.build_struct_constructor(ident, tid, env)?;
+        if fns.iter().any(|fn_dec| fn_dec.identifier == INIT_FN) {
+            self.build_init_constructors(ident, tid, fields, fns, env)?;
+        } else {
+            self.build_struct_constructor(ident, tid, fields, env)?;
+        }
 
         // Compile all user functions
         for fn_dec in fns {
@@ -78,6 +117,52 @@ impl<'ctx> CodeGen<'ctx> {
         Ok(())
     }
 
+    /// Registers the constants of a struct, whose names cannot be used by its fields or methods.
+    pub(super) fn preprocess_consts(
+        &mut self,
+        tid: TypeId,
+        consts: &[StructConst],
+        fns: &[FuncDeclaration],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        for const_def in consts {
+            let ident = &const_def.identifier;
+            if tid.get_from(env).find_field(ident).is_ok()
+                || fns.iter().any(|fn_dec| &fn_dec.identifier == ident)
+            {
+                return Err(GenError::IdentConflict);
+            }
+
+            let const_tid = self.resolve_type(&const_def.type_identifier, env)?;
+            env.register_const(tid, ident, const_tid, const_def.value.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that `__init__` and `__del__` take `self`, and that `__del__` takes nothing else and
+    /// returns None, since it is called with nothing else and has nowhere to return a value to.
+    pub(super) fn check_special_fns(
+        &mut self,
+        fns: &[FuncDeclaration],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        if fns.iter().any(|fn_dec| {
+            (fn_dec.identifier == INIT_FN || fn_dec.identifier == DEL_FN) && !fn_dec.takes_self
+        }) {
+            return Err(GenError::InvalidFunctionDefinition);
+        }
+
+        for fn_dec in fns.iter().filter(|fn_dec| fn_dec.identifier == DEL_FN) {
+            let ret_tid = self.resolve_type(&fn_dec.return_identifier, env)?;
+            if !fn_dec.params.is_empty() || ret_tid != NONE_ID {
+                return Err(GenError::InvalidFunctionDefinition);
+            }
+        }
+
+        Ok(())
+    }
+
     pub(super) fn create_struct_type(
         &self,
         ident: &str,
@@ -134,24 +219,27 @@ impl<'ctx> CodeGen<'ctx> {
         Ok(val_ptr)
     }
 
-    fn struct_unalloc(
+    pub(super) fn struct_unalloc(
         ptr: PointerValue<'ctx>,
         tid: TypeId,
         gen: &mut CodeGen<'ctx>,
         env: &mut Environment<'ctx>,
     ) -> Result<(), GenError> {
+        if let Ok(del_id) = env.find_func(DEL_FN, Some(tid), &[tid]) {
+            gen.build_call_del(ptr, tid, del_id, env)?;
+        }
+
         let type_def = tid.get_from(env);
         let ink_type = type_def.ink();
         let fields = type_def.fields();
 
-        for i in 0..fields.len() {
-            let field = &fields[i];
+        for field in fields {
             let field_type = field.tid();
-            let free_id: super::env::id::FunctionId =
+            let free_id: FunctionId =
                 env.find_func(FREE_PTR_IDENT, Some(field_type), &[field_type])?;
             let field_ptr_ptr =
                 gen.builder
-                    .build_struct_gep(ink_type, ptr, i as u32, "field_ptr_ptr")?;
+                    .build_struct_gep(ink_type, ptr, field.index(), "field_ptr_ptr")?;
             let field_ptr = gen
                 .builder
                 .build_load(gen.ptr_type(), field_ptr_ptr, "field_ptr")?
@@ -162,17 +250,80 @@ impl<'ctx> CodeGen<'ctx> {
         Ok(())
     }
 
-    fn build_struct_constructor(
+    /// Calls `__del__` on a struct whose reference count has reached zero. The struct is kept
+    /// alive while `__del__` runs, so releasing `self` when it returns does not free it again.
+    fn build_call_del(
+        &mut self,
+        ptr: PointerValue<'ctx>,
+        tid: TypeId,
+        del_id: FunctionId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let struct_type = tid.get_from(env).ink();
+        let ref_count_ptr = self.builder.build_struct_gep(
+            struct_type,
+            ptr,
+            struct_type.count_fields() - 1,
+            "refcountptr",
+        )?;
+        self.builder
+            .build_store(ref_count_ptr, self.ctx.i64_type().const_int(1, false))?;
+
+        let self_ptr = self.copy_pointer(ptr, tid, env)?;
+        let (none, none_tid) = self.call_func(del_id, &[self_ptr], env)?;
+        self.free_pointer(none, none_tid, env)?;
+
+        Ok(())
+    }
+
+    /// Frees a value that may be null, like a field that `__init__` has not set yet.
+    pub(super) fn free_pointer_if_set(
+        &mut self,
+        ptr: PointerValue<'ctx>,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        let cur_block = self.builder.get_insert_block().unwrap();
+        let free_block = self.ctx.insert_basic_block_after(cur_block, "free_set");
+        let merge_block = self.ctx.insert_basic_block_after(free_block, "freed_set");
+
+        let is_set = self.builder.build_is_not_null(ptr, "is_set")?;
+        self.builder
+            .build_conditional_branch(is_set, free_block, merge_block)?;
+
+        self.builder.position_at_end(free_block);
+        self.free_pointer(ptr, tid, env)?;
+        self.builder.build_unconditional_branch(merge_block)?;
+
+        self.builder.position_at_end(merge_block);
+
+        Ok(())
+    }
+
+    pub(in crate::codegen) fn build_struct_constructor(
         &mut self,
         ident: &str,
         tid: TypeId,
+        field_defs: &[StructField],
         env: &mut Environment<'ctx>,
     ) -> Result<(), GenError> {
         let type_def = tid.get_from(env);
         let fields: Vec<TypeId> = type_def.fields().iter().map(|field| field.tid()).collect();
+        let field_idents = type_def
+            .fields()
+            .iter()
+            .map(|field| field.ident().to_string())
+            .collect();
         let struct_type = type_def.ink();
 
-        let (fn_val, ..) = env.create_func(None, ident, &fields, tid, false)?;
+        // Fields can be passed by name, as in `Point(x=1, y=2)`, and left out when they have a
+        // default, which is filled in at the call like the default of a parameter
+        let defaults = field_defs
+            .iter()
+            .map(|field| field.default.clone())
+            .collect();
+        let (fn_val, fn_id) = env.create_func(None, ident, &fields, tid, false)?;
+        env.set_fn_params(fn_id, field_idents, defaults, false);
         let entry_block = self.ctx.append_basic_block(fn_val, "entry");
         self.builder.position_at_end(entry_block);
 
@@ -180,9 +331,341 @@ impl<'ctx> CodeGen<'ctx> {
         for i in 0..fields.len() {
             values.push(fn_val.get_nth_param(i as u32).unwrap());
         }
+        self.insert_vtable(tid, &mut values, env);
         let struct_ptr = self.build_struct(struct_type, values)?;
         self.builder.build_return(Some(&struct_ptr))?;
 
         Ok(())
     }
+
+    /// Builds a constructor for every overload of `__init__`, which takes the same arguments.
+    /// The constructor starts the struct off with the default values of its fields and leaves
+    /// the rest null for `__init__` to set.
+    pub(super) fn build_init_constructors(
+        &mut self,
+        ident: &str,
+        tid: TypeId,
+        field_defs: &[StructField],
+        fns: &[FuncDeclaration],
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
+        // The fields of the base of a class are set by calling `super().__init__(...)`
+        let inherited = match env.class_def(tid).and_then(ClassDef::base) {
+            Some(base) => base.get_from(env).fields().len(),
+            None => 0,
+        };
+
+        for init in fns.iter().filter(|fn_dec| fn_dec.identifier == INIT_FN) {
+            let is_const = |ident: &str| env.find_const(tid, ident).is_some();
+            let uninitialized =
+                find_uninitialized_field(&init.body, field_defs, inherited, is_const);
+            if let Some(field) = uninitialized {
+                return Err(GenError::UninitializedField {
+                    struct_ident: ident.to_string(),
+                    field: field.identifier.clone(),
+                });
+            }
+
+            let params = init
+                .params
+                .iter()
+                .map(|param| self.resolve_param_type(param, env))
+                .collect::<Result<Vec<_>, GenError>>()?;
+            let mut init_params = vec![tid];
+            init_params.extend(&params);
+            let init_id = env.find_func(INIT_FN, Some(tid), &init_params)?;
+
+            let init_fn = env.get_func(init_id);
+            if init_fn.ret_type != NONE_ID {
+                return Err(GenError::InvalidFunctionDefinition);
+            }
+            let param_idents = init_fn.param_idents[1..].to_vec();
+            let defaults = init_fn.defaults[1..].to_vec();
+            let var_args = init_fn.var_args;
+
+            let (fn_val, fn_id) = env.create_func(None, ident, &params, tid, false)?;
+            env.set_fn_params(fn_id, param_idents, defaults, var_args);
+            let entry_block = self.ctx.append_basic_block(fn_val, "entry");
+            self.builder.position_at_end(entry_block);
+
+            let mut values = Vec::with_capacity(field_defs.len());
+            for (i, field) in field_defs.iter().enumerate() {
+                let value = match &field.default {
+                    Some(default) => {
+                        let field_tid = tid.get_from(env).fields()[i].tid();
+                        self.compile_detached_expression(default, field_tid, env)?
+                    }
+                    None => self.ptr_type().const_null(),
+                };
+                values.push(value.into());
+            }
+
+            self.insert_vtable(tid, &mut values, env);
+            let struct_ptr = self.build_struct(tid.get_from(env).ink(), values)?;
+
+            let mut args = vec![self.copy_pointer(struct_ptr, tid, env)?];
+            args.extend(
+                (0..params.len())
+                    .map(|i| fn_val.get_nth_param(i as u32).unwrap().into_pointer_value()),
+            );
+            let (none, none_tid) = self.call_func(init_id, &args, env)?;
+            self.free_pointer(none, none_tid, env)?;
+
+            self.builder.build_return(Some(&struct_ptr))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How a statement uses `self`, which matters while `__init__` has not set every field yet.
+enum SelfUse {
+    /// Reads a field, or refers to a constant or method when no field has the name.
+    Field(String),
+    /// Uses `self` as a whole, such as by passing it on or capturing it in a closure.
+    Whole,
+}
+
+/// Finds a field that `__init__` may read before setting it, or that it never sets. Only
+/// assignments at the top level of the body count, since a branch may be skipped. Calling a
+/// method or otherwise using `self` as a whole needs every field to be set, since the fields it
+/// reads are unknown. Constants can be read at any time.
+fn find_uninitialized_field<'a>(
+    body: &[Statement],
+    field_defs: &'a [StructField],
+    inherited: usize,
+    is_const: impl Fn(&str) -> bool,
+) -> Option<&'a StructField> {
+    let mut is_set: Vec<bool> = field_defs
+        .iter()
+        .map(|field| field.default.is_some())
+        .collect();
+    let first_unset = |is_set: &[bool]| {
+        field_defs
+            .iter()
+            .zip(is_set)
+            .find(|(_, is_set)| !**is_set)
+            .map(|(field, _)| field)
+    };
+
+    for statement in body {
+        let mut uses = Vec::new();
+        statement_self_uses(statement, &mut uses);
+        for self_use in uses {
+            if matches!(&self_use, SelfUse::Field(ident) if is_const(ident)) {
+                continue;
+            }
+
+            let field = match self_use {
+                SelfUse::Field(ident) => field_defs
+                    .iter()
+                    .position(|field| field.identifier == ident),
+                SelfUse::Whole => None,
+            };
+            let unset = match field {
+                Some(i) if is_set[i] => None,
+                Some(i) => Some(&field_defs[i]),
+                None => first_unset(&is_set),
+            };
+            if unset.is_some() {
+                return unset;
+            }
+        }
+
+        if let Some(i) = set_self_field(statement).and_then(|ident| {
+            field_defs
+                .iter()
+                .position(|field| field.identifier == ident)
+        }) {
+            is_set[i] = true;
+        }
+        // The fields of the base of a class are set by `super().__init__(...)`
+        if is_super_init_call(statement) {
+            is_set[..inherited].fill(true);
+        }
+    }
+
+    first_unset(&is_set)
+}
+
+/// Whether a statement calls `super().__init__(...)`.
+fn is_super_init_call(statement: &Statement) -> bool {
+    match &statement.kind {
+        StatementKind::Expression(Expression::Invoke(callee, ..)) => match callee.as_ref() {
+            Expression::Access(expr, ident) => ident == INIT_FN && is_super(expr),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Returns the field of `self` a statement assigns to, if it is an assignment like `self.x = 1`.
+fn set_self_field(statement: &Statement) -> Option<&str> {
+    match &statement.kind {
+        StatementKind::Assignment {
+            lvalue: LValue::Access(expr, ident),
+            op: AssignOp::Assign,
+            ..
+        } if is_self(expr) => Some(ident),
+        _ => None,
+    }
+}
+
+fn is_self(expr: &Expression) -> bool {
+    matches!(expr, Expression::Primary(Primary::Identifier(ident)) if ident == "self")
+}
+
+fn statement_self_uses(statement: &Statement, uses: &mut Vec<SelfUse>) {
+    match &statement.kind {
+        StatementKind::Declaration { expression, .. }
+        | StatementKind::Destructure { expression, .. }
+        | StatementKind::Expression(expression)
+        | StatementKind::ReturnStatement { expression } => expr_self_uses(expression, uses),
+        StatementKind::Assignment {
+            lvalue,
+            op,
+            expression,
+        } => {
+            match lvalue {
+                // Assigning to a field only reads it for operators like `+=`
+                LValue::Access(expr, ident) if is_self(expr) => {
+                    if !matches!(op, AssignOp::Assign) {
+                        uses.push(SelfUse::Field(ident.clone()));
+                    }
+                }
+                LValue::Access(expr, _) => expr_self_uses(expr, uses),
+                LValue::Index(expr, index) => {
+                    expr_self_uses(expr, uses);
+                    expr_self_uses(index, uses);
+                }
+                LValue::Ident(_) => {}
+            }
+            expr_self_uses(expression, uses);
+        }
+        StatementKind::Delete { expression, index } => {
+            expr_self_uses(expression, uses);
+            expr_self_uses(index, uses);
+        }
+        // A nested function captures `self` as a whole
+        StatementKind::FunctionDeclaration(fn_dec) => {
+            let mut body_uses = Vec::new();
+            block_self_uses(&fn_dec.body, &mut body_uses);
+            if !body_uses.is_empty() {
+                uses.push(SelfUse::Whole);
+            }
+        }
+        StatementKind::IfStatement {
+            condition,
+            then_branch,
+            else_if_branches,
+            else_branch,
+        } => {
+            expr_self_uses(condition, uses);
+            block_self_uses(then_branch, uses);
+            for (condition, branch) in else_if_branches {
+                expr_self_uses(condition, uses);
+                block_self_uses(branch, uses);
+            }
+            if let Some(branch) = else_branch {
+                block_self_uses(branch, uses);
+            }
+        }
+        StatementKind::WhileLoop { condition, block } => {
+            expr_self_uses(condition, uses);
+            block_self_uses(block, uses);
+        }
+        StatementKind::ForLoop {
+            iterable, block, ..
+        } => {
+            expr_self_uses(iterable, uses);
+            block_self_uses(block, uses);
+        }
+        StatementKind::Match { subject, cases } => {
+            expr_self_uses(subject, uses);
+            for case in cases {
+                if let Some(guard) = &case.guard {
+                    expr_self_uses(guard, uses);
+                }
+                block_self_uses(&case.body, uses);
+            }
+        }
+        StatementKind::ExternFunctionDeclaration { .. }
+        | StatementKind::StructDefinition { .. }
+        | StatementKind::ClassDefinition { .. }
+        | StatementKind::ProtocolDefinition { .. }
+        | StatementKind::EnumDefinition { .. } => {}
+    }
+}
+
+fn block_self_uses(statements: &[Statement], uses: &mut Vec<SelfUse>) {
+    for statement in statements {
+        statement_self_uses(statement, uses);
+    }
+}
+
+fn expr_self_uses(expr: &Expression, uses: &mut Vec<SelfUse>) {
+    match expr {
+        Expression::Access(inner, ident) if is_self(inner) => {
+            uses.push(SelfUse::Field(ident.clone()))
+        }
+        Expression::Primary(Primary::Identifier(ident)) if ident == "self" => {
+            uses.push(SelfUse::Whole)
+        }
+        // `self.method(...)` passes the whole value to the method
+        Expression::Invoke(callee, args, kwargs) => {
+            match callee.as_ref() {
+                Expression::Access(inner, _) if is_self(inner) => uses.push(SelfUse::Whole),
+                callee => expr_self_uses(callee, uses),
+            }
+            for arg in args.iter().chain(kwargs.iter().map(|(_, arg)| arg)) {
+                expr_self_uses(arg, uses);
+            }
+        }
+        Expression::Binary(left, _, right) | Expression::BinaryFn(left, _, right) => {
+            expr_self_uses(left, uses);
+            expr_self_uses(right, uses);
+        }
+        Expression::Compare(first, chain) => {
+            expr_self_uses(first, uses);
+            for (_, expr) in chain {
+                expr_self_uses(expr, uses);
+            }
+        }
+        Expression::Conditional(condition, then_expr, else_expr) => {
+            expr_self_uses(condition, uses);
+            expr_self_uses(then_expr, uses);
+            expr_self_uses(else_expr, uses);
+        }
+        Expression::Unary(_, expr) | Expression::UnaryFn(_, expr) => expr_self_uses(expr, uses),
+        Expression::Access(expr, _) => expr_self_uses(expr, uses),
+        Expression::Index(expr, index) => {
+            expr_self_uses(expr, uses);
+            expr_self_uses(index, uses);
+        }
+        Expression::Slice(start, stop, step) => {
+            for part in [start, stop, step].into_iter().flatten() {
+                expr_self_uses(part, uses);
+            }
+        }
+        Expression::List(items) | Expression::Set(items) | Expression::Tuple(items) => {
+            for item in items {
+                expr_self_uses(item, uses);
+            }
+        }
+        Expression::Dict(entries) => {
+            for (key, value) in entries {
+                expr_self_uses(key, uses);
+                expr_self_uses(value, uses);
+            }
+        }
+        Expression::Lambda(_, body) => {
+            let mut body_uses = Vec::new();
+            expr_self_uses(body, &mut body_uses);
+            if !body_uses.is_empty() {
+                uses.push(SelfUse::Whole);
+            }
+        }
+        Expression::Primary(Primary::Grouping(expr)) => expr_self_uses(expr, uses),
+        Expression::Primary(_) => {}
+    }
 }
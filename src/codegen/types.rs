@@ -0,0 +1,86 @@
+use crate::parser::TypeExpr;
+
+use super::{
+    builtin::{dict::DICT_NAME, list::LIST_NAME, set::SET_NAME, tuple::TUPLE_NAME},
+    env::{
+        id::{TypeId, NONE_ID},
+        Environment,
+    },
+    err::GenError,
+    CodeGen,
+};
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Finds the type named by a type annotation, creating it if it is an instance of a builtin
+    /// type constructor (e.g. `Optional[int]`) or of a generic struct.
+    pub(super) fn resolve_type(
+        &mut self,
+        type_expr: &TypeExpr,
+        env: &mut Environment<'ctx>,
+    ) -> Result<TypeId, GenError> {
+        match type_expr {
+            TypeExpr::Named(ident) => env.find_type(ident),
+            TypeExpr::Generic(ident, args) => match (ident.as_str(), args.as_slice()) {
+                (LIST_NAME, [elem]) => {
+                    let elem_tid = self.resolve_type(elem, env)?;
+                    self.list_of(elem_tid, env)
+                }
+                (DICT_NAME, [key, value]) => {
+                    let key_tid = self.resolve_type(key, env)?;
+                    let value_tid = self.resolve_type(value, env)?;
+                    self.dict_of(key_tid, value_tid, env)
+                }
+                (SET_NAME, [elem]) => {
+                    let elem_tid = self.resolve_type(elem, env)?;
+                    self.set_of(elem_tid, env)
+                }
+                (TUPLE_NAME, elems) => {
+                    let elem_tids = elems
+                        .iter()
+                        .map(|elem| self.resolve_type(elem, env))
+                        .collect::<Result<Vec<_>, GenError>>()?;
+                    self.tuple_of(&elem_tids, env)
+                }
+                (ident, args) => {
+                    let type_args = args
+                        .iter()
+                        .map(|arg| self.resolve_type(arg, env))
+                        .collect::<Result<Vec<_>, GenError>>()?;
+                    self.instantiate_struct(ident, &type_args, env)
+                }
+            },
+            TypeExpr::Optional(inner) => {
+                let inner_tid = self.resolve_type(inner, env)?;
+                self.optional_of(inner_tid, env)
+            }
+            TypeExpr::Func(params, ret) => {
+                let param_tids = params
+                    .iter()
+                    .map(|param| self.resolve_type(param, env))
+                    .collect::<Result<Vec<_>, GenError>>()?;
+                let ret_tid = self.resolve_type(ret, env)?;
+                self.fn_type_of(&param_tids, ret_tid, env)
+            }
+        }
+    }
+
+    /// Finds a type that values of both given types can be coerced to.
+    pub(super) fn unify_types(
+        &mut self,
+        left: TypeId,
+        right: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<TypeId, GenError> {
+        if env.can_coerce(left, right) {
+            Ok(right)
+        } else if env.can_coerce(right, left) {
+            Ok(left)
+        } else if left == NONE_ID {
+            self.optional_of(right, env)
+        } else if right == NONE_ID {
+            self.optional_of(left, env)
+        } else {
+            Err(GenError::InvalidType)
+        }
+    }
+}
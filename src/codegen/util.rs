@@ -1,4 +1,4 @@
-use inkwell::values::{BasicMetadataValueEnum, PointerValue};
+use inkwell::values::{BasicMetadataValueEnum, FunctionValue, PointerValue};
 
 use super::{
     builtin::llvm_resources::LLVMResources,
@@ -28,6 +28,17 @@ impl<'ctx> CodeGen<'ctx> {
             .collect();
 
         let fn_val = fn_id.get_from(env);
+        // The caller can abort wherever the callee can, which matters for builtins that do not
+        // record the line themselves
+        if self.fallible_fns.contains(&fn_val.ink()) {
+            let cur_fn = self
+                .builder
+                .get_insert_block()
+                .unwrap()
+                .get_parent()
+                .unwrap();
+            self.mark_fallible(cur_fn);
+        }
         let ret_val = self.builder.build_call(fn_val.ink(), &param_vals, "_")?;
         let ret_ptr = ret_val
             .try_as_basic_value()
@@ -53,7 +64,17 @@ impl<'ctx> CodeGen<'ctx> {
         tid: TypeId,
         env: &mut Environment<'ctx>,
     ) -> Result<(), GenError> {
-        let (fn_val, ..) = env.create_func(Some(tid), COPY_PTR_IDENT, &[tid], tid, false)?;
+        let (fn_val, ..) = env.get_or_create_func(Some(tid), COPY_PTR_IDENT, &[tid], tid, false)?;
+        self.build_copy_ptr_body(fn_val, tid, env)
+    }
+
+    /// Builds the body of a function that increments the reference count of a value.
+    pub(super) fn build_copy_ptr_body(
+        &mut self,
+        fn_val: FunctionValue<'ctx>,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
         let entry = self.ctx.append_basic_block(fn_val, "entry");
         self.builder.position_at_end(entry);
 
@@ -120,7 +141,25 @@ impl<'ctx> CodeGen<'ctx> {
         ) -> Result<(), GenError>,
         env: &mut Environment<'ctx>,
     ) -> Result<(), GenError> {
-        let (fn_val, ..) = env.create_func(Some(tid), FREE_PTR_IDENT, &[tid], NONE_ID, false)?; // TODO: Optional return
+        let (fn_val, ..) =
+            env.get_or_create_func(Some(tid), FREE_PTR_IDENT, &[tid], NONE_ID, false)?; // TODO: Optional return
+        self.build_free_ptr_body(fn_val, tid, custom_unalloc, env)
+    }
+
+    /// Builds the body of a function that decrements the reference count of a value, releasing
+    /// it with `custom_unalloc` once nothing refers to it.
+    pub(super) fn build_free_ptr_body(
+        &mut self,
+        fn_val: FunctionValue<'ctx>,
+        tid: TypeId,
+        custom_unalloc: impl FnOnce(
+            PointerValue<'ctx>,
+            TypeId,
+            &mut Self,
+            &mut Environment<'ctx>,
+        ) -> Result<(), GenError>,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
         let entry = self.ctx.append_basic_block(fn_val, "entry");
         self.builder.position_at_end(entry);
 
@@ -193,7 +232,18 @@ impl<'ctx> CodeGen<'ctx> {
         tid: TypeId,
         env: &mut Environment<'ctx>,
     ) -> Result<(), GenError> {
-        let (fn_val, ..) = env.create_func(Some(tid), RCOUNT_IDENT, &[tid], INT_ID, false)?;
+        let (fn_val, ..) =
+            env.get_or_create_func(Some(tid), RCOUNT_IDENT, &[tid], INT_ID, false)?;
+        self.build_get_reference_count_body(fn_val, tid, env)
+    }
+
+    /// Builds the body of a function that returns the reference count of a value.
+    pub(super) fn build_get_reference_count_body(
+        &mut self,
+        fn_val: FunctionValue<'ctx>,
+        tid: TypeId,
+        env: &mut Environment<'ctx>,
+    ) -> Result<(), GenError> {
         let entry = self.ctx.append_basic_block(fn_val, "entry");
         self.builder.position_at_end(entry);
 
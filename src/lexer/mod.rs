@@ -90,18 +90,29 @@ impl<R: ReadSource> Lexer<R> {
             "def" => TokenKind::Keyword(KeywordKind::Def),
             "extern" => TokenKind::Keyword(KeywordKind::Extern),
             "struct" => TokenKind::Keyword(KeywordKind::Struct),
+            "class" => TokenKind::Keyword(KeywordKind::Class),
+            "protocol" => TokenKind::Keyword(KeywordKind::Protocol),
+            "enum" => TokenKind::Keyword(KeywordKind::Enum),
+            "match" => TokenKind::Keyword(KeywordKind::Match),
+            "case" => TokenKind::Keyword(KeywordKind::Case),
+            "const" => TokenKind::Keyword(KeywordKind::Const),
             "for" => TokenKind::Keyword(KeywordKind::For),
             "while" => TokenKind::Keyword(KeywordKind::While),
             "return" => TokenKind::Keyword(KeywordKind::Return),
+            "del" => TokenKind::Keyword(KeywordKind::Del),
             "self" => TokenKind::Keyword(KeywordKind::Self_),
+            "lambda" => TokenKind::Keyword(KeywordKind::Lambda),
 
             // TODO: Should these be keywords?
             "or" => TokenKind::Operator(OperatorKind::Or),
             "and" => TokenKind::Operator(OperatorKind::And),
             "not" => TokenKind::Operator(OperatorKind::Not),
+            "is" => TokenKind::Operator(OperatorKind::Is),
+            "in" => TokenKind::Operator(OperatorKind::In),
 
             "True" => TokenKind::Literal(LiteralKind::Boolean(true)),
             "False" => TokenKind::Literal(LiteralKind::Boolean(false)),
+            "None" => TokenKind::Literal(LiteralKind::None),
 
             _ => TokenKind::Identifier(text),
         }
@@ -178,7 +189,9 @@ impl<R: ReadSource> Iterator for Lexer<R> {
             return None;
         }
 
+        let prev_line = self.source.pos().line();
         self.consume_while(char::is_whitespace);
+        let starts_line = self.source.pos().line() != prev_line;
 
         self.start_token();
         let ch = self.source.advance();
@@ -195,7 +208,9 @@ impl<R: ReadSource> Iterator for Lexer<R> {
                 ';' => TokenKind::SemiColon,
                 ':' => TokenKind::Colon,
                 '.' => TokenKind::Period,
+                '?' => TokenKind::QuestionMark,
                 '@' => TokenKind::Operator(OperatorKind::MatMul),
+                '^' => TokenKind::Operator(OperatorKind::BitXor),
 
                 '+' => followed_by!(
                     '=' => TokenKind::Operator(OperatorKind::AddAssign),
@@ -236,8 +251,14 @@ impl<R: ReadSource> Iterator for Lexer<R> {
                     '=' => TokenKind::Operator(OperatorKind::GreaterThanOrEqual),
                     _ => TokenKind::Operator(OperatorKind::GreaterThan),
                 ),
-                '&' => followed_by!('&' => TokenKind::Operator(OperatorKind::And)),
-                '|' => followed_by!('|' => TokenKind::Operator(OperatorKind::Or)),
+                '&' => followed_by!(
+                    '&' => TokenKind::Operator(OperatorKind::And),
+                    _ => TokenKind::Operator(OperatorKind::BitAnd),
+                ),
+                '|' => followed_by!(
+                    '|' => TokenKind::Operator(OperatorKind::Or),
+                    _ => TokenKind::Operator(OperatorKind::BitOr),
+                ),
 
                 '"' => self.consume_string().unwrap(),
                 '_' => self.consume_identifier(),
@@ -257,6 +278,7 @@ impl<R: ReadSource> Iterator for Lexer<R> {
                 start: self.token_start,
                 end: self.source.pos(),
             },
+            starts_line,
         })
     }
 }
@@ -4,6 +4,8 @@ use crate::source::Position;
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
+    /// Whether a newline separates this token from the previous one.
+    pub starts_line: bool,
 }
 
 impl std::fmt::Display for Token {
@@ -42,6 +44,7 @@ pub enum TokenKind {
     Colon,
     Arrow,
     Period,
+    QuestionMark,
 
     EOF,
 
@@ -65,6 +68,7 @@ impl std::fmt::Display for TokenKind {
             TokenKind::SemiColon => write!(f, "SemiColon"),
             TokenKind::Colon => write!(f, "Colon"),
             TokenKind::Period => write!(f, "Period"),
+            TokenKind::QuestionMark => write!(f, "QuestionMark"),
             TokenKind::Arrow => write!(f, "Arrow"),
             TokenKind::EOF => write!(f, "EOF"),
             TokenKind::Invalid(t) => write!(f, "Invalid token ({})", t),
@@ -77,6 +81,7 @@ pub enum LiteralKind {
     Integer(i64),
     String(String),
     Boolean(bool),
+    None,
 }
 
 impl std::fmt::Display for LiteralKind {
@@ -86,6 +91,7 @@ impl std::fmt::Display for LiteralKind {
             LiteralKind::Integer(v) => write!(f, "{}", v),
             LiteralKind::String(v) => write!(f, "\"{}\"", v),
             LiteralKind::Boolean(v) => write!(f, "{}", v),
+            LiteralKind::None => write!(f, "None"),
         }
     }
 }
@@ -106,6 +112,9 @@ pub enum OperatorKind {
     ModuloAssign,
     Exponentiate,
     MatMul,
+    BitAnd,
+    BitOr,
+    BitXor,
 
     Equal,
     NotEqual,
@@ -116,6 +125,8 @@ pub enum OperatorKind {
     And,
     Or,
     Not,
+    Is,
+    In,
 }
 
 impl OperatorKind {
@@ -148,6 +159,9 @@ impl std::fmt::Display for OperatorKind {
             OperatorKind::ModuloAssign => write!(f, "%="),
             OperatorKind::Exponentiate => write!(f, "**"),
             OperatorKind::MatMul => write!(f, "@"),
+            OperatorKind::BitAnd => write!(f, "&"),
+            OperatorKind::BitOr => write!(f, "|"),
+            OperatorKind::BitXor => write!(f, "^"),
             OperatorKind::Equal => write!(f, "=="),
             OperatorKind::NotEqual => write!(f, "!="),
             OperatorKind::LessThan => write!(f, "<"),
@@ -157,6 +171,8 @@ impl std::fmt::Display for OperatorKind {
             OperatorKind::And => write!(f, "and"),
             OperatorKind::Or => write!(f, "or"),
             OperatorKind::Not => write!(f, "not"),
+            OperatorKind::Is => write!(f, "is"),
+            OperatorKind::In => write!(f, "in"),
         }
     }
 }
@@ -169,7 +185,15 @@ pub enum KeywordKind {
     While,
     Def,
     Return,
+    Del,
     Extern,
     Struct,
+    Class,
+    Protocol,
+    Enum,
+    Match,
+    Case,
+    Const,
     Self_,
+    Lambda,
 }
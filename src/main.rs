@@ -1,4 +1,4 @@
-use codegen::CodeGen;
+use codegen::{CodeGen, CodeGenOptions};
 use inkwell::context::Context;
 use lexer::{Lexer, Token};
 use parser::Parser;
@@ -6,6 +6,7 @@ use read_buffer::ReadBuffer;
 use source::SourceCursor;
 use std::fs::File;
 use std::io::Read;
+use std::path::PathBuf;
 use std::process::Command;
 use std::{env, fs};
 
@@ -21,11 +22,23 @@ fn main() {
     // TODO: Use clap
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
-        eprintln!("Usage: {} <file_path>", args[0]);
+        eprintln!("Usage: {} <file_path> [--checked-overflow]", args[0]);
         std::process::exit(1);
     }
 
     let file_path = &args[1];
+
+    let mut options = CodeGenOptions::default();
+    for arg in &args[2..] {
+        match arg.as_str() {
+            "--checked-overflow" => options.checked_overflow = true,
+            _ => {
+                eprintln!("Unknown option: {}", arg);
+                std::process::exit(1);
+            }
+        }
+    }
+
     let mut file = File::open(file_path).expect("Unable to open file");
     let mut input = String::new();
     file.read_to_string(&mut input)
@@ -47,7 +60,7 @@ fn main() {
     }
 
     let context = Context::create();
-    let mut gen = CodeGen::new(&context);
+    let mut gen = CodeGen::new(&context, options);
     let module = gen.gen_code_for(program);
     println!("\n---------Generated LLVM IR----------");
     println!("{}", module.to_string());
@@ -79,9 +92,12 @@ fn compile_to_executable() {
         return;
     }
 
-    // Link the object file to create an optimized executable
+    // Link the object file with the runtime library to create an optimized executable
     let clang_output = Command::new("clang")
         .arg("./out/output.o")
+        .arg(runtime_lib_path())
+        // Needed by the Rust standard library inside the runtime
+        .args(["-lpthread", "-ldl", "-lm"])
         .arg("-O3") // Optimization level 3
         .arg("-o")
         .arg("./out/output")
@@ -149,6 +165,8 @@ fn compile_to_executable() {
     // Link the object file to create an optimized executable
     let clang_output = Command::new("clang")
         .arg("./out/output_opt.o")
+        .arg(runtime_lib_path())
+        .args(["-lpthread", "-ldl", "-lm"])
         .arg("-O3") // Optimization level 3
         .arg("-o")
         .arg("./out/output_opt")
@@ -165,3 +183,15 @@ fn compile_to_executable() {
 
     println!("Executable 'output' generated successfully!");
 }
+
+/// Finds the runtime library, which `cargo build` puts next to the compiler unless
+/// `RETICULATED_RUNTIME` points somewhere else.
+fn runtime_lib_path() -> PathBuf {
+    if let Some(path) = env::var_os("RETICULATED_RUNTIME") {
+        return PathBuf::from(path);
+    }
+
+    env::current_exe()
+        .expect("Unable to find the compiler executable")
+        .with_file_name("libreticulated_runtime.a")
+}
@@ -1,19 +1,44 @@
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Expression {
     Binary(Box<Expression>, BinaryOp, Box<Expression>),
     BinaryFn(Box<Expression>, BinaryFnOp, Box<Expression>),
+    /// A chain of two or more comparisons, such as `a < b <= c`.
+    Compare(Box<Expression>, Vec<(BinaryFnOp, Expression)>),
+    /// `then if condition else otherwise`, stored as (condition, then, otherwise).
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>),
     Unary(UnaryOp, Box<Expression>),
     UnaryFn(UnaryFnOp, Box<Expression>),
-    Invoke(Box<Expression>, Vec<Expression>),
+    /// A call with its positional arguments, followed by its keyword arguments.
+    Invoke(Box<Expression>, Vec<Expression>, KeywordArgs),
     Access(Box<Expression>, String),
+    Index(Box<Expression>, Box<Expression>),
+    /// `start:stop:step` inside an index, where every part may be left out.
+    Slice(
+        Option<Box<Expression>>,
+        Option<Box<Expression>>,
+        Option<Box<Expression>>,
+    ),
+    List(Vec<Expression>),
+    Dict(Vec<(Expression, Expression)>),
+    Set(Vec<Expression>),
+    Tuple(Vec<Expression>),
+    /// `lambda x, y: body`, whose parameter types come from the function type it is used as.
+    Lambda(Vec<String>, Box<Expression>),
     Primary(Primary),
 }
 
-#[derive(Debug)]
+/// The keyword arguments of a call like `sep=", "`, in the order they were written.
+pub type KeywordArgs = Vec<(String, Expression)>;
+
+#[derive(Debug, Clone)]
 pub enum BinaryOp {
     // Logical
     And,
     Or,
+
+    // Identity
+    Is,
+    IsNot,
 }
 
 impl BinaryOp {
@@ -21,11 +46,13 @@ impl BinaryOp {
         match self {
             BinaryOp::And => "and",
             BinaryOp::Or => "or",
+            BinaryOp::Is => "is",
+            BinaryOp::IsNot => "is_not",
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum BinaryFnOp {
     // Equality
     NotEqual,
@@ -37,6 +64,15 @@ pub enum BinaryFnOp {
     Less,
     LessEqual,
 
+    // Membership
+    In,
+    NotIn,
+
+    // Bitwise
+    BitOr,
+    BitXor,
+    BitAnd,
+
     // Term
     Add,
     Subtract,
@@ -58,6 +94,10 @@ impl BinaryFnOp {
             BinaryFnOp::GreaterEqual => "__ge__",
             BinaryFnOp::Less => "__lt__",
             BinaryFnOp::LessEqual => "__le__",
+            BinaryFnOp::In | BinaryFnOp::NotIn => "__contains__",
+            BinaryFnOp::BitOr => "__or__",
+            BinaryFnOp::BitXor => "__xor__",
+            BinaryFnOp::BitAnd => "__and__",
             BinaryFnOp::Add => "__add__",
             BinaryFnOp::Subtract => "__sub__",
             BinaryFnOp::Multiply => "__mul__",
@@ -67,9 +107,14 @@ impl BinaryFnOp {
             BinaryFnOp::MatMul => "__matmul__",
         }
     }
+
+    /// Whether the function is called on the right operand, like `__contains__` for `in`.
+    pub fn is_reflected(&self) -> bool {
+        matches!(self, BinaryFnOp::In | BinaryFnOp::NotIn)
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum UnaryOp {
     Not,
 }
@@ -82,7 +127,7 @@ impl UnaryOp {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum UnaryFnOp {
     Negate,
 }
@@ -95,7 +140,7 @@ impl UnaryFnOp {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Primary {
     Identifier(String),
     Integer(i64),
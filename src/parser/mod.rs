@@ -9,9 +9,11 @@ use core::panic;
 
 mod expression;
 mod statement;
+mod types;
 
 pub use expression::*;
 pub use statement::*;
+pub use types::*;
 
 /// Parses tokens into an AST.
 ///
@@ -50,20 +52,21 @@ impl<R: ReadTokens> Parser<R> {
     }
 
     fn statement(&mut self) -> Result<Statement> {
-        // statement -> (declaration | assignment | function_declaration | extern_function
-        // | if_statement | return_statement | expression) "\n"
+        // statement -> (declaration | assignment | destructure | function_declaration
+        // | extern_function | if_statement | return_statement | expression) "\n"
 
         let next = self.tokens.peek_next();
         let pos = next.span.start;
-        let statement = match next.kind {
+        let kind = match next.kind {
             TokenKind::Identifier(_) => match self.tokens.peek(1).kind {
                 TokenKind::Colon => self.declaration().parsing_ctx("declaration", pos)?,
+                TokenKind::Comma => self.destructure().parsing_ctx("destructure", pos)?,
                 TokenKind::Operator(op) if op.is_assign_op() => {
                     self.assignment().parsing_ctx("assignment", pos)?
                 }
                 _ => self.expr_statement().parsing_ctx("expression", pos)?,
             },
-            TokenKind::Keyword(KeywordKind::Def) => Statement::FunctionDeclaration(
+            TokenKind::Keyword(KeywordKind::Def) => StatementKind::FunctionDeclaration(
                 self.fn_declaration()
                     .parsing_ctx("function declaration", pos)?,
             ),
@@ -76,87 +79,129 @@ impl<R: ReadTokens> Parser<R> {
             TokenKind::Keyword(KeywordKind::Return) => self
                 .return_statement()
                 .parsing_ctx("return statement", pos)?,
-            TokenKind::Keyword(KeywordKind::Struct) => {
-                self.struct_definition().parsing_ctx("struct", pos)?
+            TokenKind::Keyword(KeywordKind::Del) => {
+                self.del_statement().parsing_ctx("del statement", pos)?
+            }
+            TokenKind::Keyword(KeywordKind::Struct) => self
+                .struct_definition(Vec::new())
+                .parsing_ctx("struct", pos)?,
+            TokenKind::Operator(OperatorKind::MatMul) => {
+                self.decorated_struct().parsing_ctx("struct", pos)?
+            }
+            TokenKind::Keyword(KeywordKind::Class) => {
+                self.class_definition().parsing_ctx("class", pos)?
+            }
+            TokenKind::Keyword(KeywordKind::Protocol) => {
+                self.protocol_definition().parsing_ctx("protocol", pos)?
+            }
+            TokenKind::Keyword(KeywordKind::Enum) => {
+                self.enum_definition().parsing_ctx("enum", pos)?
+            }
+            TokenKind::Keyword(KeywordKind::Match) => {
+                self.match_statement().parsing_ctx("match statement", pos)?
             }
             TokenKind::Keyword(KeywordKind::While) => {
                 self.while_loop().parsing_ctx("while loop", pos)?
             }
+            TokenKind::Keyword(KeywordKind::For) => self.for_loop().parsing_ctx("for loop", pos)?,
             _ => self.expr_statement().parsing_ctx("expression", pos)?,
         };
 
         // TODO: Newline?
 
-        Ok(statement)
+        Ok(Statement { kind, pos })
     }
 
-    fn expr_statement(&mut self) -> Result<Statement> {
+    fn expr_statement(&mut self) -> Result<StatementKind> {
         // expr_statement -> expression
-        // assignment -> access "=" expression
+        // assignment -> (access | index) "=" expression
 
         let expr = self.expression()?;
         let assign_op = self.match_assign_op();
 
         if let Some((token, op)) = assign_op {
-            if let Expression::Access(expr, ident) = expr {
-                let rvalue = self.expression()?;
-                Ok(Statement::Assignment {
-                    lvalue: LValue::Access(expr, ident),
-                    op,
-                    expression: rvalue,
-                })
-            } else {
-                Err(anyhow!(
-                    "Expected identifier or access at {}, found expression ({:?}) instead.",
-                    token.span,
-                    expr
-                ))
-            }
+            let lvalue = match expr {
+                Expression::Access(expr, ident) => LValue::Access(expr, ident),
+                Expression::Index(expr, index) => LValue::Index(expr, index),
+                _ => {
+                    return Err(anyhow!(
+                        "Expected identifier, access or index at {}, found ({:?}) instead.",
+                        token.span,
+                        expr
+                    ))
+                }
+            };
+
+            let rvalue = self.expression()?;
+            Ok(StatementKind::Assignment {
+                lvalue,
+                op,
+                expression: rvalue,
+            })
         } else {
-            Ok(Statement::Expression(expr))
+            Ok(StatementKind::Expression(expr))
         }
     }
 
-    fn declaration(&mut self) -> Result<Statement> {
-        // declaration -> IDENTIFIER ":" IDENTIFIER "=" expression
+    fn declaration(&mut self) -> Result<StatementKind> {
+        // declaration -> IDENTIFIER ":" type "=" expression
 
         let identifier = self.tokens.expect_identifier()?;
 
         self.tokens.expect(TokenKind::Colon)?; // Skip the colon
 
-        let type_identifier = self.tokens.expect_identifier()?;
+        let type_identifier = self.type_expr()?;
 
         self.tokens.expect_operator(OperatorKind::Assign)?; // Skip the equals sign
 
         let expression = self.expression()?;
 
-        Ok(Statement::Declaration {
+        Ok(StatementKind::Declaration {
             identifier,
             type_identifier,
             expression,
         })
     }
 
-    fn assignment(&mut self) -> Result<Statement> {
+    fn assignment(&mut self) -> Result<StatementKind> {
         // assignment -> IDENTIFIER "=" expression
 
         let identifier = self.tokens.expect_identifier()?;
         let (_, op) = self.match_assign_op().unwrap();
         let expression = self.expression()?;
 
-        Ok(Statement::Assignment {
+        Ok(StatementKind::Assignment {
             lvalue: LValue::Ident(identifier),
             op,
             expression,
         })
     }
 
+    fn destructure(&mut self) -> Result<StatementKind> {
+        // destructure -> IDENTIFIER ("," IDENTIFIER)+ "=" expression
+
+        let mut identifiers = vec![self.tokens.expect_identifier()?];
+        while self.tokens.check(TokenKind::Comma) {
+            self.tokens.advance();
+            identifiers.push(self.tokens.expect_identifier()?);
+        }
+
+        self.tokens.expect_operator(OperatorKind::Assign)?;
+        let expression = self.expression()?;
+
+        Ok(StatementKind::Destructure {
+            identifiers,
+            expression,
+        })
+    }
+
     fn fn_declaration(&mut self) -> Result<FuncDeclaration> {
-        // function_declaration -> "fn" IDENTIFIER "(" parameters ")" "->" IDENTIFIER block
+        // function_declaration -> "def" IDENTIFIER type_params? "(" parameters ")" "->" type block
 
         self.tokens.expect_keyword(KeywordKind::Def)?;
 
         let identifier = self.tokens.expect_identifier()?;
+        let type_params = self.type_params()?;
 
         self.tokens.expect(TokenKind::OpenParenthesis)?;
 
@@ -165,12 +210,13 @@ impl<R: ReadTokens> Parser<R> {
         self.tokens.expect(TokenKind::CloseParenthesis)?;
         self.tokens.expect(TokenKind::Arrow)?;
 
-        let return_identifier = self.tokens.expect_identifier()?;
+        let return_identifier = self.type_expr()?;
 
         let body = self.block()?;
 
         Ok(FuncDeclaration::new(
             identifier,
+            type_params,
             takes_self,
             parameters,
             return_identifier,
@@ -178,8 +224,27 @@ impl<R: ReadTokens> Parser<R> {
         ))
     }
 
-    fn extern_fn_declaration(&mut self) -> Result<Statement> {
-        // extern_function -> "extern" "def" IDENTIFIER "(" extern_parameters ")" "->" IDENTIFIER
+    fn type_params(&mut self) -> Result<Vec<String>> {
+        // type_params -> "[" IDENTIFIER ("," IDENTIFIER)* "]"
+
+        if !self.tokens.check(TokenKind::OpenBracket) {
+            return Ok(Vec::new());
+        }
+        self.tokens.advance(); // Eat the open bracket
+
+        let mut params = vec![self.tokens.expect_identifier()?];
+        while self.tokens.check(TokenKind::Comma) {
+            self.tokens.advance();
+            params.push(self.tokens.expect_identifier()?);
+        }
+
+        self.tokens.expect(TokenKind::CloseBracket)?;
+
+        Ok(params)
+    }
+
+    fn extern_fn_declaration(&mut self) -> Result<StatementKind> {
+        // extern_function -> "extern" "def" IDENTIFIER "(" extern_parameters ")" "->" type
 
         self.tokens.expect_keyword(KeywordKind::Extern)?;
         self.tokens.expect_keyword(KeywordKind::Def)?;
@@ -198,9 +263,9 @@ impl<R: ReadTokens> Parser<R> {
         self.tokens.expect(TokenKind::CloseParenthesis)?;
         self.tokens.expect(TokenKind::Arrow)?;
 
-        let return_identifier = self.tokens.expect_identifier()?;
+        let return_identifier = self.type_expr()?;
 
-        Ok(Statement::ExternFunctionDeclaration {
+        Ok(StatementKind::ExternFunctionDeclaration {
             identifier,
             parameters,
             return_identifier,
@@ -209,7 +274,7 @@ impl<R: ReadTokens> Parser<R> {
 
     fn fn_parameters(&mut self) -> Result<(bool, Vec<FuncParameter>)> {
         // parameters -> ("self" ",")? (parameter ("," "*"? parameter)*)?
-        // parameter -> IDENTIFIER ":" IDENTIFIER
+        // parameter -> IDENTIFIER ":" type ("=" expression)?
 
         let mut params = Vec::new();
         let mut is_first = true;
@@ -238,16 +303,41 @@ impl<R: ReadTokens> Parser<R> {
                 false
             };
 
+            let start = self.tokens.peek_next().span.start;
             let identifier = self.tokens.expect_identifier()?;
             self.tokens.expect(TokenKind::Colon)?;
-            let type_identifier = self.tokens.expect_identifier()?;
-            params.push(FuncParameter::new(identifier, type_identifier, var_args));
+            let type_identifier = self.type_expr()?;
+
+            let default = if self.tokens.check(TokenKind::Operator(OperatorKind::Assign)) {
+                self.tokens.advance();
+                Some(self.expression()?)
+            } else {
+                None
+            };
+
+            // Like Python, every parameter after one with a default needs a default too
+            let follows_default = params
+                .last()
+                .is_some_and(|param: &FuncParameter| param.default.is_some());
+            if default.is_none() && !var_args && follows_default {
+                return Err(anyhow!(
+                    "Parameter without a default follows a parameter with a default at {}",
+                    start
+                ));
+            }
+
+            params.push(FuncParameter::new(
+                identifier,
+                type_identifier,
+                var_args,
+                default,
+            ));
         }
 
         Ok((has_self, params))
     }
 
-    fn if_statement(&mut self) -> Result<Statement> {
+    fn if_statement(&mut self) -> Result<StatementKind> {
         // if_statement -> "if" expression block ("else" "if" expression block)* ("else" block)?
 
         self.tokens.expect_keyword(KeywordKind::If)?;
@@ -276,7 +366,7 @@ impl<R: ReadTokens> Parser<R> {
             }
         }
 
-        Ok(Statement::IfStatement {
+        Ok(StatementKind::IfStatement {
             condition,
             then_branch,
             else_if_branches,
@@ -284,26 +374,181 @@ impl<R: ReadTokens> Parser<R> {
         })
     }
 
-    fn return_statement(&mut self) -> Result<Statement> {
+    fn return_statement(&mut self) -> Result<StatementKind> {
         // return_statement -> "return" expression
 
         self.tokens.expect_keyword(KeywordKind::Return)?;
         let expression = self.expression()?;
 
-        Ok(Statement::ReturnStatement { expression })
+        Ok(StatementKind::ReturnStatement { expression })
+    }
+
+    fn del_statement(&mut self) -> Result<StatementKind> {
+        // del_statement -> "del" expression "[" expression "]"
+
+        self.tokens.expect_keyword(KeywordKind::Del)?;
+        let span = self.tokens.peek_next().span.clone();
+        match self.expression()? {
+            Expression::Index(expression, index) => Ok(StatementKind::Delete {
+                expression: *expression,
+                index: *index,
+            }),
+            expr => Err(anyhow!(
+                "Expected index after del at {}, found ({:?}) instead.",
+                span,
+                expr
+            )),
+        }
+    }
+
+    fn decorated_struct(&mut self) -> Result<StatementKind> {
+        // decorated_struct -> "@" "derive" "(" IDENTIFIER ("," IDENTIFIER)* ")" struct_declaration
+
+        self.tokens
+            .expect(TokenKind::Operator(OperatorKind::MatMul))?;
+
+        let decorator = self.tokens.expect_identifier()?;
+        if decorator != "derive" {
+            return Err(anyhow!(
+                "Unknown decorator '{}', only 'derive' is supported.",
+                decorator
+            ));
+        }
+
+        self.tokens.expect(TokenKind::OpenParenthesis)?;
+        let mut derives = Vec::new();
+        loop {
+            let ident = self.tokens.expect_identifier()?;
+            let derive = Derive::from_ident(&ident).ok_or_else(|| {
+                anyhow!(
+                    "Cannot derive '{}', expected one of str, eq or hash.",
+                    ident
+                )
+            })?;
+            if derives.contains(&derive) {
+                return Err(anyhow!("'{}' is derived more than once.", ident));
+            }
+            derives.push(derive);
+
+            if !self.tokens.check(TokenKind::Comma) {
+                break;
+            }
+            self.tokens.advance();
+        }
+        self.tokens.expect(TokenKind::CloseParenthesis)?;
+
+        self.struct_definition(derives)
     }
 
-    fn struct_definition(&mut self) -> Result<Statement> {
-        // struct_declaration -> "struct" IDENTIFIER "{" (struct_field",")* "}"
-        // struct_field -> IDENTIFIER: IDENTIFIER
+    fn struct_definition(&mut self, derives: Vec<Derive>) -> Result<StatementKind> {
+        // struct_declaration -> "struct" IDENTIFIER type_params? "{" (struct_field",")* "}"
+        // struct_field -> IDENTIFIER: type
 
         self.tokens.expect_keyword(KeywordKind::Struct)?;
 
+        let identifier = self.tokens.expect_identifier()?;
+        let type_params = self.type_params()?;
+        let protocols = self.bases()?;
+        let (fields, consts, fns) = self.struct_body()?;
+
+        Ok(StatementKind::StructDefinition {
+            identifier,
+            type_params,
+            fields,
+            consts,
+            fns,
+            protocols,
+            derives,
+        })
+    }
+
+    fn class_definition(&mut self) -> Result<StatementKind> {
+        // class_declaration -> "class" IDENTIFIER bases? "{" struct_field* "}"
+
+        self.tokens.expect_keyword(KeywordKind::Class)?;
+
+        let identifier = self.tokens.expect_identifier()?;
+        let bases = self.bases()?;
+        let (fields, consts, fns) = self.struct_body()?;
+
+        Ok(StatementKind::ClassDefinition {
+            identifier,
+            bases,
+            fields,
+            consts,
+            fns,
+        })
+    }
+
+    fn bases(&mut self) -> Result<Vec<String>> {
+        // bases -> "(" IDENTIFIER ("," IDENTIFIER)* ")"
+
+        if !self.tokens.check(TokenKind::OpenParenthesis) {
+            return Ok(Vec::new());
+        }
+        self.tokens.advance();
+
+        let mut bases = vec![self.tokens.expect_identifier()?];
+        while self.tokens.check(TokenKind::Comma) {
+            self.tokens.advance();
+            bases.push(self.tokens.expect_identifier()?);
+        }
+
+        self.tokens.expect(TokenKind::CloseParenthesis)?;
+
+        Ok(bases)
+    }
+
+    fn protocol_definition(&mut self) -> Result<StatementKind> {
+        // protocol_declaration -> "protocol" IDENTIFIER "{" protocol_method* "}"
+        // protocol_method -> "def" IDENTIFIER "(" parameters ")" "->" type
+
+        self.tokens.expect_keyword(KeywordKind::Protocol)?;
+
         let identifier = self.tokens.expect_identifier()?;
 
         self.tokens.expect(TokenKind::OpenBrace)?;
 
+        let mut methods = Vec::new();
+        while !self.tokens.check(TokenKind::CloseBrace) {
+            self.tokens.expect_keyword(KeywordKind::Def)?;
+            let method_ident = self.tokens.expect_identifier()?;
+
+            self.tokens.expect(TokenKind::OpenParenthesis)?;
+            let (takes_self, params) = self.fn_parameters()?;
+            self.tokens.expect(TokenKind::CloseParenthesis)?;
+            if !takes_self {
+                return Err(anyhow!(
+                    "Protocol methods must take 'self' as the first parameter."
+                ));
+            }
+
+            self.tokens.expect(TokenKind::Arrow)?;
+            let return_identifier = self.type_expr()?;
+
+            methods.push(ProtocolMethod {
+                identifier: method_ident,
+                params,
+                return_identifier,
+            });
+        }
+
+        self.tokens.expect(TokenKind::CloseBrace)?;
+
+        Ok(StatementKind::ProtocolDefinition {
+            identifier,
+            methods,
+        })
+    }
+
+    /// Parses the fields, constants and methods between the braces of a struct or class.
+    fn struct_body(
+        &mut self,
+    ) -> Result<(Vec<StructField>, Vec<StructConst>, Vec<FuncDeclaration>)> {
+        self.tokens.expect(TokenKind::OpenBrace)?;
+
         let mut fields = Vec::new();
+        let mut consts = Vec::new();
         let mut fns = Vec::new();
 
         while !self.tokens.check(TokenKind::CloseBrace) {
@@ -314,25 +559,24 @@ impl<R: ReadTokens> Parser<R> {
                     let next_fn = self
                         .fn_declaration()
                         .parsing_ctx("function declaration", pos)?;
-                    if !next_fn.takes_self {
+                    if !next_fn.type_params.is_empty() {
                         return Err(anyhow!(
-                            "Struct methods must take 'self' as the first parameter."
+                            "Struct methods cannot have type parameters of their own."
                         ));
                     }
                     fns.push(next_fn);
                 }
+                TokenKind::Keyword(KeywordKind::Const) => {
+                    consts.push(self.struct_const().parsing_ctx("constant", pos)?);
+                    self.tokens.expect(TokenKind::Comma)?;
+                }
                 TokenKind::Identifier(_) => {
-                    let field_name = self.tokens.expect_identifier()?;
-                    self.tokens.expect(TokenKind::Colon)?; // Skip the colon
-
-                    let field_type = self.tokens.expect_identifier()?;
-                    fields.push((field_name, field_type));
-
+                    fields.push(self.struct_field()?);
                     self.tokens.expect(TokenKind::Comma)?;
                 }
                 _ => {
                     return Err(anyhow!(
-                        "Expected identifier or function declaration at {} found {}",
+                        "Expected identifier, constant or function declaration at {} found {}",
                         next.span.start,
                         next.kind
                     ))
@@ -342,14 +586,193 @@ impl<R: ReadTokens> Parser<R> {
 
         self.tokens.expect(TokenKind::CloseBrace)?;
 
-        Ok(Statement::StructDefinition {
+        Ok((fields, consts, fns))
+    }
+
+    fn struct_const(&mut self) -> Result<StructConst> {
+        // const -> "const" IDENTIFIER ":" type "=" expression
+
+        self.tokens.expect_keyword(KeywordKind::Const)?;
+
+        let identifier = self.tokens.expect_identifier()?;
+        self.tokens.expect(TokenKind::Colon)?;
+        let type_identifier = self.type_expr()?;
+        self.tokens
+            .expect(TokenKind::Operator(OperatorKind::Assign))?;
+        let value = self.expression()?;
+
+        Ok(StructConst {
             identifier,
-            fields,
-            fns,
+            type_identifier,
+            value,
         })
     }
 
-    fn while_loop(&mut self) -> Result<Statement> {
+    fn struct_field(&mut self) -> Result<StructField> {
+        // field -> IDENTIFIER ":" type ("=" expression)?
+
+        let field_name = self.tokens.expect_identifier()?;
+        self.tokens.expect(TokenKind::Colon)?; // Skip the colon
+
+        let field_type = self.type_expr()?;
+        let default = if self.tokens.check(TokenKind::Operator(OperatorKind::Assign)) {
+            self.tokens.advance();
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        Ok(StructField::new(field_name, field_type, default))
+    }
+
+    fn enum_definition(&mut self) -> Result<StatementKind> {
+        // enum_declaration -> "enum" IDENTIFIER "{" (variant ("," variant)* ","?)? "}"
+        // variant -> IDENTIFIER ("(" (field ("," field)*)? ")")?
+
+        self.tokens.expect_keyword(KeywordKind::Enum)?;
+
+        let identifier = self.tokens.expect_identifier()?;
+
+        self.tokens.expect(TokenKind::OpenBrace)?;
+
+        let mut variants = Vec::new();
+        while !self.tokens.check(TokenKind::CloseBrace) {
+            if !variants.is_empty() {
+                self.tokens.expect(TokenKind::Comma)?;
+                // Allow a trailing comma
+                if self.tokens.check(TokenKind::CloseBrace) {
+                    break;
+                }
+            }
+
+            let variant_ident = self.tokens.expect_identifier()?;
+            let mut fields = Vec::new();
+            if self.tokens.check(TokenKind::OpenParenthesis) {
+                self.tokens.advance();
+                while !self.tokens.check(TokenKind::CloseParenthesis) {
+                    if !fields.is_empty() {
+                        self.tokens.expect(TokenKind::Comma)?;
+                    }
+                    fields.push(self.struct_field()?);
+                }
+                self.tokens.advance(); // Eat the close parenthesis
+            }
+
+            variants.push(EnumVariant {
+                identifier: variant_ident,
+                fields,
+            });
+        }
+
+        self.tokens.expect(TokenKind::CloseBrace)?;
+
+        Ok(StatementKind::EnumDefinition {
+            identifier,
+            variants,
+        })
+    }
+
+    fn match_statement(&mut self) -> Result<StatementKind> {
+        // match_statement -> "match" expression "{" match_case* "}"
+        // match_case -> "case" pattern ("if" expression)? block
+
+        self.tokens.expect_keyword(KeywordKind::Match)?;
+
+        let subject = self.expression()?;
+
+        self.tokens.expect(TokenKind::OpenBrace)?;
+
+        let mut cases = Vec::new();
+        while !self.tokens.check(TokenKind::CloseBrace) {
+            self.tokens.expect_keyword(KeywordKind::Case)?;
+
+            let pattern = self.pattern()?;
+            let guard = if self.tokens.check(TokenKind::Keyword(KeywordKind::If)) {
+                self.tokens.advance();
+                Some(self.expression()?)
+            } else {
+                None
+            };
+            let body = self.block()?;
+
+            cases.push(MatchCase {
+                pattern,
+                guard,
+                body,
+            });
+        }
+
+        self.tokens.expect(TokenKind::CloseBrace)?;
+
+        Ok(StatementKind::Match { subject, cases })
+    }
+
+    fn pattern(&mut self) -> Result<Pattern> {
+        // pattern -> "_" | IDENTIFIER ("(" (pattern ("," pattern)*)? ")")? | "-"? LITERAL
+
+        let Some(next) = self.tokens.advance() else {
+            return Err(anyhow!("Unexpectedly reached end of input."));
+        };
+
+        match next.kind {
+            TokenKind::Identifier(ident) if ident == "_" => Ok(Pattern::Wildcard),
+            TokenKind::Identifier(ident) => {
+                if !self.tokens.check(TokenKind::OpenParenthesis) {
+                    return Ok(Pattern::Identifier(ident));
+                }
+                self.tokens.advance();
+
+                let mut fields = Vec::new();
+                while !self.tokens.check(TokenKind::CloseParenthesis) {
+                    if !fields.is_empty() {
+                        self.tokens.expect(TokenKind::Comma)?;
+                    }
+                    fields.push(self.pattern()?);
+                }
+                self.tokens.advance(); // Eat the close parenthesis
+
+                Ok(Pattern::Variant(ident, fields))
+            }
+            TokenKind::Operator(OperatorKind::Subtract) => {
+                let Some(next) = self.tokens.advance() else {
+                    return Err(anyhow!("Unexpectedly reached end of input."));
+                };
+                match next.kind {
+                    TokenKind::Literal(LiteralKind::Integer(value)) => {
+                        Ok(Pattern::Literal(Primary::Integer(-value)))
+                    }
+                    TokenKind::Literal(LiteralKind::Float(value)) => {
+                        Ok(Pattern::Literal(Primary::Float(-value)))
+                    }
+                    _ => Err(anyhow!(
+                        "Expected number at {} found {}",
+                        next.span.start,
+                        next.kind
+                    )),
+                }
+            }
+            TokenKind::Literal(LiteralKind::Integer(value)) => {
+                Ok(Pattern::Literal(Primary::Integer(value)))
+            }
+            TokenKind::Literal(LiteralKind::Float(value)) => {
+                Ok(Pattern::Literal(Primary::Float(value)))
+            }
+            TokenKind::Literal(LiteralKind::String(value)) => {
+                Ok(Pattern::Literal(Primary::String(value)))
+            }
+            TokenKind::Literal(LiteralKind::Boolean(value)) => {
+                Ok(Pattern::Literal(Primary::Bool(value)))
+            }
+            TokenKind::Literal(LiteralKind::None) => Ok(Pattern::Literal(Primary::None)),
+            _ => Err(anyhow!(
+                "Expected pattern at {} found {}",
+                next.span.start,
+                next.kind
+            )),
+        }
+    }
+
+    fn while_loop(&mut self) -> Result<StatementKind> {
         // while_loop -> "while" expression block
 
         self.tokens.expect_keyword(KeywordKind::While)?;
@@ -358,12 +781,81 @@ impl<R: ReadTokens> Parser<R> {
 
         let body = self.block()?;
 
-        Ok(Statement::WhileLoop {
+        Ok(StatementKind::WhileLoop {
             condition,
             block: body,
         })
     }
 
+    fn for_loop(&mut self) -> Result<StatementKind> {
+        // for_loop -> "for" IDENTIFIER "in" expression block
+
+        self.tokens.expect_keyword(KeywordKind::For)?;
+
+        let identifier = self.tokens.expect_identifier()?;
+        self.tokens.expect_operator(OperatorKind::In)?;
+        let iterable = self.expression()?;
+
+        let body = self.block()?;
+
+        Ok(StatementKind::ForLoop {
+            identifier,
+            iterable,
+            block: body,
+        })
+    }
+
+    fn type_expr(&mut self) -> Result<TypeExpr> {
+        // type -> (IDENTIFIER ("[" type ("," type)* "]")? | fn_type) "?"?
+        // fn_type -> "fn" "(" (type ("," type)*)? ")" "->" type
+
+        let identifier = self.tokens.expect_identifier()?;
+
+        let mut type_expr = if identifier == "fn" && self.tokens.check(TokenKind::OpenParenthesis) {
+            self.tokens.advance(); // Eat the open parenthesis
+
+            let mut params = Vec::new();
+            while !self.tokens.check(TokenKind::CloseParenthesis) {
+                if !params.is_empty() {
+                    self.tokens.expect(TokenKind::Comma)?;
+                }
+                params.push(self.type_expr()?);
+            }
+            self.tokens.advance(); // Eat the close parenthesis
+
+            self.tokens.expect(TokenKind::Arrow)?;
+            TypeExpr::Func(params, Box::new(self.type_expr()?))
+        } else if self.tokens.check(TokenKind::OpenBracket) {
+            self.tokens.advance(); // Eat the open bracket
+
+            let mut args = vec![self.type_expr()?];
+            while self.tokens.check(TokenKind::Comma) {
+                self.tokens.advance();
+                args.push(self.type_expr()?);
+            }
+
+            self.tokens.expect(TokenKind::CloseBracket)?;
+
+            if identifier == "Optional" {
+                if args.len() != 1 {
+                    return Err(anyhow!("Optional takes exactly one type argument."));
+                }
+                TypeExpr::Optional(Box::new(args.pop().unwrap()))
+            } else {
+                TypeExpr::Generic(identifier, args)
+            }
+        } else {
+            TypeExpr::Named(identifier)
+        };
+
+        if self.tokens.check(TokenKind::QuestionMark) {
+            self.tokens.advance();
+            type_expr = TypeExpr::Optional(Box::new(type_expr));
+        }
+
+        Ok(type_expr)
+    }
+
     fn block(&mut self) -> Result<Vec<Statement>> {
         let mut statements = Vec::new();
 
@@ -379,39 +871,145 @@ impl<R: ReadTokens> Parser<R> {
     }
 
     fn expression(&mut self) -> Result<Expression> {
-        self.logical()
+        // expression -> lambda | conditional
+
+        if self.tokens.check(TokenKind::Keyword(KeywordKind::Lambda)) {
+            self.lambda()
+        } else {
+            self.conditional()
+        }
+    }
+
+    fn lambda(&mut self) -> Result<Expression> {
+        // lambda -> "lambda" (IDENTIFIER ("," IDENTIFIER)*)? ":" expression
+
+        self.tokens.expect_keyword(KeywordKind::Lambda)?;
+
+        let mut params = Vec::new();
+        while !self.tokens.check(TokenKind::Colon) {
+            if !params.is_empty() {
+                self.tokens.expect(TokenKind::Comma)?;
+            }
+            params.push(self.tokens.expect_identifier()?);
+        }
+        self.tokens.advance(); // Eat the colon
+
+        let body = self.expression()?;
+        Ok(Expression::Lambda(params, Box::new(body)))
+    }
+
+    fn conditional(&mut self) -> Result<Expression> {
+        // conditional -> logical ("if" logical "else" conditional)?
+
+        let expr = self.logical()?;
+
+        // An `if` on a new line starts an if statement rather than continuing this expression
+        let next = self.tokens.peek_next();
+        if next.kind != TokenKind::Keyword(KeywordKind::If) || next.starts_line {
+            return Ok(expr);
+        }
+
+        self.tokens.advance(); // Eat the if
+        let condition = self.logical()?;
+        self.tokens.expect_keyword(KeywordKind::Else)?;
+        let else_expr = self.conditional()?;
+
+        Ok(Expression::Conditional(
+            Box::new(condition),
+            Box::new(expr),
+            Box::new(else_expr),
+        ))
     }
 
     fn logical(&mut self) -> Result<Expression> {
-        // logical -> equality ( ("or" | "and") equality )*
+        // logical -> identity ( ("or" | "and") identity )*
 
-        let mut expr = self.equality()?;
+        let mut expr = self.identity()?;
 
         while let Some(op) = self.match_logical_op() {
-            let right = self.equality()?;
+            let right = self.identity()?;
             expr = Expression::Binary(Box::new(expr), op, Box::new(right));
         }
 
         Ok(expr)
     }
 
-    fn equality(&mut self) -> Result<Expression> {
+    fn identity(&mut self) -> Result<Expression> {
+        // identity -> comparison ( ("is" | "is" "not") comparison )*
+
         let mut expr = self.comparison()?;
 
-        while let Some(op) = self.match_equality_op() {
+        while let Some(op) = self.match_identity_op() {
             let right = self.comparison()?;
-            expr = Expression::BinaryFn(Box::new(expr), op, Box::new(right));
+            expr = Expression::Binary(Box::new(expr), op, Box::new(right));
         }
 
         Ok(expr)
     }
 
     fn comparison(&mut self) -> Result<Expression> {
-        let mut expr = self.term()?;
+        // comparison -> bit_or ( ( "==" | "!=" | ">" | ">=" | "<" | "<=" | "in" | "not" "in" ) bit_or )*
 
+        let expr = self.bit_or()?;
+
+        let mut chain = Vec::new();
         while let Some(op) = self.match_comparison_op() {
+            chain.push((op, self.bit_or()?));
+        }
+
+        Ok(Self::comparison_chain(expr, chain))
+    }
+
+    /// Comparisons chain like Python, so `a < b < c` means `a < b and b < c` and `a < b == c`
+    /// means `a < b and b == c`.
+    fn comparison_chain(expr: Expression, mut chain: Vec<(BinaryFnOp, Expression)>) -> Expression {
+        match chain.len() {
+            0 => expr,
+            1 => {
+                let (op, right) = chain.pop().unwrap();
+                Expression::BinaryFn(Box::new(expr), op, Box::new(right))
+            }
+            _ => Expression::Compare(Box::new(expr), chain),
+        }
+    }
+
+    fn bit_or(&mut self) -> Result<Expression> {
+        // bit_or -> bit_xor ( "|" bit_xor )*
+
+        let mut expr = self.bit_xor()?;
+
+        while self.tokens.check(TokenKind::Operator(OperatorKind::BitOr)) {
+            self.tokens.advance();
+            let right = self.bit_xor()?;
+            expr = Expression::BinaryFn(Box::new(expr), BinaryFnOp::BitOr, Box::new(right));
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expression> {
+        // bit_xor -> bit_and ( "^" bit_and )*
+
+        let mut expr = self.bit_and()?;
+
+        while self.tokens.check(TokenKind::Operator(OperatorKind::BitXor)) {
+            self.tokens.advance();
+            let right = self.bit_and()?;
+            expr = Expression::BinaryFn(Box::new(expr), BinaryFnOp::BitXor, Box::new(right));
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_and(&mut self) -> Result<Expression> {
+        // bit_and -> term ( "&" term )*
+
+        let mut expr = self.term()?;
+
+        while self.tokens.check(TokenKind::Operator(OperatorKind::BitAnd)) {
+            self.tokens.advance();
             let right = self.term()?;
-            expr = Expression::BinaryFn(Box::new(expr), op, Box::new(right));
+            expr = Expression::BinaryFn(Box::new(expr), BinaryFnOp::BitAnd, Box::new(right));
         }
 
         Ok(expr)
@@ -456,51 +1054,230 @@ impl<R: ReadTokens> Parser<R> {
     }
 
     fn invoke(&mut self) -> Result<Expression> {
-        // invoke -> (invoke | access)  "(" parameter_values ")"
+        // invoke -> primary ( "(" call_arguments ")" | "." IDENTIFIER | "[" subscript "]" )*
 
-        let mut expr = self.access()?;
+        let mut expr = self.primary()?;
 
-        while self.tokens.check(TokenKind::OpenParenthesis) {
-            // allow multiple invokations in a row
-            self.tokens.advance(); // Eat open paranthesis
+        loop {
+            let next = self.tokens.peek_next();
 
-            let mut args = Vec::new();
+            // A bracket on a new line starts a list literal rather than indexing this expression
+            if next.kind == TokenKind::OpenBracket && next.starts_line {
+                return Ok(expr);
+            }
 
-            let mut first = true;
-            while self.tokens.peek_next().kind != TokenKind::CloseParenthesis {
-                if !first {
-                    self.tokens.expect(TokenKind::Comma)?;
+            match next.kind.clone() {
+                TokenKind::OpenParenthesis => {
+                    self.tokens.advance(); // Eat open paranthesis
+                    let (args, kwargs) = self.call_arguments()?;
+                    expr = Expression::Invoke(Box::new(expr), args, kwargs)
                 }
+                TokenKind::Period => {
+                    self.tokens.advance(); // eat the period
+                    let member = self.tokens.expect_identifier()?;
+                    expr = Expression::Access(Box::new(expr), member);
+                }
+                TokenKind::OpenBracket => {
+                    self.tokens.advance(); // Eat open bracket
+                    let index = self.subscript()?;
+                    expr = Expression::Index(Box::new(expr), Box::new(index));
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    /// Parses the index of an index expression up to and including the closing bracket.
+    fn subscript(&mut self) -> Result<Expression> {
+        // subscript -> slice "]" | expression ("," expression)* "]"
+        // slice -> expression? ":" expression? (":" expression?)?
+
+        let start = self.slice_bound()?;
+
+        if self.tokens.check(TokenKind::Colon) {
+            self.tokens.advance();
+            let stop = self.slice_bound()?;
+            let step = if self.tokens.check(TokenKind::Colon) {
+                self.tokens.advance();
+                self.slice_bound()?
+            } else {
+                None
+            };
+
+            self.tokens.expect(TokenKind::CloseBracket)?;
+            return Ok(Expression::Slice(start, stop, step));
+        }
+
+        let Some(index) = start else {
+            let next = self.tokens.peek_next();
+            return Err(anyhow!(
+                "Expected index at {} found {}",
+                next.span.start,
+                next.kind
+            ));
+        };
+
+        // Like Python, several indexes make a tuple, as in `Pair[int, str]`
+        if self.tokens.check(TokenKind::Comma) {
+            self.tokens.advance();
+            let mut items = vec![*index];
+            items.extend(self.arguments(TokenKind::CloseBracket)?);
+            return Ok(Expression::Tuple(items));
+        }
+
+        self.tokens.expect(TokenKind::CloseBracket)?;
+        Ok(*index)
+    }
+
+    /// Parses a bound of a slice, which is left out when the next token ends it.
+    fn slice_bound(&mut self) -> Result<Option<Box<Expression>>> {
+        if self.tokens.check(TokenKind::Colon) || self.tokens.check(TokenKind::CloseBracket) {
+            return Ok(None);
+        }
 
-                args.push(self.expression()?);
+        Ok(Some(Box::new(self.expression()?)))
+    }
 
-                first = false;
+    /// Parses comma separated expressions up to and including the closing token.
+    fn arguments(&mut self, close: TokenKind) -> Result<Vec<Expression>> {
+        // arguments -> (expression ("," expression)*)?
+
+        let mut args = Vec::new();
+
+        let mut first = true;
+        while self.tokens.peek_next().kind != close {
+            if !first {
+                self.tokens.expect(TokenKind::Comma)?;
             }
 
+            args.push(self.expression()?);
+
+            first = false;
+        }
+
+        self.tokens.expect(close)?;
+
+        Ok(args)
+    }
+
+    /// Parses the arguments of a call up to and including the closing parenthesis, where keyword
+    /// arguments must come after every positional argument.
+    fn call_arguments(&mut self) -> Result<(Vec<Expression>, KeywordArgs)> {
+        // call_arguments -> (argument ("," argument)*)?
+        // argument -> (IDENTIFIER "=")? expression
+
+        let mut args = Vec::new();
+        let mut kwargs: KeywordArgs = Vec::new();
+
+        let mut first = true;
+        while self.tokens.peek_next().kind != TokenKind::CloseParenthesis {
+            if !first {
+                self.tokens.expect(TokenKind::Comma)?;
+            }
+            first = false;
+
+            let is_keyword = matches!(self.tokens.peek_next().kind, TokenKind::Identifier(_))
+                && self.tokens.peek(1).kind == TokenKind::Operator(OperatorKind::Assign);
+            if is_keyword {
+                let start = self.tokens.peek_next().span.start;
+                let ident = self.tokens.expect_identifier()?;
+                self.tokens.expect_operator(OperatorKind::Assign)?;
+
+                if kwargs.iter().any(|(kwarg, _)| *kwarg == ident) {
+                    return Err(anyhow!(
+                        "Keyword argument '{}' repeated at {}",
+                        ident,
+                        start
+                    ));
+                }
+                kwargs.push((ident, self.expression()?));
+                continue;
+            }
+
+            if !kwargs.is_empty() {
+                let next = self.tokens.peek_next();
+                return Err(anyhow!(
+                    "Positional argument follows keyword argument at {}",
+                    next.span.start
+                ));
+            }
+            args.push(self.expression()?);
+        }
+
+        self.tokens.expect(TokenKind::CloseParenthesis)?;
+
+        Ok((args, kwargs))
+    }
+
+    fn grouping_or_tuple(&mut self) -> Result<Expression> {
+        // grouping_or_tuple -> ")" | expression ")" | expression "," arguments
+
+        if self.tokens.check(TokenKind::CloseParenthesis) {
+            self.tokens.advance();
+            return Ok(Expression::Tuple(Vec::new()));
+        }
+
+        let expr = self.expression()?;
+        if !self.tokens.check(TokenKind::Comma) {
             self.tokens.expect(TokenKind::CloseParenthesis)?;
+            return Ok(Expression::Primary(Primary::Grouping(Box::new(expr))));
+        }
+
+        // Like Python, a trailing comma makes a tuple with one element
+        self.tokens.advance();
+        let mut items = vec![expr];
+        items.extend(self.arguments(TokenKind::CloseParenthesis)?);
 
-            expr = Expression::Invoke(Box::new(expr), args)
+        Ok(Expression::Tuple(items))
+    }
+
+    fn dict_or_set(&mut self) -> Result<Expression> {
+        // dict_or_set -> "}" | dict_entries | expression ("," expression)* "}"
+
+        if self.tokens.check(TokenKind::CloseBrace) {
+            self.tokens.advance();
+            return Ok(Expression::Dict(Vec::new()));
         }
 
-        Ok(expr)
+        let first = self.expression()?;
+        if self.tokens.check(TokenKind::Colon) {
+            return Ok(Expression::Dict(self.dict_entries(first)?));
+        }
+
+        let mut items = vec![first];
+        if self.tokens.check(TokenKind::Comma) {
+            self.tokens.advance();
+            items.extend(self.arguments(TokenKind::CloseBrace)?);
+        } else {
+            self.tokens.expect(TokenKind::CloseBrace)?;
+        }
+
+        Ok(Expression::Set(items))
     }
 
-    fn access(&mut self) -> Result<Expression> {
-        // access -> (access | primary) "." IDENTIFIER
+    fn dict_entries(&mut self, first_key: Expression) -> Result<Vec<(Expression, Expression)>> {
+        // dict_entries -> expression ":" expression ("," expression ":" expression)* "}"
 
-        let mut expr = self.primary()?;
+        self.tokens.expect(TokenKind::Colon)?;
+        let mut entries = vec![(first_key, self.expression()?)];
+
+        while self.tokens.peek_next().kind != TokenKind::CloseBrace {
+            self.tokens.expect(TokenKind::Comma)?;
 
-        while self.tokens.check(TokenKind::Period) {
-            self.tokens.advance(); // eat the period
-            let member = self.tokens.expect_identifier()?;
-            expr = Expression::Access(Box::new(expr), member);
+            let key = self.expression()?;
+            self.tokens.expect(TokenKind::Colon)?;
+            let value = self.expression()?;
+            entries.push((key, value));
         }
 
-        Ok(expr)
+        self.tokens.expect(TokenKind::CloseBrace)?;
+
+        Ok(entries)
     }
 
     fn primary(&mut self) -> Result<Expression> {
-        // primary -> IDENTIFIER | LITERAL | "(" expression ")"
+        // primary -> IDENTIFIER | LITERAL | "(" grouping_or_tuple | "[" arguments "]"
+        //     | "{" dict_or_set
 
         let Some(next) = self.tokens.advance() else {
             return Err(anyhow!("Unexpectedly reached end of input."));
@@ -522,15 +1299,15 @@ impl<R: ReadTokens> Parser<R> {
             TokenKind::Literal(LiteralKind::Boolean(value)) => {
                 Ok(Expression::Primary(Primary::Bool(value)))
             }
+            TokenKind::Literal(LiteralKind::None) => Ok(Expression::Primary(Primary::None)),
             TokenKind::Identifier(identifier) => {
                 Ok(Expression::Primary(Primary::Identifier(identifier)))
             }
-            // TODO: Add null/none type
-            TokenKind::OpenParenthesis => {
-                let expr = self.expression()?;
-                self.tokens.expect(TokenKind::CloseParenthesis)?;
-                Ok(Expression::Primary(Primary::Grouping(Box::new(expr))))
+            TokenKind::OpenParenthesis => self.grouping_or_tuple(),
+            TokenKind::OpenBracket => {
+                Ok(Expression::List(self.arguments(TokenKind::CloseBracket)?))
             }
+            TokenKind::OpenBrace => self.dict_or_set(),
             _ => Err(anyhow!(
                 "Expected primary expression at {} found {}",
                 next.span.start,
@@ -553,6 +1330,20 @@ impl<R: ReadTokens> Parser<R> {
         }
     }
 
+    fn match_identity_op(&mut self) -> Option<BinaryOp> {
+        if self.tokens.peek_next().kind != TokenKind::Operator(OperatorKind::Is) {
+            return None;
+        }
+
+        self.tokens.advance();
+        if self.tokens.check(TokenKind::Operator(OperatorKind::Not)) {
+            self.tokens.advance();
+            Some(BinaryOp::IsNot)
+        } else {
+            Some(BinaryOp::Is)
+        }
+    }
+
     fn match_assign_op(&mut self) -> Option<(Token, AssignOp)> {
         match self.tokens.peek_next().kind {
             TokenKind::Operator(OperatorKind::Assign) => {
@@ -577,7 +1368,15 @@ impl<R: ReadTokens> Parser<R> {
         }
     }
 
-    fn match_equality_op(&mut self) -> Option<BinaryFnOp> {
+    fn match_comparison_op(&mut self) -> Option<BinaryFnOp> {
+        if self.tokens.check(TokenKind::Operator(OperatorKind::Not))
+            && self.tokens.peek(1).kind == TokenKind::Operator(OperatorKind::In)
+        {
+            self.tokens.advance();
+            self.tokens.advance();
+            return Some(BinaryFnOp::NotIn);
+        }
+
         match self.tokens.peek_next().kind {
             TokenKind::Operator(OperatorKind::NotEqual) => {
                 self.tokens.advance();
@@ -587,12 +1386,6 @@ impl<R: ReadTokens> Parser<R> {
                 self.tokens.advance();
                 Some(BinaryFnOp::Equal)
             }
-            _ => None,
-        }
-    }
-
-    fn match_comparison_op(&mut self) -> Option<BinaryFnOp> {
-        match self.tokens.peek_next().kind {
             TokenKind::Operator(OperatorKind::GreaterThan) => {
                 self.tokens.advance();
                 Some(BinaryFnOp::Greater)
@@ -609,6 +1402,10 @@ impl<R: ReadTokens> Parser<R> {
                 self.tokens.advance();
                 Some(BinaryFnOp::LessEqual)
             }
+            TokenKind::Operator(OperatorKind::In) => {
+                self.tokens.advance();
+                Some(BinaryFnOp::In)
+            }
             _ => None,
         }
     }
@@ -1,16 +1,25 @@
-use super::{BinaryFnOp, Expression};
+use crate::source::Position;
 
-#[derive(Debug)]
+use super::{BinaryFnOp, Expression, Primary, TypeExpr};
+
+#[derive(Debug, Clone)]
 pub enum LValue {
     Ident(String),
     Access(Box<Expression>, String),
+    Index(Box<Expression>, Box<Expression>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub pos: Position,
 }
 
-#[derive(Debug)]
-pub enum Statement {
+#[derive(Debug, Clone)]
+pub enum StatementKind {
     Declaration {
         identifier: String,
-        type_identifier: String,
+        type_identifier: TypeExpr,
         expression: Expression,
     },
     Assignment {
@@ -18,11 +27,16 @@ pub enum Statement {
         op: AssignOp,
         expression: Expression,
     },
+    /// `a, b = expression`, which unpacks a tuple into variables.
+    Destructure {
+        identifiers: Vec<String>,
+        expression: Expression,
+    },
     FunctionDeclaration(FuncDeclaration),
     ExternFunctionDeclaration {
         identifier: String,
         parameters: Vec<FuncParameter>,
-        return_identifier: String,
+        return_identifier: TypeExpr,
     },
     Expression(Expression),
     IfStatement {
@@ -34,37 +48,83 @@ pub enum Statement {
     ReturnStatement {
         expression: Expression,
     },
+    /// `del expression[index]`
+    Delete {
+        expression: Expression,
+        index: Expression,
+    },
     StructDefinition {
         identifier: String,
-        fields: Vec<(String, String)>,
+        type_params: Vec<String>,
+        fields: Vec<StructField>,
+        consts: Vec<StructConst>,
         fns: Vec<FuncDeclaration>,
+        /// The protocols the struct declares to conform to.
+        protocols: Vec<String>,
+        /// The methods to generate from the fields, given by `@derive(...)`.
+        derives: Vec<Derive>,
+    },
+    /// A struct whose methods are called through its vtable, so that a class inheriting from it
+    /// can override them.
+    ClassDefinition {
+        identifier: String,
+        /// The class inherited from, if any, along with the protocols the class declares to
+        /// conform to.
+        bases: Vec<String>,
+        fields: Vec<StructField>,
+        consts: Vec<StructConst>,
+        fns: Vec<FuncDeclaration>,
+    },
+    /// The methods a type needs to be passed where the protocol is expected.
+    ProtocolDefinition {
+        identifier: String,
+        methods: Vec<ProtocolMethod>,
+    },
+    /// A type whose values are one of several variants, each with fields of its own.
+    EnumDefinition {
+        identifier: String,
+        variants: Vec<EnumVariant>,
+    },
+    /// Runs the body of the first case whose pattern matches the subject and whose guard holds.
+    Match {
+        subject: Expression,
+        cases: Vec<MatchCase>,
     },
 
     WhileLoop {
         condition: Expression,
         block: Vec<Statement>,
     },
+    ForLoop {
+        identifier: String,
+        iterable: Expression,
+        block: Vec<Statement>,
+    },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FuncDeclaration {
     pub identifier: String,
+    /// The type parameters of a generic function, such as `T` in `def first[T](...)`.
+    pub type_params: Vec<String>,
     pub takes_self: bool,
     pub params: Vec<FuncParameter>,
-    pub return_identifier: String,
+    pub return_identifier: TypeExpr,
     pub body: Vec<Statement>,
 }
 
 impl FuncDeclaration {
     pub fn new(
         identifier: String,
+        type_params: Vec<String>,
         takes_self: bool,
         parameters: Vec<FuncParameter>,
-        return_identifier: String,
+        return_identifier: TypeExpr,
         body: Vec<Statement>,
     ) -> Self {
         Self {
             identifier,
+            type_params,
             takes_self,
             params: parameters,
             return_identifier,
@@ -73,24 +133,121 @@ impl FuncDeclaration {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct StructField {
+    pub identifier: String,
+    pub type_identifier: TypeExpr,
+    /// The value the field starts with, which lets the constructor leave it out.
+    pub default: Option<Expression>,
+}
+
+impl StructField {
+    pub fn new(identifier: String, type_identifier: TypeExpr, default: Option<Expression>) -> Self {
+        Self {
+            identifier,
+            type_identifier,
+            default,
+        }
+    }
+}
+
+/// A constant declared in the body of a struct, which is accessed on the type as in `Vec.DIM`.
+#[derive(Debug, Clone)]
+pub struct StructConst {
+    pub identifier: String,
+    pub type_identifier: TypeExpr,
+    /// Evaluated wherever the constant is used.
+    pub value: Expression,
+}
+
+/// A method required by a protocol, which is declared without a body.
+#[derive(Debug, Clone)]
+pub struct ProtocolMethod {
+    pub identifier: String,
+    pub params: Vec<FuncParameter>,
+    pub return_identifier: TypeExpr,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumVariant {
+    pub identifier: String,
+    pub fields: Vec<StructField>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchCase {
+    pub pattern: Pattern,
+    /// A condition checked after the pattern matched, which can use the names it binds.
+    pub guard: Option<Expression>,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// `_`, which matches any value.
+    Wildcard,
+    /// A name, which is a variant without fields when the subject is an enum with such a
+    /// variant, and otherwise matches any value and binds it to the name.
+    Identifier(String),
+    /// A literal, which matches values equal to it.
+    Literal(Primary),
+    /// A variant with a pattern for each of its fields, such as `Circle(r)`.
+    Variant(String, Vec<Pattern>),
+}
+
+/// A method that a struct can generate from its fields with `@derive(...)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Derive {
+    Str,
+    Eq,
+    Hash,
+}
+
+impl Derive {
+    pub fn from_ident(ident: &str) -> Option<Self> {
+        match ident {
+            "str" => Some(Derive::Str),
+            "eq" => Some(Derive::Eq),
+            "hash" => Some(Derive::Hash),
+            _ => None,
+        }
+    }
+
+    pub fn fn_name(&self) -> &str {
+        match self {
+            Derive::Str => "__str__",
+            Derive::Eq => BinaryFnOp::Equal.fn_name(),
+            Derive::Hash => "__hash__",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct FuncParameter {
     pub identifier: String,
-    pub type_identifier: String,
+    pub type_identifier: TypeExpr,
     pub var_args: bool,
+    /// The value used when a call leaves out the argument, which is evaluated at every such call.
+    pub default: Option<Expression>,
 }
 
 impl FuncParameter {
-    pub fn new(identifier: String, type_identifier: String, var_args: bool) -> Self {
+    pub fn new(
+        identifier: String,
+        type_identifier: TypeExpr,
+        var_args: bool,
+        default: Option<Expression>,
+    ) -> Self {
         Self {
             identifier,
             type_identifier,
             var_args,
+            default,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AssignOp {
     Assign,
     SubtractAssign,
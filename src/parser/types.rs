@@ -0,0 +1,40 @@
+use std::fmt;
+
+/// A type annotation, such as `int`, `Vec?` or `Optional[str]`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeExpr {
+    Named(String),
+    Generic(String, Vec<TypeExpr>),
+    Optional(Box<TypeExpr>),
+    /// A function type, such as `fn(int, int) -> int`.
+    Func(Vec<TypeExpr>, Box<TypeExpr>),
+}
+
+impl fmt::Display for TypeExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeExpr::Named(ident) => write!(f, "{}", ident),
+            TypeExpr::Generic(ident, args) => {
+                write!(f, "{}[", ident)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, "]")
+            }
+            TypeExpr::Optional(inner) => write!(f, "Optional[{}]", inner),
+            TypeExpr::Func(params, ret) => {
+                write!(f, "fn(")?;
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", param)?;
+                }
+                write!(f, ") -> {}", ret)
+            }
+        }
+    }
+}